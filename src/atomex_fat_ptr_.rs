@@ -0,0 +1,152 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ptr,
+    sync::atomic::Ordering,
+};
+use crate::{atomics_::AtomicPtr, SeqCount};
+
+/// An atomic cell for fat pointers such as `*mut [T]` and `*mut dyn Trait`.
+///
+/// The two words of the pointer are protected by a sequence word: readers
+/// retry until they observe the same even sequence before and after reading
+/// both words, while writers serialize on making the sequence odd. Loads
+/// never block writers, so publishing a slice does not need an extra boxed
+/// indirection.
+///
+/// # Example
+///
+/// ```
+/// use core::ptr;
+/// use atomex::AtomexFatPtr;
+///
+/// let mut a = [1u8, 2, 3];
+/// let mut b = [4u8, 5];
+/// let pa = ptr::slice_from_raw_parts_mut(a.as_mut_ptr(), a.len());
+/// let pb = ptr::slice_from_raw_parts_mut(b.as_mut_ptr(), b.len());
+///
+/// let cell = AtomexFatPtr::<[u8]>::new(pa);
+/// assert_eq!(cell.load().len(), 3);
+/// assert!(cell.compare_exchange(pa, pb).is_ok());
+/// assert!(cell.compare_exchange(pa, pb).is_err());
+/// assert_eq!(cell.load().len(), 2);
+/// ```
+pub struct AtomexFatPtr<T: ?Sized> {
+    seq: SeqCount,
+    words: [AtomicPtr<()>; 2],
+    _p: PhantomData<fn() -> *mut T>,
+}
+
+type Words = [*mut (); 2];
+
+impl<T: ?Sized> AtomexFatPtr<T> {
+    pub fn new(p: *mut T) -> Self {
+        let [w0, w1] = Self::split(p);
+        AtomexFatPtr {
+            seq: SeqCount::new(),
+            words: [AtomicPtr::new(w0), AtomicPtr::new(w1)],
+            _p: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> *mut T {
+        let [w0, w1] = self.words;
+        Self::join([w0.into_inner(), w1.into_inner()])
+    }
+
+    /// Loads a consistent snapshot of both words of the pointer.
+    pub fn load(&self) -> *mut T {
+        loop {
+            let s = self.seq.read_begin();
+            let words = self.read_words();
+            if !self.seq.read_retry(s) {
+                break Self::join(words);
+            }
+        }
+    }
+
+    pub fn store(&self, p: *mut T) {
+        let _ = self.swap(p);
+    }
+
+    /// Stores a pointer into the cell, returning the previous pointer.
+    pub fn swap(&self, p: *mut T) -> *mut T {
+        let w = self.seq.begin_write();
+        let prev = self.read_words();
+        self.write_words(Self::split(p));
+        self.seq.end_write(w);
+        Self::join(prev)
+    }
+
+    /// Stores `desired` if both words of the current pointer are the same as
+    /// `current`.
+    ///
+    /// The return value is a result indicating whether the new pointer was
+    /// written and containing the previous pointer.
+    pub fn compare_exchange(
+        &self,
+        current: *mut T,
+        desired: *mut T,
+    ) -> Result<*mut T, *mut T> {
+        let w = self.seq.begin_write();
+        let prev = self.read_words();
+        let is_same = prev == Self::split(current);
+        if is_same {
+            self.write_words(Self::split(desired));
+            self.seq.end_write(w);
+        } else {
+            self.seq.cancel_write(w);
+        }
+        if is_same {
+            Result::Ok(Self::join(prev))
+        } else {
+            Result::Err(Self::join(prev))
+        }
+    }
+
+    fn split(p: *mut T) -> Words {
+        const {
+            assert!(
+                mem::size_of::<*mut T>() == mem::size_of::<Words>(),
+                "AtomexFatPtr requires a pointer of two words",
+            )
+        };
+        unsafe { mem::transmute_copy::<*mut T, Words>(&p) }
+    }
+
+    fn join(words: Words) -> *mut T {
+        const {
+            assert!(
+                mem::size_of::<*mut T>() == mem::size_of::<Words>(),
+                "AtomexFatPtr requires a pointer of two words",
+            )
+        };
+        unsafe { mem::transmute_copy::<Words, *mut T>(&words) }
+    }
+
+    fn read_words(&self) -> Words {
+        [
+            self.words[0].load(Ordering::Relaxed),
+            self.words[1].load(Ordering::Relaxed),
+        ]
+    }
+
+    fn write_words(&self, words: Words) {
+        self.words[0].store(words[0], Ordering::Relaxed);
+        self.words[1].store(words[1], Ordering::Relaxed);
+    }
+}
+
+impl<T> Default for AtomexFatPtr<[T]> {
+    fn default() -> Self {
+        Self::new(ptr::slice_from_raw_parts_mut(ptr::null_mut(), 0))
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for AtomexFatPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [w0, w1] = Self::split(self.load());
+        write!(f, "AtomexFatPtr([{w0:p}, {w1:p}])")
+    }
+}
@@ -0,0 +1,141 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+};
+use crate::{atomics_::AtomicPtr, StrictOrderings, TrCmpxchOrderings};
+
+/// An atomic pointer carrying a mark in its lowest bit, which is always zero
+/// in the address of a `T` aligned to at least 2 bytes.
+///
+/// The pointer and its mark are loaded and exchanged together, so that a
+/// node of a lock-free list can be marked as deleted and, from then on, its
+/// successor can no longer be changed by an unmarked exchange.
+///
+/// # Example
+///
+/// ```
+/// use atomex::AtomexMarkedPtr;
+///
+/// let mut x = 1u32;
+/// let px: *mut u32 = &mut x;
+/// let cell = AtomexMarkedPtr::<u32>::new(px, false);
+/// assert_eq!(cell.fetch_mark(), (px, false));
+/// assert_eq!(cell.load(), (px, true));
+/// assert!(cell.compare_exchange((px, false), (px, false)).is_err());
+/// ```
+pub struct AtomexMarkedPtr<T, O = StrictOrderings>(
+    AtomicPtr<T>,
+    PhantomData<O>,
+);
+
+impl<T, O: TrCmpxchOrderings> AtomexMarkedPtr<T, O> {
+    pub const fn new(p: *mut T, mark: bool) -> Self {
+        const {
+            assert!(
+                mem::align_of::<T>() >= 2,
+                "AtomexMarkedPtr requires an alignment of at least 2",
+            )
+        };
+        let word = p.cast::<u8>().wrapping_add(mark as usize).cast::<T>();
+        AtomexMarkedPtr(AtomicPtr::new(word), PhantomData)
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> (*mut T, bool) {
+        Self::unpack(self.0.load(O::LOAD_ORDERING))
+    }
+
+    #[inline(always)]
+    pub fn pointer(&self) -> *mut T {
+        self.load().0
+    }
+
+    #[inline(always)]
+    pub fn is_marked(&self) -> bool {
+        self.load().1
+    }
+
+    pub fn store(&self, p: *mut T, mark: bool) {
+        self.0.store(Self::pack(p, mark), O::STORE_ORDERING)
+    }
+
+    /// Replaces the pointer and its mark with `new` if they are `current`.
+    ///
+    /// Returns `Ok(current)` if they were replaced, else `Err(actual)`.
+    pub fn compare_exchange(
+        &self,
+        current: (*mut T, bool),
+        new: (*mut T, bool),
+    ) -> Result<(*mut T, bool), (*mut T, bool)> {
+        self.0
+            .compare_exchange(
+                Self::pack(current.0, current.1),
+                Self::pack(new.0, new.1),
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(Self::unpack)
+            .map_err(Self::unpack)
+    }
+
+    /// Like `compare_exchange` but allowed to fail spuriously.
+    pub fn compare_exchange_weak(
+        &self,
+        current: (*mut T, bool),
+        new: (*mut T, bool),
+    ) -> Result<(*mut T, bool), (*mut T, bool)> {
+        self.0
+            .compare_exchange_weak(
+                Self::pack(current.0, current.1),
+                Self::pack(new.0, new.1),
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(Self::unpack)
+            .map_err(Self::unpack)
+    }
+
+    /// Sets the mark, keeping the pointer, and returns the previous pointer
+    /// and mark.
+    pub fn fetch_mark(&self) -> (*mut T, bool) {
+        let mut current = self.load();
+        while !current.1 {
+            match self.compare_exchange_weak(current, (current.0, true)) {
+                Result::Ok(x) => return x,
+                Result::Err(x) => current = x,
+            }
+        }
+        current
+    }
+
+    pub fn into_inner(self) -> (*mut T, bool) {
+        Self::unpack(self.0.into_inner())
+    }
+
+    #[inline(always)]
+    fn pack(p: *mut T, mark: bool) -> *mut T {
+        p.map_addr(|a| a | mark as usize)
+    }
+
+    #[inline(always)]
+    fn unpack(word: *mut T) -> (*mut T, bool) {
+        (word.map_addr(|a| a & !1), word.addr() & 1 != 0)
+    }
+}
+
+impl<T, O: TrCmpxchOrderings> Default for AtomexMarkedPtr<T, O> {
+    fn default() -> Self {
+        Self::new(core::ptr::null_mut(), false)
+    }
+}
+
+impl<T, O: TrCmpxchOrderings> fmt::Debug for AtomexMarkedPtr<T, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (p, mark) = self.load();
+        f.debug_struct("AtomexMarkedPtr")
+            .field("pointer", &p)
+            .field("mark", &mark)
+            .finish()
+    }
+}
@@ -0,0 +1,299 @@
+﻿use core::{
+    borrow::BorrowMut,
+    fmt,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+};
+use crate::{
+    atomic_cell_, CmpxchResult, PhantomAtomicPtr, StrictOrderings,
+    TrAtomicCell, TrAtomicData, TrAtomicFlags, TrCmpxchOrderings,
+};
+
+/// An atomic pointer stored as an offset relative to a base address.
+///
+/// The atomic cell holds an unsigned offset of type `V` instead of an absolute
+/// address, while the base address is supplied on construction and kept in
+/// the wrapper only. This makes the cell meaningful to every process mapping
+/// the same memory segment, even at different addresses.
+///
+/// The offset `0` encodes the null pointer, so the base address itself can
+/// not be pointed to. This usually fits well with segments starting with a
+/// header.
+///
+/// # Example
+///
+/// ```
+/// use core::{ptr::NonNull, sync::atomic::AtomicU32};
+/// use atomex::AtomexOffsetPtr;
+///
+/// let mut segment = [0u64; 4];
+/// let base = NonNull::from(&mut segment).cast::<u8>();
+/// let slot = unsafe { NonNull::new_unchecked(segment.as_mut_ptr().add(2)) };
+///
+/// let mut cell = AtomicU32::new(0);
+/// let ptr = AtomexOffsetPtr::<u64, u32, &mut AtomicU32>::new(&mut cell, base);
+///
+/// assert!(ptr.load().is_none());
+/// assert!(ptr.try_spin_init(slot).is_ok());
+/// assert_eq!(ptr.offset(), 16);
+/// assert_eq!(ptr.load(), Some(slot));
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| assert_eq!(ptr.offset(), 16));
+/// });
+/// ```
+pub struct AtomexOffsetPtr<
+    T,
+    V = usize,
+    B = <V as TrAtomicData>::AtomicCell,
+    O = StrictOrderings,
+>(B, NonNull<u8>, PhantomAtomicPtr<T>, PhantomData<V>, PhantomData<O>)
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings;
+
+// Safety: the base is only an address to resolve the offsets against, which
+// the wrapper never dereferences itself, and which stays valid in every
+// thread of the process as long as the segment is mapped.
+unsafe impl<T, V, B, O> Send for AtomexOffsetPtr<T, V, B, O>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell> + Send,
+    O: TrCmpxchOrderings,
+{}
+
+// Safety: as above.
+unsafe impl<T, V, B, O> Sync for AtomexOffsetPtr<T, V, B, O>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell> + Sync,
+    O: TrCmpxchOrderings,
+{}
+
+impl<T, V, B, O> AtomexOffsetPtr<T, V, B, O>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    /// Create an instance over an offset cell, resolving offsets against
+    /// `base`, which is the address of the segment in this process.
+    pub const fn new(cell: B, base: NonNull<u8>) -> Self {
+        AtomexOffsetPtr(cell, base, PhantomData, PhantomData, PhantomData)
+    }
+
+    #[inline(always)]
+    pub fn base(&self) -> NonNull<u8> {
+        self.1
+    }
+
+    /// The raw offset currently stored, `0` for null.
+    #[inline(always)]
+    pub fn offset(&self) -> V {
+        TrAtomicFlags::value(self)
+    }
+
+    #[inline(always)]
+    pub fn pointer(&self) -> *mut T {
+        self.offset_to_ptr(self.offset())
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> Option<NonNull<T>> {
+        NonNull::new(self.pointer())
+    }
+
+    /// Converts an offset of this segment into a pointer of this process.
+    pub fn offset_to_ptr(&self, offset: V) -> *mut T {
+        if offset == V::ZERO {
+            return ptr::null_mut();
+        }
+        let Result::Ok(offset) = TryInto::<usize>::try_into(offset) else {
+            unreachable!("[AtomexOffsetPtr::offset_to_ptr] offset exceeds usize")
+        };
+        self.1.as_ptr().wrapping_add(offset).cast()
+    }
+
+    /// Converts a pointer of this process into an offset of this segment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer is the base address, lies before the base
+    /// address or too far away from it to be represented by `V`.
+    pub fn ptr_to_offset(&self, p: *mut T) -> V {
+        if p.is_null() {
+            return V::ZERO;
+        }
+        let base = self.1.as_ptr() as usize;
+        let addr = p as usize;
+        let Option::Some(offset) = addr.checked_sub(base) else {
+            panic!("[AtomexOffsetPtr::ptr_to_offset] {p:p} before base")
+        };
+        match V::try_from(offset) {
+            Result::Ok(v) if v != V::ZERO => v,
+            _ => panic!("[AtomexOffsetPtr::ptr_to_offset] {p:p} out of range"),
+        }
+    }
+
+    #[inline(always)]
+    pub fn compare_exchange_weak(
+        &self,
+        current: *mut T,
+        desired: *mut T,
+    ) -> Result<*mut T, *mut T> {
+        // Through `TrAtomicFlags`, which wakes up the waiters on success.
+        let current = self.ptr_to_offset(current);
+        let desired = self.ptr_to_offset(desired);
+        let r: Result<_, _> = TrAtomicFlags::try_once_compare_exchange_weak(
+            self,
+            current,
+            |_| true,
+            |_| desired,
+        )
+        .into();
+        r.map(|v| self.offset_to_ptr(v))
+            .map_err(|v| self.offset_to_ptr(v))
+    }
+
+    pub fn try_once_compare_exchange_weak(
+        &self,
+        current: *mut T,
+        mut expect: impl FnMut(*mut T) -> bool,
+        mut desire: impl FnMut(*mut T) -> *mut T,
+    ) -> CmpxchResult<*mut T> {
+        TrAtomicFlags::try_once_compare_exchange_weak(
+            self,
+            self.ptr_to_offset(current),
+            |v| expect(self.offset_to_ptr(v)),
+            |v| self.ptr_to_offset(desire(self.offset_to_ptr(v))),
+        )
+        .map(|v| self.offset_to_ptr(v))
+    }
+
+    pub fn try_spin_compare_exchange_weak(
+        &self,
+        mut expect: impl FnMut(*mut T) -> bool,
+        mut desire: impl FnMut(*mut T) -> *mut T,
+    ) -> CmpxchResult<*mut T> {
+        TrAtomicFlags::try_spin_compare_exchange_weak(
+            self,
+            |v| expect(self.offset_to_ptr(v)),
+            |v| self.ptr_to_offset(desire(self.offset_to_ptr(v))),
+        )
+        .map(|v| self.offset_to_ptr(v))
+    }
+
+    /// Try to update the pointer from non-null to null.
+    ///
+    /// Returns value indicates if the reset is successful and contains the
+    /// previous stored value.
+    pub fn try_reset(&self) -> Result<NonNull<T>, *mut T> {
+        let r: Result<_, _> = TrAtomicFlags::try_spin_compare_exchange_weak(
+            self,
+            |v| v != V::ZERO,
+            |_| V::ZERO,
+        )
+        .into();
+        r.map(|v| unsafe { NonNull::new_unchecked(self.offset_to_ptr(v)) })
+            .map_err(|v| self.offset_to_ptr(v))
+    }
+
+    /// Try to update the pointer from non-null to null, after checking the
+    /// equality between the stored pointer and the argument pointer.
+    pub fn try_spin_compare_and_reset(
+        &self,
+        p: NonNull<T>,
+    ) -> Result<NonNull<T>, *mut T> {
+        let expected = self.ptr_to_offset(p.as_ptr());
+        let r: Result<_, _> = TrAtomicFlags::try_spin_compare_exchange_weak(
+            self,
+            |v| v == expected,
+            |_| V::ZERO,
+        )
+        .into();
+        r.map(|_| p).map_err(|v| self.offset_to_ptr(v))
+    }
+
+    /// Try to update the pointer from null to non-null.
+    ///
+    /// Returns value indicates if the init is successful and contains the
+    /// previous stored value.
+    pub fn try_spin_init(&self, init: NonNull<T>) -> Result<*mut T, NonNull<T>> {
+        let desired = self.ptr_to_offset(init.as_ptr());
+        let r: Result<_, _> = TrAtomicFlags::try_spin_compare_exchange_weak(
+            self,
+            |v| v == V::ZERO,
+            |_| desired,
+        )
+        .into();
+        r.map(|_| ptr::null_mut())
+            .map_err(|v| unsafe { NonNull::new_unchecked(self.offset_to_ptr(v)) })
+    }
+
+    pub fn store(&self, p: *mut T) {
+        self.0.borrow().store(self.ptr_to_offset(p), O::STORE_ORDERING);
+        atomic_cell_::fence_after::<O>();
+    }
+}
+
+impl<T, V, B, O> AsRef<<V as TrAtomicData>::AtomicCell>
+for AtomexOffsetPtr<T, V, B, O>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    fn as_ref(&self) -> &<V as TrAtomicData>::AtomicCell {
+        self.0.borrow()
+    }
+}
+
+impl<T, V, B, O> TrAtomicFlags<V, O> for AtomexOffsetPtr<T, V, B, O>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{}
+
+pub type AtomexOffsetPtrMut<'a, T, V, O> =
+    AtomexOffsetPtr<T, V, &'a mut <V as TrAtomicData>::AtomicCell, O>;
+pub type AtomexOffsetPtrOwned<T, V, O> =
+    AtomexOffsetPtr<T, V, <V as TrAtomicData>::AtomicCell, O>;
+
+impl<T, V, B, O> fmt::Debug for AtomexOffsetPtr<T, V, B, O>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base = self.1.as_ptr();
+        let offset = self.offset();
+        write!(f, "AtomexOffsetPtr({base:p} + {offset:#x})")
+    }
+}
+
+impl<T, V, B, O> fmt::Display for AtomexOffsetPtr<T, V, B, O>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Option::Some(p) = self.load() {
+            let x = p.as_ptr();
+            write!(f, "[{x:p}]")
+        } else {
+            write!(f, "[null]")
+        }
+    }
+}
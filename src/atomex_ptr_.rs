@@ -1,18 +1,36 @@
 ﻿use core::{
     borrow::BorrowMut,
+    convert::Infallible,
     fmt,
     marker::PhantomData,
     ptr::{self, NonNull},
-    sync::atomic::AtomicPtr,
 };
-use crate::{CmpxchResult, StrictOrderings, TrAtomicFlags, TrCmpxchOrderings};
+#[cfg(feature = "debug-aba")]
+use core::sync::atomic::Ordering;
+#[cfg(feature = "debug-aba")]
+use crate::{atomics_::AtomicUsize, relax};
+use crate::{
+    atomics_::AtomicPtr,
+    atomic_cell_,
+    hazard::{HazardDomain, HazardGuard},
+    CmpxchOutcome, CmpxchResult, NoBackoff, StrictOrderings,
+    TrAtomicCell, TrAtomicFlags, TrBackoff, TrCmpxchOrderings,
+};
 
 /// A wrapper around the [`AtomicPtr`](core::sync::atomic::AtomicPtr).
+///
+/// With the `debug-aba` feature, every modification made through the wrapper
+/// also advances a shadow generation, and the spinning helpers panic when
+/// their compare-exchange succeeds against a pointer that has been replaced
+/// and restored since they loaded it, whether they are called as inherent
+/// methods or through `TrAtomicFlags`. Modifications are serialized in this
+/// mode, so it is meant for testing only.
 #[derive(Debug)]
 pub struct AtomexPtr<T, B = AtomicPtr<T>, O = StrictOrderings>(
     B,
     PhantomData<AtomicPtr<T>>,
-    PhantomData<O>)
+    PhantomData<O>,
+    #[cfg(feature = "debug-aba")] AbaShadow)
 where
     B: BorrowMut<AtomicPtr<T>>,
     O: TrCmpxchOrderings;
@@ -23,7 +41,14 @@ where
     O: TrCmpxchOrderings,
 {
     pub const fn new(a: B) -> Self {
-        AtomexPtr(a, PhantomData, PhantomData)
+        #[cfg(not(feature = "debug-aba"))]
+        {
+            AtomexPtr(a, PhantomData, PhantomData)
+        }
+        #[cfg(feature = "debug-aba")]
+        {
+            AtomexPtr(a, PhantomData, PhantomData, AbaShadow::new())
+        }
     }
 
     #[inline(always)]
@@ -36,33 +61,63 @@ where
         NonNull::new(self.pointer())
     }
 
+    /// Loads the pointer and publishes it into a slot of `domain`, so that it
+    /// will not be reclaimed until the returned guard is dropped.
+    pub fn load_guarded<'d, const N: usize>(
+        &self,
+        domain: &'d HazardDomain<N>,
+    ) -> HazardGuard<'d, T> {
+        domain.protect_with(|| self.pointer())
+    }
+
     #[inline(always)]
     pub fn compare_exchange_weak(
         &self,
         current: *mut T,
         desired: *mut T,
     ) -> Result<*mut T, *mut T> {
-        self.0.borrow().compare_exchange_weak(
-            current,
-            desired,
-            O::SUCC_ORDERING,
-            O::FAIL_ORDERING,
-        )
+        self.compare_exchange_checked(current, Option::None, desired, true)
+            .0
     }
 
-    #[inline(always)]
+    /// Stores `desired` if the current pointer is `current`, for callers that
+    /// have already proven both pointers to be non-null.
+    ///
+    /// On failure the returned value is the pointer actually stored, which is
+    /// `None` if it was null.
+    pub fn compare_exchange_nonnull(
+        &self,
+        current: NonNull<T>,
+        desired: NonNull<T>,
+    ) -> Result<NonNull<T>, Option<NonNull<T>>> {
+        let (current, desired) = (current.as_ptr(), desired.as_ptr());
+        self.compare_exchange_checked(current, Option::None, desired, false)
+            .0
+            .map(|x| unsafe { NonNull::new_unchecked(x) })
+            .map_err(NonNull::new)
+    }
+
+    /// The same as `compare_exchange_nonnull` but is allowed to spuriously
+    /// fail even when the comparison succeeds.
+    pub fn compare_exchange_weak_nonnull(
+        &self,
+        current: NonNull<T>,
+        desired: NonNull<T>,
+    ) -> Result<NonNull<T>, Option<NonNull<T>>> {
+        self.compare_exchange_weak(current.as_ptr(), desired.as_ptr())
+            .map(|_| current)
+            .map_err(NonNull::new)
+    }
+
+    /// Makes a single attempt with a pointer loaded by the caller, which the
+    /// `debug-aba` check cannot tell the age of.
     pub fn try_once_compare_exchange_weak(
         &self,
         current: *mut T,
         expect: impl FnMut(*mut T) -> bool,
         desire: impl FnMut(*mut T) -> *mut T,
     ) -> CmpxchResult<*mut T> {
-        TrAtomicFlags::try_once_compare_exchange_weak(
-            self,
-            current,
-            expect,
-            desire,
-        )
+        self.try_once_checked(current, expect, desire, true)
     }
 
     #[inline(always)]
@@ -71,14 +126,66 @@ where
         expect: impl FnMut(*mut T) -> bool,
         desire: impl FnMut(*mut T) -> *mut T,
     ) -> CmpxchResult<*mut T> {
-        TrAtomicFlags::try_spin_compare_exchange_weak(self, expect, desire)
+        self.try_spin_compare_exchange_weak_with_backoff(
+            NoBackoff,
+            expect,
+            desire,
+        )
+    }
+
+    pub fn try_spin_compare_exchange_weak_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+        expect: impl FnMut(*mut T) -> bool,
+        mut desire: impl FnMut(*mut T) -> *mut T,
+    ) -> CmpxchResult<*mut T> {
+        let desire = |p| Result::<_, Infallible>::Ok(desire(p));
+        let r = self.spin_checked(true, usize::MAX, backoff, expect, desire);
+        infallible(r).result
+    }
+
+    /// Like `try_spin_compare_exchange_weak`, but also reports how many
+    /// compare-exchange attempts were made.
+    pub fn try_spin_compare_exchange_weak_counted(
+        &self,
+        expect: impl FnMut(*mut T) -> bool,
+        mut desire: impl FnMut(*mut T) -> *mut T,
+    ) -> CmpxchOutcome<*mut T> {
+        let desire = |p| Result::<_, Infallible>::Ok(desire(p));
+        let r = self.spin_checked(true, usize::MAX, NoBackoff, expect, desire);
+        infallible(r)
+    }
+
+    /// Like `try_spin_compare_exchange_weak`, but gives up after
+    /// `max_attempts` failed attempts, returning `Exhausted` with the last
+    /// pointer observed.
+    pub fn try_spin_compare_exchange_weak_bounded(
+        &self,
+        max_attempts: usize,
+        expect: impl FnMut(*mut T) -> bool,
+        mut desire: impl FnMut(*mut T) -> *mut T,
+    ) -> CmpxchResult<*mut T> {
+        let desire = |p| Result::<_, Infallible>::Ok(desire(p));
+        let n = max_attempts;
+        let r = self.spin_checked(true, n, NoBackoff, expect, desire);
+        infallible(r).result
     }
 
     /// Try to update the atomic pointer from non-null to null.
     ///
     /// Returns value indicates if the reset is successful and contains the
     /// previous stored value.
+    #[inline(always)]
     pub fn try_reset(&self) -> Result<NonNull<T>, *mut T> {
+        self.try_reset_with_backoff(NoBackoff)
+    }
+
+    /// The same as `try_reset` but waits according to `backoff` when
+    /// contended.
+    pub fn try_reset_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+    ) -> Result<NonNull<T>, *mut T> {
         fn expect_not_null<X>(p: *mut X) -> bool {
             !p.is_null()
         }
@@ -89,7 +196,11 @@ where
             unsafe { NonNull::new_unchecked(p) }
         }
         let r: Result<_, _> = self
-            .try_spin_compare_exchange_weak(expect_not_null, desire_ptr_null)
+            .try_spin_compare_exchange_weak_with_backoff(
+                backoff,
+                expect_not_null,
+                desire_ptr_null,
+            )
             .into();
         r.map(op_ptr_to_non_null)
     }
@@ -113,18 +224,288 @@ where
     ///
     /// Returns value indicates if the init is successful and contains the
     /// previous stored value.
+    #[inline(always)]
     pub fn try_spin_init(&self, init: NonNull<T>) -> Result<*mut T, NonNull<T>> {
+        self.try_spin_init_with_backoff(init, NoBackoff)
+    }
+
+    /// The same as `try_spin_init` but waits according to `backoff` when
+    /// contended.
+    pub fn try_spin_init_with_backoff(
+        &self,
+        init: NonNull<T>,
+        backoff: impl TrBackoff,
+    ) -> Result<*mut T, NonNull<T>> {
         let p = init.as_ptr();
         let expect = |x: *mut T| x.is_null();
         let desire = |_| p;
         let r: Result<_, _> = self
-            .try_spin_compare_exchange_weak(expect, desire)
+            .try_spin_compare_exchange_weak_with_backoff(backoff, expect, desire)
             .into();
         r.map_err(|x| unsafe { NonNull::new_unchecked(x) })
     }
 
+    /// Returns a mutable reference to the stored pointer, which needs no
+    /// atomic operation since the access is exclusive.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut *mut T {
+        self.0.borrow_mut().get_mut()
+    }
+
+    /// Stores `p` with the `STORE_ORDERING` of `O`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::{ptr, sync::atomic::AtomicPtr};
+    /// use atomex::{AtomexPtr, LocksOrderings};
+    ///
+    /// let mut x = 1;
+    /// let atom = AtomexPtr::<i32, AtomicPtr<i32>, LocksOrderings>::new(
+    ///     AtomicPtr::new(ptr::null_mut()));
+    /// atom.store(&mut x);
+    /// assert_eq!(atom.swap(ptr::null_mut()), &mut x as *mut i32);
+    /// assert!(atom.load().is_none());
+    /// ```
     pub fn store(&self, p: *mut T) {
-        self.0.borrow().store(p, O::SUCC_ORDERING)
+        let _ = self.modify(|a| {
+            TrAtomicCell::store(a, p, O::STORE_ORDERING);
+            Result::<(), ()>::Ok(())
+        });
+        atomic_cell_::fence_after::<O>();
+    }
+
+    /// Stores `p`, and returns the previous pointer.
+    pub fn swap(&self, p: *mut T) -> *mut T {
+        let r = self.modify(|a| {
+            let prev = TrAtomicCell::swap(a, p, O::SWAP_ORDERING);
+            Result::<_, *mut T>::Ok(prev)
+        });
+        atomic_cell_::fence_after::<O>();
+        r.unwrap_or_else(|x| x)
+    }
+
+    /// Loads the pointer, along with the stamp a later compare-exchange
+    /// against it is checked with.
+    fn load_stamped(&self) -> (*mut T, Stamp) {
+        #[cfg(feature = "debug-aba")]
+        {
+            self.3.load(self.0.borrow(), O::LOAD_ORDERING)
+        }
+        #[cfg(not(feature = "debug-aba"))]
+        {
+            (self.pointer(), ())
+        }
+    }
+
+    /// The compare-exchange every modification but `store` and `swap` goes
+    /// through, which wakes the waiters on success.
+    ///
+    /// With the `debug-aba` feature, it panics when it succeeds against a
+    /// pointer that has been replaced and restored since `stamp`, if any. On
+    /// failure, it returns the stamp of the pointer observed.
+    fn compare_exchange_checked(
+        &self,
+        current: *mut T,
+        stamp: Option<Stamp>,
+        desired: *mut T,
+        weak: bool,
+    ) -> (Result<*mut T, *mut T>, Stamp) {
+        let op = |a: &AtomicPtr<T>| {
+            let (succ, fail) = (O::SUCC_ORDERING, O::FAIL_ORDERING);
+            if weak {
+                TrAtomicCell::compare_exchange_weak(
+                    a, current, desired, succ, fail,
+                )
+            } else {
+                TrAtomicCell::compare_exchange(a, current, desired, succ, fail)
+            }
+        };
+        #[cfg(feature = "debug-aba")]
+        let (r, generation) = self.3.modify(self.0.borrow(), op);
+        #[cfg(not(feature = "debug-aba"))]
+        let (r, generation) = (op(self.0.borrow()), ());
+        if let Result::Ok(x) = r {
+            #[cfg(feature = "debug-aba")]
+            if let Option::Some(stamp) = stamp {
+                AbaShadow::check(x, stamp, generation);
+            }
+            #[cfg(not(feature = "debug-aba"))]
+            let _ = (x, stamp);
+            let atomic: &AtomicPtr<T> = self.0.borrow();
+            let addr = atomic as *const _ as usize;
+            atomic_cell_::notify_waiters(addr, O::SUCC_ORDERING);
+        }
+        (r, generation)
+    }
+
+    fn try_once_checked(
+        &self,
+        current: *mut T,
+        mut expect: impl FnMut(*mut T) -> bool,
+        mut desire: impl FnMut(*mut T) -> *mut T,
+        weak: bool,
+    ) -> CmpxchResult<*mut T> {
+        if !expect(current) {
+            return CmpxchResult::Unexpected(current);
+        }
+        let desired = desire(current);
+        let unstamped = Option::None;
+        match self.compare_exchange_checked(current, unstamped, desired, weak) {
+            (Result::Ok(x), _) => CmpxchResult::Succ(x),
+            (Result::Err(x), _) => CmpxchResult::Fail(x),
+        }
+    }
+
+    /// The loop behind every spinning compare-exchange, which gives up with
+    /// `Exhausted` after `max_attempts` failed attempts.
+    fn spin_checked<E>(
+        &self,
+        weak: bool,
+        max_attempts: usize,
+        mut backoff: impl TrBackoff,
+        mut expect: impl FnMut(*mut T) -> bool,
+        mut desire: impl FnMut(*mut T) -> Result<*mut T, E>,
+    ) -> Result<CmpxchOutcome<*mut T>, E> {
+        let (mut current, mut stamp) = self.load_stamped();
+        let mut attempts = 0;
+        let result = loop {
+            if attempts == max_attempts {
+                break CmpxchResult::Exhausted(current);
+            }
+            if !expect(current) {
+                break CmpxchResult::Unexpected(current);
+            }
+            let desired = desire(current)?;
+            attempts += 1;
+            let r = self.compare_exchange_checked(
+                current,
+                Option::Some(stamp),
+                desired,
+                weak,
+            );
+            match r {
+                (Result::Ok(x), _) => break CmpxchResult::Succ(x),
+                (Result::Err(x), s) => {
+                    current = x;
+                    stamp = s;
+                    backoff.backoff();
+                }
+            }
+        };
+        Result::Ok(CmpxchOutcome { result, attempts })
+    }
+
+    #[inline(always)]
+    fn modify<X>(
+        &self,
+        op: impl FnOnce(&AtomicPtr<T>) -> Result<X, X>,
+    ) -> Result<X, X> {
+        #[cfg(feature = "debug-aba")]
+        {
+            self.3.modify(self.0.borrow(), op).0
+        }
+        #[cfg(not(feature = "debug-aba"))]
+        {
+            op(self.0.borrow())
+        }
+    }
+}
+
+/// What a compare-exchange is checked against for ABA, i.e. the shadow
+/// generation with the `debug-aba` feature.
+#[cfg(feature = "debug-aba")]
+type Stamp = usize;
+#[cfg(not(feature = "debug-aba"))]
+type Stamp = ();
+
+fn infallible<X>(r: Result<X, Infallible>) -> X {
+    match r {
+        Result::Ok(x) => x,
+        Result::Err(e) => match e {},
+    }
+}
+
+/// The shadow generation of an `AtomexPtr`, advanced by every successful
+/// modification.
+///
+/// The generation is odd while a modification is in progress, which makes the
+/// pair of pointer and generation observed by `load` consistent.
+#[cfg(feature = "debug-aba")]
+#[derive(Debug, Default)]
+struct AbaShadow(AtomicUsize);
+
+#[cfg(feature = "debug-aba")]
+impl AbaShadow {
+    const fn new() -> Self {
+        AbaShadow(AtomicUsize::new(0))
+    }
+
+    fn load<T>(&self, atomic: &AtomicPtr<T>, order: Ordering) -> (*mut T, usize) {
+        loop {
+            let generation = self.0.load(Ordering::SeqCst);
+            if generation & 1 == 1 {
+                relax::relax();
+                continue;
+            }
+            let p = TrAtomicCell::load(atomic, order);
+            if self.0.load(Ordering::SeqCst) == generation {
+                break (p, generation);
+            }
+        }
+    }
+
+    /// Runs `op` exclusively, returning its result and the generation it was
+    /// performed at.
+    fn modify<T, X>(
+        &self,
+        atomic: &AtomicPtr<T>,
+        op: impl FnOnce(&AtomicPtr<T>) -> Result<X, X>,
+    ) -> (Result<X, X>, usize) {
+        let mut generation = self.0.load(Ordering::Relaxed);
+        loop {
+            if generation & 1 == 0 {
+                match self.0.compare_exchange_weak(
+                    generation,
+                    generation.wrapping_add(1),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Result::Ok(_) => break,
+                    Result::Err(x) => generation = x,
+                }
+            } else {
+                relax::relax();
+                generation = self.0.load(Ordering::Relaxed);
+            }
+        }
+        let r = op(atomic);
+        let next = if r.is_ok() {
+            generation.wrapping_add(2)
+        } else {
+            generation
+        };
+        self.0.store(next, Ordering::SeqCst);
+        (r, generation)
+    }
+
+    fn check<T>(p: *mut T, stamp: usize, generation: usize) {
+        if stamp != generation {
+            let n = generation.wrapping_sub(stamp) / 2;
+            panic!(
+                "[AtomexPtr] ABA detected: {p:p} has been modified {n} times \
+                since it was loaded"
+            );
+        }
+    }
+}
+
+impl<T, O> AtomexPtr<T, AtomicPtr<T>, O>
+where
+    O: TrCmpxchOrderings,
+{
+    pub fn into_inner(self) -> AtomicPtr<T> {
+        self.0
     }
 }
 
@@ -148,11 +529,112 @@ where
 pub type AtomexPtrMut<'a, T, O> = AtomexPtr<T, &'a mut AtomicPtr<T>, O>;
 pub type AtomexPtrOwned<T, O> = AtomexPtr<T, AtomicPtr<T>, O>;
 
+/// The compare-exchange methods go through the same checked helper as the
+/// inherent ones, so that none of them bypasses the `debug-aba` shadow.
 impl<T, B, O> TrAtomicFlags<*mut T, O> for AtomexPtr<T, B, O>
 where
     B: BorrowMut<AtomicPtr<T>>,
     O: TrCmpxchOrderings,
-{}
+{
+    fn try_spin_compare_exchange_weak_with_backoff<Bo, FnExpect, FnDesire>(
+        &self,
+        backoff: Bo,
+        expect: FnExpect,
+        desire: FnDesire,
+    ) -> CmpxchResult<*mut T>
+    where
+        Bo: TrBackoff,
+        FnExpect: FnMut(*mut T) -> bool,
+        FnDesire: FnMut(*mut T) -> *mut T,
+    {
+        AtomexPtr::try_spin_compare_exchange_weak_with_backoff(
+            self, backoff, expect, desire,
+        )
+    }
+
+    fn try_spin_compare_exchange_weak_counted<FnExpect, FnDesire>(
+        &self,
+        expect: FnExpect,
+        desire: FnDesire,
+    ) -> CmpxchOutcome<*mut T>
+    where
+        FnExpect: FnMut(*mut T) -> bool,
+        FnDesire: FnMut(*mut T) -> *mut T,
+    {
+        AtomexPtr::try_spin_compare_exchange_weak_counted(self, expect, desire)
+    }
+
+    fn try_spin_compare_exchange_weak_fallible<FnExpect, FnDesire, E>(
+        &self,
+        expect: FnExpect,
+        desire: FnDesire,
+    ) -> Result<CmpxchResult<*mut T>, E>
+    where
+        FnExpect: FnMut(*mut T) -> bool,
+        FnDesire: FnMut(*mut T) -> Result<*mut T, E>,
+    {
+        self.spin_checked(true, usize::MAX, NoBackoff, expect, desire)
+            .map(|outcome| outcome.result)
+    }
+
+    fn try_spin_compare_exchange_weak_bounded<FnExpect, FnDesire>(
+        &self,
+        max_attempts: usize,
+        expect: FnExpect,
+        desire: FnDesire,
+    ) -> CmpxchResult<*mut T>
+    where
+        FnExpect: FnMut(*mut T) -> bool,
+        FnDesire: FnMut(*mut T) -> *mut T,
+    {
+        AtomexPtr::try_spin_compare_exchange_weak_bounded(
+            self,
+            max_attempts,
+            expect,
+            desire,
+        )
+    }
+
+    fn try_once_compare_exchange_weak<FnExpect, FnDesire>(
+        &self,
+        current: *mut T,
+        expect: FnExpect,
+        desire: FnDesire,
+    ) -> CmpxchResult<*mut T>
+    where
+        FnExpect: FnMut(*mut T) -> bool,
+        FnDesire: FnMut(*mut T) -> *mut T,
+    {
+        self.try_once_checked(current, expect, desire, true)
+    }
+
+    fn try_spin_compare_exchange<FnExpect, FnDesire>(
+        &self,
+        expect: FnExpect,
+        mut desire: FnDesire,
+    ) -> CmpxchResult<*mut T>
+    where
+        FnExpect: FnMut(*mut T) -> bool,
+        FnDesire: FnMut(*mut T) -> *mut T,
+    {
+        let desire = |p| Result::<_, Infallible>::Ok(desire(p));
+        let r = self.spin_checked(false, usize::MAX, NoBackoff, expect, desire);
+        infallible(r).result
+    }
+
+    fn try_once_compare_exchange<FnExpect, FnDesire>(
+        &self,
+        current: *mut T,
+        expect: FnExpect,
+        desire: FnDesire,
+    ) -> CmpxchResult<*mut T>
+    where
+        FnExpect: FnMut(*mut T) -> bool,
+        FnDesire: FnMut(*mut T) -> *mut T,
+    {
+        self.try_once_checked(current, expect, desire, false)
+    }
+}
 
 impl<T, B, O> fmt::Display for AtomexPtr<T, B, O>
 where
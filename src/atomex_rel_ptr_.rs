@@ -0,0 +1,179 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+};
+use crate::{
+    atomics_::AtomicIsize, PhantomAtomicPtr, StrictOrderings,
+    TrCmpxchOrderings,
+};
+
+/// An atomic pointer stored as the distance from its own address to the
+/// pointee, for the links of data structures in a memory segment mapped by
+/// several processes.
+///
+/// Unlike `AtomexOffsetPtr`, it needs no base address, so that it can lie
+/// in the segment itself: it resolves to the same pointee in every mapping
+/// of the segment, whatever the address of the mapping. It is
+/// `#[repr(transparent)]` over an `AtomicIsize`, and is reached in the
+/// segment with `from_raw_ptr`.
+///
+/// The distance `0` encodes the null pointer, so the pointer can not point
+/// to itself. A pointer that is not null must not be moved, or it would
+/// point elsewhere.
+///
+/// # Example
+///
+/// ```
+/// use atomex::AtomexRelPtr;
+///
+/// let mut segment = [0isize; 4];
+/// let base = segment.as_mut_ptr();
+/// // Safety: the segment outlives the pointer, and its first word is only
+/// // accessed through it.
+/// let head = unsafe { AtomexRelPtr::<isize>::from_raw_ptr(base) };
+/// head.store(base.wrapping_add(2));
+///
+/// // Another mapping of the same segment, at another address.
+/// let mut mapping = segment;
+/// let base = mapping.as_mut_ptr();
+/// // Safety: as above.
+/// let head = unsafe { AtomexRelPtr::<isize>::from_raw_ptr(base) };
+/// assert_eq!(head.pointer(), base.wrapping_add(2));
+/// ```
+#[repr(transparent)]
+pub struct AtomexRelPtr<T, O = StrictOrderings>(
+    AtomicIsize,
+    PhantomAtomicPtr<T>,
+    PhantomData<O>,
+)
+where
+    O: TrCmpxchOrderings;
+
+impl<T, O> AtomexRelPtr<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    /// Create a null pointer.
+    pub const fn new() -> Self {
+        AtomexRelPtr(AtomicIsize::new(0), PhantomData, PhantomData)
+    }
+
+    /// The pointer over the distance at `ptr`, such as in a memory segment
+    /// mapped by several processes.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be aligned for an `AtomicIsize`, and valid for reads and
+    /// writes for `'a`, during which the distance is only accessed
+    /// atomically.
+    pub unsafe fn from_raw_ptr<'a>(ptr: *mut isize) -> &'a Self {
+        debug_assert!(
+            ptr.cast::<Self>().is_aligned(),
+            "[AtomexRelPtr::from_raw_ptr] misaligned pointer",
+        );
+        // Safety: the pointer is transparent over an `AtomicIsize`, which
+        // has the layout of `isize` but for the alignment, and the pointer
+        // is valid as required.
+        unsafe { &*ptr.cast::<Self>() }
+    }
+
+    /// The raw distance currently stored, `0` for null.
+    #[inline(always)]
+    pub fn distance(&self) -> isize {
+        self.0.load(O::LOAD_ORDERING)
+    }
+
+    #[inline(always)]
+    pub fn pointer(&self) -> *mut T {
+        self.decode(self.distance())
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> Option<NonNull<T>> {
+        NonNull::new(self.pointer())
+    }
+
+    #[inline(always)]
+    pub fn store(&self, p: *mut T) {
+        self.0.store(self.encode(p), O::STORE_ORDERING)
+    }
+
+    #[inline(always)]
+    pub fn swap(&self, p: *mut T) -> *mut T {
+        self.decode(self.0.swap(self.encode(p), O::SWAP_ORDERING))
+    }
+
+    #[inline(always)]
+    pub fn compare_exchange(
+        &self,
+        current: *mut T,
+        desired: *mut T,
+    ) -> Result<*mut T, *mut T> {
+        self.0
+            .compare_exchange(
+                self.encode(current),
+                self.encode(desired),
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(|d| self.decode(d))
+            .map_err(|d| self.decode(d))
+    }
+
+    #[inline(always)]
+    pub fn compare_exchange_weak(
+        &self,
+        current: *mut T,
+        desired: *mut T,
+    ) -> Result<*mut T, *mut T> {
+        self.0
+            .compare_exchange_weak(
+                self.encode(current),
+                self.encode(desired),
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(|d| self.decode(d))
+            .map_err(|d| self.decode(d))
+    }
+
+    #[inline(always)]
+    fn addr(&self) -> isize {
+        self as *const Self as isize
+    }
+
+    /// Converts a pointer of this process into its distance from here.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer is the address of this pointer.
+    fn encode(&self, p: *mut T) -> isize {
+        if p.is_null() {
+            return 0;
+        }
+        let d = (p as isize).wrapping_sub(self.addr());
+        assert!(d != 0, "[AtomexRelPtr::encode] {p:p} points to itself");
+        d
+    }
+
+    /// Converts a distance from here into a pointer of this process.
+    fn decode(&self, d: isize) -> *mut T {
+        if d == 0 {
+            return ptr::null_mut();
+        }
+        self.addr().wrapping_add(d) as *mut T
+    }
+}
+
+impl<T, O: TrCmpxchOrderings> Default for AtomexRelPtr<T, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, O: TrCmpxchOrderings> fmt::Debug for AtomexRelPtr<T, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomexRelPtr").field(&self.pointer()).finish()
+    }
+}
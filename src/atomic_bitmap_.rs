@@ -0,0 +1,202 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+};
+use crate::{atomics_::AtomicUsize, AcqRelOrderings, TrCmpxchOrderings};
+
+/// A set of `N * usize::BITS` bits, each set, cleared and tested atomically.
+///
+/// Every operation on a single bit is a single atomic operation on its word.
+/// The operations spanning several words, such as searching or iterating,
+/// look at each word atomically but not at all the words at once.
+///
+/// # Example
+///
+/// ```
+/// use atomex::AtomicBitmap;
+///
+/// let dirty = AtomicBitmap::<2>::new();
+/// assert!(!dirty.set(3));
+/// assert!(dirty.set(3));
+/// dirty.set(70);
+/// assert!(dirty.test(70));
+/// assert_eq!(dirty.find_first_set(), Some(3));
+/// assert_eq!(dirty.find_first_zero(), Some(0));
+/// assert_eq!(dirty.claim_first_zero(), Some(0));
+/// assert_eq!(dirty.find_first_zero(), Some(1));
+/// assert_eq!(dirty.iter().collect::<Vec<_>>(), [0, 3, 70]);
+/// assert!(dirty.clear(3));
+/// assert_eq!(dirty.count_ones(), 2);
+/// dirty.clear_all();
+/// assert_eq!(dirty.count_ones(), 0);
+/// ```
+pub struct AtomicBitmap<const N: usize, O = AcqRelOrderings> {
+    words: [AtomicUsize; N],
+    _orderings: PhantomData<O>,
+}
+
+impl<const N: usize, O: TrCmpxchOrderings> AtomicBitmap<N, O> {
+    const BITS: usize = usize::BITS as usize;
+
+    /// Creates a bitmap with all the bits clear.
+    pub const fn new() -> Self {
+        AtomicBitmap {
+            words: [const { AtomicUsize::new(0) }; N],
+            _orderings: PhantomData,
+        }
+    }
+
+    /// The number of bits.
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        N * Self::BITS
+    }
+
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Sets the bit at `index`, and returns whether it was set already.
+    pub fn set(&self, index: usize) -> bool {
+        let (word, mask) = self.locate(index);
+        word.fetch_or(mask, O::SWAP_ORDERING) & mask != 0
+    }
+
+    /// Clears the bit at `index`, and returns whether it was set.
+    pub fn clear(&self, index: usize) -> bool {
+        let (word, mask) = self.locate(index);
+        word.fetch_and(!mask, O::SWAP_ORDERING) & mask != 0
+    }
+
+    /// Returns `true` if the bit at `index` is set.
+    pub fn test(&self, index: usize) -> bool {
+        let (word, mask) = self.locate(index);
+        word.load(O::LOAD_ORDERING) & mask != 0
+    }
+
+    /// The index of the first set bit, or `None` if none is.
+    pub fn find_first_set(&self) -> Option<usize> {
+        self.find_first(|w| w)
+    }
+
+    /// The index of the first clear bit, or `None` if none is.
+    pub fn find_first_zero(&self) -> Option<usize> {
+        self.find_first(|w| !w)
+    }
+
+    /// Sets the first clear bit, and returns its index, or `None` if all the
+    /// bits are set.
+    ///
+    /// Unlike `find_first_zero` followed by `set`, no other thread can set
+    /// the same bit in between, so the bit is claimed by the caller alone.
+    pub fn claim_first_zero(&self) -> Option<usize> {
+        self.words.iter().enumerate().find_map(|(k, w)| {
+            claim_lowest_zero::<O>(w).map(|bit| k * Self::BITS + bit)
+        })
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|w| w.load(O::LOAD_ORDERING).count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterates over the indices of the set bits, in increasing order.
+    ///
+    /// Each word is loaded once, when the iteration reaches it.
+    pub fn iter(&self) -> SetBits<'_, N, O> {
+        SetBits {
+            bitmap: self,
+            k: 0,
+            word: 0,
+        }
+    }
+
+    /// Clears all the bits, one word at a time.
+    pub fn clear_all(&self) {
+        for w in self.words.iter() {
+            w.store(0, O::STORE_ORDERING);
+        }
+    }
+
+    fn locate(&self, index: usize) -> (&AtomicUsize, usize) {
+        assert!(
+            index < self.len(),
+            "[AtomicBitmap] index {index} out of range",
+        );
+        (&self.words[index / Self::BITS], 1 << (index % Self::BITS))
+    }
+
+    fn find_first(&self, f: impl Fn(usize) -> usize) -> Option<usize> {
+        self.words.iter().enumerate().find_map(|(k, w)| {
+            let bits = f(w.load(O::LOAD_ORDERING));
+            let bit = bits.trailing_zeros() as usize;
+            (bits != 0).then_some(k * Self::BITS + bit)
+        })
+    }
+}
+
+impl<const N: usize, O: TrCmpxchOrderings> Default for AtomicBitmap<N, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, O: TrCmpxchOrderings> fmt::Debug for AtomicBitmap<N, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, const N: usize, O: TrCmpxchOrderings> IntoIterator
+    for &'a AtomicBitmap<N, O>
+{
+    type Item = usize;
+    type IntoIter = SetBits<'a, N, O>;
+
+    fn into_iter(self) -> SetBits<'a, N, O> {
+        self.iter()
+    }
+}
+
+/// Sets the lowest clear bit of `word` in a compare-exchange loop, and
+/// returns its index, or `None` if all the bits of `word` are set.
+pub(crate) fn claim_lowest_zero<O: TrCmpxchOrderings>(
+    word: &AtomicUsize,
+) -> Option<usize> {
+    // `w + 1` turns the lowest clear bit of `w` on and the bits below it off.
+    word.fetch_update(O::SUCC_ORDERING, O::FAIL_ORDERING, |w| {
+        (w != !0).then(|| w | (w + 1))
+    })
+    .ok()
+    .map(|w| (!w).trailing_zeros() as usize)
+}
+
+/// The iterator over the set bits of an `AtomicBitmap`.
+pub struct SetBits<'a, const N: usize, O = AcqRelOrderings> {
+    bitmap: &'a AtomicBitmap<N, O>,
+    /// The index of the next word to load.
+    k: usize,
+    /// The bits of the current word not yielded yet.
+    word: usize,
+}
+
+impl<const N: usize, O: TrCmpxchOrderings> Iterator for SetBits<'_, N, O> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            if self.k == N {
+                return Option::None;
+            }
+            self.word = self.bitmap.words[self.k].load(O::LOAD_ORDERING);
+            self.k += 1;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        Option::Some((self.k - 1) * AtomicBitmap::<N, O>::BITS + bit)
+    }
+}
@@ -1,5 +1,5 @@
-﻿use core::{marker::PhantomData, sync::atomic::*};
-use crate::fetch;
+﻿use core::{marker::PhantomData, sync::atomic::{fence, Ordering}};
+use crate::{atomics_::*, fetch};
 
 pub trait TrAtomicCell {
     /// The underlying primitive value type
@@ -60,6 +60,16 @@ pub trait TrAtomicCell {
     ) -> Result<Self::Value, Self::Value>;
 }
 
+/// The cells giving access to their value in place through an exclusive
+/// reference.
+pub trait TrAtomicCellMut: TrAtomicCell {
+    /// Returns a mutable reference to the underlying value.
+    ///
+    /// This is safe because the mutable reference guarantees that no other
+    /// threads are concurrently accessing the atomic data.
+    fn get_mut(&mut self) -> &mut Self::Value;
+}
+
 pub trait TrAtomicData {
     type AtomicCell: TrAtomicCell<Value = Self>;
 }
@@ -88,6 +98,51 @@ pub trait TrCmpxchOrderings: Unpin {
     const SUCC_ORDERING: Ordering;
     const FAIL_ORDERING: Ordering;
     const LOAD_ORDERING: Ordering;
+
+    /// The ordering of plain stores, derived from `SUCC_ORDERING` by default
+    /// with the acquire part turned into release, since a store cannot
+    /// acquire.
+    const STORE_ORDERING: Ordering = store_ordering_of(Self::SUCC_ORDERING);
+
+    /// The ordering of unconditional read-modify-write operations, such as
+    /// swaps and fetch operations.
+    const SWAP_ORDERING: Ordering = Self::SUCC_ORDERING;
+
+    /// The ordering of a fence issued right after every store or swap, if
+    /// any.
+    const FENCE_ORDERING: Option<Ordering> = Option::None;
+}
+
+/// Maps an ordering for a read-modify-write operation to the closest one
+/// that is valid for a store.
+pub const fn store_ordering_of(order: Ordering) -> Ordering {
+    match order {
+        Ordering::Acquire | Ordering::AcqRel => Ordering::Release,
+        Ordering::Relaxed => Ordering::Relaxed,
+        Ordering::Release => Ordering::Release,
+        _ => Ordering::SeqCst,
+    }
+}
+
+/// Issues the `FENCE_ORDERING` fence of `O`, if any.
+#[inline(always)]
+pub(crate) fn fence_after<O: TrCmpxchOrderings>() {
+    if let Option::Some(order) = O::FENCE_ORDERING {
+        fence(order);
+    }
+}
+
+/// Wakes up the waiters on the atomic at `addr`, after it was modified with
+/// `order`.
+#[inline(always)]
+pub(crate) fn notify_waiters(addr: usize, order: Ordering) {
+    #[cfg(feature = "async")]
+    crate::wait_table_::notify(addr, order);
+    #[cfg(feature = "futex")]
+    crate::futex_::notify(addr, order);
+    #[cfg(feature = "wfe")]
+    crate::backoff_::send_event();
+    let _ = (addr, order);
 }
 
 /// Provide the most strict orderings with cost of higher overhead.
@@ -111,44 +166,66 @@ impl TrCmpxchOrderings for LocksOrderings {
     const LOAD_ORDERING: Ordering = Ordering::Acquire;
 }
 
+/// Provide only atomicity without any synchronization, for values that do
+/// not guard other memory, such as statistics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RelaxedOrderings;
+
+impl TrCmpxchOrderings for RelaxedOrderings {
+    const SUCC_ORDERING: Ordering = Ordering::Relaxed;
+    const FAIL_ORDERING: Ordering = Ordering::Relaxed;
+    const LOAD_ORDERING: Ordering = Ordering::Relaxed;
+}
+
+/// Provide both acquire and release semantics on successful updates, for
+/// values that both publish and consume other memory.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AcqRelOrderings;
+
+impl TrCmpxchOrderings for AcqRelOrderings {
+    const SUCC_ORDERING: Ordering = Ordering::AcqRel;
+    const FAIL_ORDERING: Ordering = Ordering::Acquire;
+    const LOAD_ORDERING: Ordering = Ordering::Acquire;
+}
+
 pub type PhantomAtomicPtr<T> = PhantomData<AtomicPtr<T>>;
 
-#[cfg(target_has_atomic = "8")]
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
 impl TrAtomicData for i8 {
     type AtomicCell = AtomicI8;
 }
 
-#[cfg(target_has_atomic = "8")]
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
 impl TrAtomicData for u8 {
     type AtomicCell = AtomicU8;
 }
 
-#[cfg(target_has_atomic = "16")]
+#[cfg(any(target_has_atomic = "16", feature = "portable-atomic"))]
 impl TrAtomicData for i16 {
     type AtomicCell = AtomicI16;
 }
 
-#[cfg(target_has_atomic = "16")]
+#[cfg(any(target_has_atomic = "16", feature = "portable-atomic"))]
 impl TrAtomicData for u16 {
     type AtomicCell = AtomicU16;
 }
 
-#[cfg(target_has_atomic = "32")]
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
 impl TrAtomicData for i32 {
     type AtomicCell = AtomicI32;
 }
 
-#[cfg(target_has_atomic = "32")]
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
 impl TrAtomicData for u32 {
     type AtomicCell = AtomicU32;
 }
 
-#[cfg(target_has_atomic = "64")]
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
 impl TrAtomicData for i64 {
     type AtomicCell = AtomicI64;
 }
 
-#[cfg(target_has_atomic = "64")]
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
 impl TrAtomicData for u64 {
     type AtomicCell = AtomicU64;
 }
@@ -163,22 +240,45 @@ impl TrAtomicData for u64 {
 //     type AtomicCell = AtomicU128;
 // }
 
+#[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
 impl TrAtomicData for isize {
     type AtomicCell = AtomicIsize;
 }
 
+#[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
 impl TrAtomicData for usize {
     type AtomicCell = AtomicUsize;
 }
 
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
 impl TrAtomicData for bool {
     type AtomicCell = AtomicBool;
 }
 
+#[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
 impl<T> TrAtomicData for *mut T {
     type AtomicCell = AtomicPtr<T>;
 }
 
+/// Records an operation on `$cell` with the `audit` feature, else nothing.
+macro_rules! audit {
+    ($cell:expr, $op:ident, $order:expr) => {
+        audit!($cell, $op, $order, Option::None)
+    };
+    ($cell:expr, $op:ident, $order:expr, $fail:expr) => {
+        #[cfg(feature = "audit")]
+        $crate::audit::record(
+            $cell,
+            $crate::audit::AuditOp::$op,
+            $order,
+            $fail,
+        );
+        #[cfg(not(feature = "audit"))]
+        let _: (_, Ordering, Option<Ordering>) = ($cell, $order, $fail);
+    };
+}
+pub(crate) use audit;
+
 macro_rules! impl_atomic {
     ($atomic:ident : $primitive:ty ; $( $traits:tt ),*) => {
         impl_atomic!(__impl atomic $atomic : $primitive);
@@ -194,6 +294,10 @@ macro_rules! impl_atomic {
 
             impl_atomic!(__impl atomic_methods $atomic);
         }
+
+        impl<$param> TrAtomicCellMut for $atomic <$param> {
+            impl_atomic!(__impl get_mut $atomic);
+        }
     };
 
     (__impl atomic $atomic:ident : $primitive:ty) => {
@@ -202,6 +306,17 @@ macro_rules! impl_atomic {
 
             impl_atomic!(__impl atomic_methods $atomic);
         }
+
+        impl TrAtomicCellMut for $atomic {
+            impl_atomic!(__impl get_mut $atomic);
+        }
+    };
+
+    (__impl get_mut $atomic:ident) => {
+        #[inline(always)]
+        fn get_mut(&mut self) -> &mut Self::Value {
+            Self::get_mut(self)
+        }
     };
 
     (__impl atomic_methods $atomic:ident) => {
@@ -217,17 +332,22 @@ macro_rules! impl_atomic {
 
         #[inline(always)]
         fn load(&self, order: Ordering) -> Self::Value {
-            Self::load(self, order)
+            let v = Self::load(self, order);
+            audit!(self, Load, order);
+            v
         }
 
         #[inline(always)]
         fn store(&self, val: Self::Value, order: Ordering) {
-            Self::store(self, val, order)
+            Self::store(self, val, order);
+            audit!(self, Store, order);
         }
 
         #[inline(always)]
         fn swap(&self, val: Self::Value, order: Ordering) -> Self::Value {
-            Self::swap(self, val, order)
+            let v = Self::swap(self, val, order);
+            audit!(self, Swap, order);
+            v
         }
 
         #[inline(always)]
@@ -238,7 +358,10 @@ macro_rules! impl_atomic {
             success: Ordering,
             failure: Ordering,
         ) -> Result<Self::Value, Self::Value> {
-            Self::compare_exchange(self, current, desired, success, failure)
+            let r = Self::compare_exchange(
+                self, current, desired, success, failure);
+            audit!(self, CompareExchange, success, Option::Some(failure));
+            r
         }
 
         #[inline(always)]
@@ -249,7 +372,10 @@ macro_rules! impl_atomic {
             success: Ordering,
             failure: Ordering,
         ) -> Result<Self::Value, Self::Value> {
-            Self::compare_exchange_weak(self, current, desired, success, failure)
+            let r = Self::compare_exchange_weak(
+                self, current, desired, success, failure);
+            audit!(self, CompareExchangeWeak, success, Option::Some(failure));
+            r
         }
     };
 
@@ -261,7 +387,9 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_and(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_and(self, val, order)
+                let v = Self::fetch_and(self, val, order);
+                audit!(self, FetchAnd, order);
+                v
             }
         }
 
@@ -270,7 +398,9 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_nand(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_nand(self, val, order)
+                let v = Self::fetch_nand(self, val, order);
+                audit!(self, FetchNand, order);
+                v
             }
         }
 
@@ -279,7 +409,9 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_or(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_or(self, val, order)
+                let v = Self::fetch_or(self, val, order);
+                audit!(self, FetchOr, order);
+                v
             }
         }
 
@@ -288,7 +420,9 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_xor(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_xor(self, val, order)
+                let v = Self::fetch_xor(self, val, order);
+                audit!(self, FetchXor, order);
+                v
             }
         }
     };
@@ -301,7 +435,9 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_add(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_add(self, val, order)
+                let v = Self::fetch_add(self, val, order);
+                audit!(self, FetchAdd, order);
+                v
             }
         }
 
@@ -310,7 +446,9 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_sub(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_sub(self, val, order)
+                let v = Self::fetch_sub(self, val, order);
+                audit!(self, FetchSub, order);
+                v
             }
         }
 
@@ -326,7 +464,9 @@ macro_rules! impl_atomic {
             ) -> Result<Self::Value, Self::Value>
             where
                 F: FnMut(Self::Value) -> Option<Self::Value> {
-                Self::fetch_update(self, fetch_order, set_order, f)
+                let r = Self::fetch_update(self, fetch_order, set_order, f);
+                audit!(self, FetchUpdate, set_order, Option::Some(fetch_order));
+                r
             }
         }
 
@@ -335,7 +475,9 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_max(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_max(self, val, order)
+                let v = Self::fetch_max(self, val, order);
+                audit!(self, FetchMax, order);
+                v
             }
         }
 
@@ -344,42 +486,51 @@ macro_rules! impl_atomic {
 
             #[inline(always)]
             fn fetch_min(&self, val: Self::Value, order: Ordering) -> Self::Value {
-                Self::fetch_min(self, val, order)
+                let v = Self::fetch_min(self, val, order);
+                audit!(self, FetchMin, order);
+                v
             }
         }
     };
 }
 
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
 impl_atomic!(AtomicBool: bool; bitwise);
+
+#[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
 impl_atomic!(AtomicIsize: isize; bitwise, numops);
+
+#[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
 impl_atomic!(AtomicUsize: usize; bitwise, numops);
+
+#[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
 impl_atomic!(AtomicPtr<T>);
 
-#[cfg(target_has_atomic = "8")]
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
 impl_atomic!(AtomicI8: i8; bitwise, numops);
 
-#[cfg(target_has_atomic = "16")]
+#[cfg(any(target_has_atomic = "16", feature = "portable-atomic"))]
 impl_atomic!(AtomicI16: i16; bitwise, numops);
 
-#[cfg(target_has_atomic = "32")]
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
 impl_atomic!(AtomicI32: i32; bitwise, numops);
 
-#[cfg(target_has_atomic = "64")]
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
 impl_atomic!(AtomicI64: i64; bitwise, numops);
 
 // #[cfg(target_has_atomic = "128")]
 // impl_atomic!(AtomicI128: i128; bitwise, numops);
 
-#[cfg(target_has_atomic = "8")]
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
 impl_atomic!(AtomicU8: u8; bitwise, numops);
 
-#[cfg(target_has_atomic = "16")]
+#[cfg(any(target_has_atomic = "16", feature = "portable-atomic"))]
 impl_atomic!(AtomicU16: u16; bitwise, numops);
 
-#[cfg(target_has_atomic = "32")]
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
 impl_atomic!(AtomicU32: u32; bitwise, numops);
 
-#[cfg(target_has_atomic = "64")]
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
 impl_atomic!(AtomicU64: u64; bitwise, numops);
 
 // #[cfg(target_has_atomic = "128")]
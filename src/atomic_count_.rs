@@ -2,30 +2,110 @@
     borrow::BorrowMut,
     fmt::{self, Debug},
     marker::PhantomData,
-    sync::atomic::Ordering
+    sync::atomic::{self, Ordering},
 };
 use crate::{
     fetch::{self, Add, Sub},
-    TrAtomicCell, TrAtomicData,
+    PhantomAtomicPtr, StrictOrderings, TrAtomicCell, TrAtomicCellMut,
+    TrAtomicData,
 };
 
-pub struct AtomicCount<V, B = <V as TrAtomicData>::AtomicCell>(
+/// An helper trait to define the orderings used by `AtomicCount`.
+pub trait TrCountOrderings: Unpin {
+    const ADD_ORDERING: Ordering;
+    const SUB_ORDERING: Ordering;
+    const LOAD_ORDERING: Ordering;
+
+    /// The ordering of `set`.
+    const STORE_ORDERING: Ordering = Ordering::Release;
+
+    /// The ordering of `swap`, `reset` and `fetch_update`.
+    const SWAP_ORDERING: Ordering = Ordering::AcqRel;
+}
+
+/// The default orderings of `AtomicCount`: `Acquire` on increment, `Release`
+/// on decrement and `Relaxed` on load.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CountOrderings;
+
+impl TrCountOrderings for CountOrderings {
+    const ADD_ORDERING: Ordering = Ordering::Acquire;
+    const SUB_ORDERING: Ordering = Ordering::Release;
+    const LOAD_ORDERING: Ordering = Ordering::Relaxed;
+}
+
+/// Only atomicity and no synchronization, for purely statistical counters.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RelaxedCountOrderings;
+
+impl TrCountOrderings for RelaxedCountOrderings {
+    const ADD_ORDERING: Ordering = Ordering::Relaxed;
+    const SUB_ORDERING: Ordering = Ordering::Relaxed;
+    const LOAD_ORDERING: Ordering = Ordering::Relaxed;
+    const STORE_ORDERING: Ordering = Ordering::Relaxed;
+    const SWAP_ORDERING: Ordering = Ordering::Relaxed;
+}
+
+/// Both acquire and release on every modification, for reference counts.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RefCountOrderings;
+
+impl TrCountOrderings for RefCountOrderings {
+    const ADD_ORDERING: Ordering = Ordering::AcqRel;
+    const SUB_ORDERING: Ordering = Ordering::AcqRel;
+    const LOAD_ORDERING: Ordering = Ordering::Acquire;
+}
+
+impl TrCountOrderings for StrictOrderings {
+    const ADD_ORDERING: Ordering = Ordering::SeqCst;
+    const SUB_ORDERING: Ordering = Ordering::SeqCst;
+    const LOAD_ORDERING: Ordering = Ordering::SeqCst;
+    const STORE_ORDERING: Ordering = Ordering::SeqCst;
+    const SWAP_ORDERING: Ordering = Ordering::SeqCst;
+}
+
+/// An atomic counter over an integer cell.
+///
+/// The orderings are chosen by `O`, which defaults to `CountOrderings`.
+///
+/// The counter is `#[repr(transparent)]` over `B`, so that over the cell
+/// itself it has the layout of the cell, and can be laid in memory shared
+/// with other processes with `from_raw_ptr`.
+///
+/// # Example
+///
+/// ```
+/// use atomex::{AtomicCountOwned, RelaxedCountOrderings};
+///
+/// let hits = AtomicCountOwned::<u64, RelaxedCountOrderings>::default();
+/// hits.inc();
+/// assert_eq!(hits.val(), 1);
+/// ```
+#[repr(transparent)]
+pub struct AtomicCount<
+    V,
+    B = <V as TrAtomicData>::AtomicCell,
+    O = CountOrderings,
+>(
     B,
-    PhantomData<<V as TrAtomicData>::AtomicCell>)
+    PhantomData<<V as TrAtomicData>::AtomicCell>,
+    PhantomAtomicPtr<O>)
 where
     V: TrAtomicData + funty::Integral,
     <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
         + fetch::Add<Value = V>
         + fetch::Sub<Value = V>,
-    B: BorrowMut<<V as TrAtomicData>::AtomicCell>;
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCountOrderings;
 
-impl<V, B> AtomicCount<V, B>
+impl<V, B, O> AtomicCount<V, B, O>
 where
     V: TrAtomicData + funty::Integral,
     <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
         + fetch::Add<Value = V>
         + fetch::Sub<Value = V>,
     B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCountOrderings,
 {
     /// Create an instance by moving or borrowing an `TrAtomicCell`
     /// 
@@ -46,7 +126,7 @@ where
     /// assert_eq!(cnt.val(), 0usize);
     /// ```
     pub const fn new(cell: B) -> Self {
-        AtomicCount(cell, PhantomData)
+        AtomicCount(cell, PhantomData, PhantomData)
     }
 
     #[inline(always)]
@@ -54,8 +134,32 @@ where
         self.add(V::ONE)
     }
 
+    /// Adds `val` to the count and returns the previous count.
+    ///
+    /// # Panics
+    ///
+    /// With `debug_assertions` enabled, panics if the count would wrap
+    /// around, leaving it unchanged. The addition is then a CAS loop instead
+    /// of a single `fetch_add`. Use `wrapping_add` when wrapping is intended.
     pub fn add(&self, val: V) -> V {
-        self.0.borrow().fetch_add(val, Ordering::Acquire)
+        if !cfg!(debug_assertions) {
+            return self.wrapping_add(val);
+        }
+        match self.update_with(O::ADD_ORDERING, |v| v.checked_add(val)) {
+            Result::Ok(prev) => prev,
+            Result::Err(prev) => {
+                panic!("[AtomicCount::add] overflow: {prev} + {val}")
+            }
+        }
+    }
+
+    /// Adds `val` to the count, wrapping around at the numeric bounds, and
+    /// returns the previous count.
+    #[inline(always)]
+    pub fn wrapping_add(&self, val: V) -> V {
+        let prev = self.0.borrow().fetch_add(val, O::ADD_ORDERING);
+        self.notify_if_zero(prev.wrapping_add(val));
+        prev
     }
 
     #[inline(always)]
@@ -63,29 +167,368 @@ where
         self.sub(V::ONE)
     }
 
+    /// Subtracts `val` from the count and returns the previous count.
+    ///
+    /// # Panics
+    ///
+    /// With `debug_assertions` enabled, panics if the count would wrap
+    /// around, leaving it unchanged. The subtraction is then a CAS loop
+    /// instead of a single `fetch_sub`. Use `wrapping_sub` when wrapping is
+    /// intended.
+    ///
+    /// ```should_panic
+    /// use atomex::AtomicCountOwned;
+    ///
+    /// let refs = AtomicCountOwned::<usize>::default();
+    /// refs.dec();
+    /// ```
     pub fn sub(&self, val: V) -> V {
-        self.0.borrow().fetch_sub(val, Ordering::Release)
+        if !cfg!(debug_assertions) {
+            return self.wrapping_sub(val);
+        }
+        match self.update_with(O::SUB_ORDERING, |v| v.checked_sub(val)) {
+            Result::Ok(prev) => prev,
+            Result::Err(prev) => {
+                panic!("[AtomicCount::sub] underflow: {prev} - {val}")
+            }
+        }
+    }
+
+    /// Subtracts `val` from the count, wrapping around at the numeric bounds,
+    /// and returns the previous count.
+    #[inline(always)]
+    pub fn wrapping_sub(&self, val: V) -> V {
+        let prev = self.0.borrow().fetch_sub(val, O::SUB_ORDERING);
+        self.notify_if_zero(prev.wrapping_sub(val));
+        prev
+    }
+
+    /// Decrements the count and returns `true` exactly when it reaches zero,
+    /// i.e. when the caller has dropped the last reference.
+    ///
+    /// The decrement is `Release` whatever the orderings of the counter, and
+    /// an `Acquire` fence is issued before returning `true`, so that every
+    /// access made through the other references happens before the caller
+    /// destroys the shared data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::AtomicCountOwned;
+    ///
+    /// let refs = AtomicCountOwned::<usize>::default();
+    /// refs.add(2);
+    /// assert!(!refs.dec_and_test());
+    /// assert!(refs.dec_and_test());
+    /// ```
+    pub fn dec_and_test(&self) -> bool {
+        let prev = if cfg!(debug_assertions) {
+            // A CAS loop, so that an underflow is caught before it is made.
+            let r = self.update_with(Ordering::Release, |v| {
+                v.checked_sub(V::ONE)
+            });
+            r.unwrap_or_else(|_| {
+                panic!("[AtomicCount::dec_and_test] underflow")
+            })
+        } else {
+            self.0.borrow().fetch_sub(V::ONE, Ordering::Release)
+        };
+        if prev != V::ONE {
+            return false;
+        }
+        atomic::fence(Ordering::Acquire);
+        // The CAS loop has notified the waiters already.
+        if !cfg!(debug_assertions) {
+            self.notify_if_zero(V::ZERO);
+        }
+        true
     }
 
     pub fn val(&self) -> V {
-        self.0.borrow().load(Ordering::Relaxed)
+        self.0.borrow().load(O::LOAD_ORDERING)
+    }
+
+    /// Overwrites the count with `val`.
+    pub fn set(&self, val: V) {
+        self.0.borrow().store(val, O::STORE_ORDERING);
+        self.notify_if_zero(val);
+    }
+
+    /// Overwrites the count with `val`, and returns the previous count.
+    pub fn swap(&self, val: V) -> V {
+        let prev = self.0.borrow().swap(val, O::SWAP_ORDERING);
+        self.notify_if_zero(val);
+        prev
+    }
+
+    /// Resets the count to zero, and returns the previous count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::AtomicCountOwned;
+    ///
+    /// let cnt = AtomicCountOwned::<u32>::default();
+    /// cnt.set(3);
+    /// assert_eq!(cnt.swap(5), 3);
+    /// assert_eq!(cnt.fetch_update(|v| (v < 10).then_some(v * 2)), Ok(5));
+    /// assert_eq!(cnt.fetch_update(|v| (v < 10).then_some(v * 2)), Err(10));
+    /// assert_eq!(cnt.reset(), 10);
+    /// assert_eq!(cnt.val(), 0);
+    /// ```
+    #[inline(always)]
+    pub fn reset(&self) -> V {
+        self.swap(V::ZERO)
+    }
+
+    /// Stores the count computed by `f` from the current one, unless `f`
+    /// returns `None`.
+    ///
+    /// Returns `Ok(previous)` if the count was updated, else `Err(current)`.
+    /// `f` may be called several times when contended.
+    pub fn fetch_update<F>(&self, f: F) -> Result<V, V>
+    where
+        F: FnMut(V) -> Option<V>,
+    {
+        self.update_with(O::SWAP_ORDERING, f)
+    }
+
+    /// Decrements the count only if it is currently nonzero.
+    ///
+    /// Returns `Ok(previous)` if the count was decremented, else `Err(0)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::AtomicCountOwned;
+    ///
+    /// let permits = AtomicCountOwned::<usize>::default();
+    /// permits.inc();
+    /// assert_eq!(permits.try_dec_nonzero(), Ok(1));
+    /// assert_eq!(permits.try_dec_nonzero(), Err(0));
+    /// ```
+    pub fn try_dec_nonzero(&self) -> Result<V, V> {
+        self.update_with(O::SUB_ORDERING, |v| {
+            (v != V::ZERO).then(|| v - V::ONE)
+        })
+    }
+
+    /// Increments the count unless it would exceed `max`, returning whether
+    /// the increment happened.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::AtomicCountOwned;
+    ///
+    /// let conns = AtomicCountOwned::<usize>::default();
+    /// assert!(conns.inc_unless(2));
+    /// assert!(conns.add_unless(1, 2));
+    /// assert!(!conns.inc_unless(2));
+    /// assert_eq!(conns.val(), 2);
+    /// ```
+    #[inline(always)]
+    pub fn inc_unless(&self, max: V) -> bool {
+        self.add_unless(V::ONE, max)
+    }
+
+    /// Adds `val` to the count unless the sum would exceed `max`, returning
+    /// whether the addition happened.
+    pub fn add_unless(&self, val: V, max: V) -> bool {
+        self.update_with(O::ADD_ORDERING, |v| {
+            v.checked_add(val).filter(|x| *x <= max)
+        })
+        .is_ok()
+    }
+
+    /// Adds `val` to the count, clamping at the numeric bounds instead of
+    /// wrapping around, and returns the previous count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::AtomicCountOwned;
+    ///
+    /// let bytes = AtomicCountOwned::<u8>::default();
+    /// assert_eq!(bytes.saturating_add(200), 0);
+    /// assert_eq!(bytes.saturating_add(200), 200);
+    /// assert_eq!(bytes.val(), u8::MAX);
+    /// assert_eq!(bytes.saturating_sub(u8::MAX), u8::MAX);
+    /// assert_eq!(bytes.saturating_sub(1), 0);
+    /// assert_eq!(bytes.val(), 0);
+    /// ```
+    pub fn saturating_add(&self, val: V) -> V {
+        let r = self.update_with(O::ADD_ORDERING, |v| {
+            let x = v.saturating_add(val);
+            (x != v).then_some(x)
+        });
+        r.unwrap_or_else(|v| v)
+    }
+
+    /// Subtracts `val` from the count, clamping at the numeric bounds instead
+    /// of wrapping around, and returns the previous count.
+    pub fn saturating_sub(&self, val: V) -> V {
+        let r = self.update_with(O::SUB_ORDERING, |v| {
+            let x = v.saturating_sub(val);
+            (x != v).then_some(x)
+        });
+        r.unwrap_or_else(|v| v)
+    }
+
+    /// Stores the value computed by `f` from the current one with a CAS
+    /// loop, unless `f` returns `None`.
+    fn update_with<F>(&self, order: Ordering, mut f: F) -> Result<V, V>
+    where
+        F: FnMut(V) -> Option<V>,
+    {
+        let cell = self.0.borrow();
+        let mut current = cell.load(O::LOAD_ORDERING);
+        loop {
+            let Option::Some(desired) = f(current) else {
+                break Result::Err(current);
+            };
+            match cell.compare_exchange_weak(
+                current,
+                desired,
+                order,
+                O::LOAD_ORDERING,
+            ) {
+                Result::Ok(x) => {
+                    self.notify_if_zero(desired);
+                    break Result::Ok(x);
+                }
+                Result::Err(x) => current = x,
+            }
+        }
+    }
+
+    /// Returns a future resolved once the count is zero, e.g. to wait for the
+    /// operations in flight to drain.
+    ///
+    /// The count is checked at once, and again every time it is brought to
+    /// zero by a method of `AtomicCount`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::{
+    ///     future::Future,
+    ///     pin::pin,
+    ///     task::{Context, Poll, Waker},
+    /// };
+    /// use atomex::AtomicCountOwned;
+    ///
+    /// let in_flight = AtomicCountOwned::<usize>::default();
+    /// in_flight.add(2);
+    /// let mut drained = pin!(in_flight.wait_zero());
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert!(drained.as_mut().poll(&mut cx).is_pending());
+    ///
+    /// in_flight.dec();
+    /// assert!(drained.as_mut().poll(&mut cx).is_pending());
+    /// in_flight.dec();
+    /// assert_eq!(drained.as_mut().poll(&mut cx), Poll::Ready(()));
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn wait_zero(&self) -> impl core::future::Future<Output = ()> + '_ {
+        let addr = self.0.borrow() as *const _ as usize;
+        crate::wait_table_::Wait::new(addr, move || {
+            (self.val() == V::ZERO).then_some(())
+        })
+    }
+
+    /// Wakes up the tasks in `wait_zero` if `count` is zero.
+    #[inline(always)]
+    fn notify_if_zero(&self, count: V) {
+        #[cfg(feature = "async")]
+        if count == V::ZERO {
+            crate::wait_table_::notify(
+                self.0.borrow() as *const _ as usize,
+                Ordering::Relaxed,
+            )
+        }
+        #[cfg(not(feature = "async"))]
+        let _ = count;
     }
 }
 
-impl<V> AtomicCount<V, <V as TrAtomicData>::AtomicCell>
+impl<V, O> AtomicCount<V, <V as TrAtomicData>::AtomicCell, O>
 where
     V: TrAtomicData + funty::Integral,
     <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
         + fetch::Add<Value = V>
         + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
 {
     pub fn into_inner(self) -> <V as TrAtomicData>::AtomicCell {
         self.0
     }
+
+    /// Returns a mutable reference to the count, which needs no atomic
+    /// operation since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut V
+    where
+        <V as TrAtomicData>::AtomicCell: TrAtomicCellMut,
+    {
+        TrAtomicCellMut::get_mut(&mut self.0)
+    }
+
+    /// The counter over the count at `ptr`, such as in a memory segment
+    /// mapped by several processes, which all count in the same cell.
+    ///
+    /// The cell must have the size of `V`, as those of `core` do.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be aligned for the cell, which may be more than for `V`,
+    /// and valid for reads and writes for `'a`, during which the count is
+    /// only accessed atomically.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::AtomicCount;
+    ///
+    /// let mut segment = [0u64; 2];
+    /// let base = segment.as_mut_ptr();
+    /// // Safety: the segment outlives the counter and is only counted in.
+    /// let hits = unsafe { AtomicCount::<u64>::from_raw_ptr(base.add(1)) };
+    /// hits.inc();
+    /// assert_eq!(hits.val(), 1);
+    /// assert_eq!(segment[1], 1);
+    /// ```
+    #[cfg(feature = "std")]
+    pub unsafe fn from_raw_ptr<'a>(ptr: *mut V) -> &'a Self {
+        const {
+            assert!(
+                core::mem::size_of::<<V as TrAtomicData>::AtomicCell>()
+                    == core::mem::size_of::<V>()
+            )
+        };
+        debug_assert!(
+            ptr.cast::<Self>().is_aligned(),
+            "[AtomicCount::from_raw_ptr] misaligned pointer",
+        );
+        // Safety: the counter is transparent over the cell, which has the
+        // size of `V`, and the pointer is valid as required.
+        unsafe { &*ptr.cast::<Self>() }
+    }
 }
 
+/// Only with the default orderings, which a conversion could not infer.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicU32;
+/// use atomex::AtomicCount;
+///
+/// let mut cell = AtomicU32::new(1);
+/// let refs = AtomicCount::<u32, _>::from(&mut cell);
+/// assert_eq!(refs.dec(), 1);
+/// ```
 impl<'a, V> From<&'a mut <V as TrAtomicData>::AtomicCell>
-for AtomicCount<V, &'a mut <V as TrAtomicData>::AtomicCell>
+for AtomicCount<V, &'a mut <V as TrAtomicData>::AtomicCell, CountOrderings>
 where
     V: TrAtomicData + funty::Integral,
     <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
@@ -97,32 +540,35 @@ where
     }
 }
 
-impl<V, B> Debug for AtomicCount<V, B>
+impl<V, B, O> Debug for AtomicCount<V, B, O>
 where
     V: TrAtomicData + funty::Integral,
     <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
         + fetch::Add<Value = V>
         + fetch::Sub<Value = V> + Debug,
     B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCountOrderings,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.borrow().fmt(f)
     }
 }
 
-impl<V> Default for AtomicCount<V>
+impl<V, O> Default for AtomicCount<V, <V as TrAtomicData>::AtomicCell, O>
 where
     V: TrAtomicData + funty::Integral,
     <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
         + fetch::Add<Value = V>
         + fetch::Sub<Value = V>
         + Default,
+    O: TrCountOrderings,
 {
     fn default() -> Self {
         Self::new(V::AtomicCell::default())
     }
 }
 
-pub type AtomicCountOwned<V> = AtomicCount<V, <V as TrAtomicData>::AtomicCell>;
-pub type AtomicCountMut<'a, V> =
-    AtomicCount<V, &'a mut <V as TrAtomicData>::AtomicCell>;
\ No newline at end of file
+pub type AtomicCountOwned<V, O = CountOrderings> =
+    AtomicCount<V, <V as TrAtomicData>::AtomicCell, O>;
+pub type AtomicCountMut<'a, V, O = CountOrderings> =
+    AtomicCount<V, &'a mut <V as TrAtomicData>::AtomicCell, O>;
\ No newline at end of file
@@ -0,0 +1,211 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+};
+use crate::{
+    fetch, AcqRelOrderings, NumOps, TrAtomicCell,
+    TrAtomicData, TrCmpxchOrderings,
+};
+
+/// A fixed-point number with `FRAC_BITS` fractional bits, stored in an
+/// atomic integer of type `I`, for fractional accumulators on targets
+/// without an FPU or float atomics.
+///
+/// The arithmetic operates on the raw representation, that is the number
+/// times `2^FRAC_BITS`, with the same fetch operations as the integer cells.
+/// The conversions from and to integers and floats saturate at the bounds of
+/// `I`, and round towards negative infinity.
+///
+/// # Example
+///
+/// ```
+/// use atomex::AtomicFixed;
+///
+/// type Q16 = AtomicFixed<i32, 16>;
+///
+/// let load = Q16::from_int(1);
+/// load.fetch_add(Q16::raw_from_f64(0.25));
+/// assert_eq!(load.to_f64(), 1.25);
+/// load.saturating_add(i32::MAX);
+/// assert_eq!(load.to_int(), 32767);
+/// load.store(Q16::raw_from_f64(-2.5));
+/// assert_eq!(load.to_int(), -3);
+/// ```
+pub struct AtomicFixed<I, const FRAC_BITS: u32, O = AcqRelOrderings>
+where
+    I: TrAtomicData + funty::Integral,
+    <I as TrAtomicData>::AtomicCell: TrAtomicCell<Value = I> + NumOps,
+    O: TrCmpxchOrderings,
+{
+    raw: <I as TrAtomicData>::AtomicCell,
+    _orderings: PhantomData<O>,
+}
+
+impl<I, const FRAC_BITS: u32, O> AtomicFixed<I, FRAC_BITS, O>
+where
+    I: TrAtomicData + funty::Integral,
+    <I as TrAtomicData>::AtomicCell: TrAtomicCell<Value = I> + NumOps,
+    O: TrCmpxchOrderings,
+{
+    /// Creates a cell from the raw representation of a number.
+    pub fn new(raw: I) -> Self {
+        const {
+            assert!(
+                FRAC_BITS < I::BITS,
+                "[AtomicFixed] no integral bit left",
+            )
+        };
+        AtomicFixed {
+            raw: TrAtomicCell::new(raw),
+            _orderings: PhantomData,
+        }
+    }
+
+    pub fn from_int(n: i64) -> Self {
+        Self::new(Self::raw_from_int(n))
+    }
+
+    pub fn from_f64(x: f64) -> Self {
+        Self::new(Self::raw_from_f64(x))
+    }
+
+    /// The raw representation of the integer `n`, saturated.
+    pub fn raw_from_int(n: i64) -> I {
+        Self::saturate((n as i128) << FRAC_BITS)
+    }
+
+    /// The integral part of the number of raw representation `raw`,
+    /// rounded down and saturated.
+    pub fn raw_to_int(raw: I) -> i64 {
+        let n = Self::widen(raw) >> FRAC_BITS;
+        n.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    /// The raw representation of `x`, rounded down and saturated, `0` for
+    /// NaN.
+    pub fn raw_from_f64(x: f64) -> I {
+        let scaled = x * (1u64 << FRAC_BITS) as f64;
+        // Float to integer casts saturate, and floor is not in `core`.
+        let mut n = scaled as i128;
+        if (n as f64) > scaled {
+            n = n.saturating_sub(1);
+        }
+        Self::saturate(n)
+    }
+
+    pub fn raw_to_f64(raw: I) -> f64 {
+        Self::widen(raw) as f64 / (1u64 << FRAC_BITS) as f64
+    }
+
+    /// The raw representation of the number.
+    #[inline(always)]
+    pub fn load(&self) -> I {
+        self.raw.load(O::LOAD_ORDERING)
+    }
+
+    #[inline(always)]
+    pub fn to_int(&self) -> i64 {
+        Self::raw_to_int(self.load())
+    }
+
+    #[inline(always)]
+    pub fn to_f64(&self) -> f64 {
+        Self::raw_to_f64(self.load())
+    }
+
+    /// Stores the number of raw representation `raw`.
+    pub fn store(&self, raw: I) {
+        self.raw.store(raw, O::STORE_ORDERING)
+    }
+
+    pub fn swap(&self, raw: I) -> I {
+        self.raw.swap(raw, O::SWAP_ORDERING)
+    }
+
+    /// Adds the number of raw representation `raw`, wrapping around, and
+    /// returns the previous one.
+    pub fn fetch_add(&self, raw: I) -> I {
+        fetch::Add::fetch_add(&self.raw, raw, O::SWAP_ORDERING)
+    }
+
+    /// Subtracts the number of raw representation `raw`, wrapping around,
+    /// and returns the previous one.
+    pub fn fetch_sub(&self, raw: I) -> I {
+        fetch::Sub::fetch_sub(&self.raw, raw, O::SWAP_ORDERING)
+    }
+
+    pub fn fetch_max(&self, raw: I) -> I {
+        fetch::Max::fetch_max(&self.raw, raw, O::SWAP_ORDERING)
+    }
+
+    pub fn fetch_min(&self, raw: I) -> I {
+        fetch::Min::fetch_min(&self.raw, raw, O::SWAP_ORDERING)
+    }
+
+    /// Adds the number of raw representation `raw`, saturating at the
+    /// bounds, and returns the previous one.
+    pub fn saturating_add(&self, raw: I) -> I {
+        self.fetch_update(|x| Option::Some(x.saturating_add(raw)))
+            .unwrap_or_else(|x| x)
+    }
+
+    /// Subtracts the number of raw representation `raw`, saturating at the
+    /// bounds, and returns the previous one.
+    pub fn saturating_sub(&self, raw: I) -> I {
+        self.fetch_update(|x| Option::Some(x.saturating_sub(raw)))
+            .unwrap_or_else(|x| x)
+    }
+
+    /// Stores the raw representation computed by `f` from the current one,
+    /// unless `f` returns `None`.
+    ///
+    /// Returns `Ok(previous)` if it was stored, else `Err(current)`.
+    pub fn fetch_update<F>(&self, f: F) -> Result<I, I>
+    where
+        F: FnMut(I) -> Option<I>,
+    {
+        fetch::Update::fetch_update(
+            &self.raw,
+            O::SUCC_ORDERING,
+            O::FAIL_ORDERING,
+            f,
+        )
+    }
+
+    pub fn into_inner(self) -> I {
+        self.raw.into_inner()
+    }
+
+    fn widen(raw: I) -> i128 {
+        let Result::Ok(n) = raw.try_into() else {
+            unreachable!("[AtomicFixed] atomic integer wider than i128")
+        };
+        n
+    }
+
+    fn saturate(n: i128) -> I {
+        I::try_from(n).unwrap_or(if n < 0 { I::MIN } else { I::MAX })
+    }
+}
+
+impl<I, const FRAC_BITS: u32, O> Default for AtomicFixed<I, FRAC_BITS, O>
+where
+    I: TrAtomicData + funty::Integral,
+    <I as TrAtomicData>::AtomicCell: TrAtomicCell<Value = I> + NumOps,
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new(I::ZERO)
+    }
+}
+
+impl<I, const FRAC_BITS: u32, O> fmt::Debug for AtomicFixed<I, FRAC_BITS, O>
+where
+    I: TrAtomicData + funty::Integral,
+    <I as TrAtomicData>::AtomicCell: TrAtomicCell<Value = I> + NumOps,
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicFixed").field(&self.to_f64()).finish()
+    }
+}
@@ -3,11 +3,14 @@
     convert::AsRef,
     fmt::{self, Debug},
     marker::PhantomData,
+    ops::{BitAnd, Not},
+    sync::atomic::Ordering,
 };
 
 use crate::{
-    CmpxchResult, PhantomAtomicPtr, StrictOrderings,
-    TrAtomicCell, TrAtomicData, TrCmpxchOrderings,
+    atomic_cell_, fetch, CmpxchOutcome, CmpxchResult, NoBackoff,
+    PhantomAtomicPtr, SpinBackoff, StrictOrderings, TrAtomicCell,
+    TrAtomicCellMut, TrAtomicData, TrBackoff, TrCmpxchOrderings,
 };
 
 pub trait TrAtomicFlags<T, O = StrictOrderings>
@@ -21,12 +24,101 @@ where
         self.as_ref().load(O::LOAD_ORDERING)
     }
 
+    /// Spins until the loaded value satisfies `cond`, and returns that value.
+    fn spin_until<FnCond>(&self, cond: FnCond) -> T
+    where
+        FnCond: FnMut(T) -> bool,
+    {
+        self.spin_until_with_backoff(SpinBackoff::new(), cond)
+    }
+
+    /// Like `spin_until`, but waits according to `backoff` after every load
+    /// failing `cond`.
+    fn spin_until_with_backoff<Bo, FnCond>(
+        &self,
+        mut backoff: Bo,
+        mut cond: FnCond,
+    ) -> T
+    where
+        Bo: TrBackoff,
+        FnCond: FnMut(T) -> bool,
+    {
+        loop {
+            let v = self.value();
+            if cond(v) {
+                break v;
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Spins as long as the loaded value satisfies `cond`, and returns the
+    /// first value that does not.
+    fn spin_while<FnCond>(&self, cond: FnCond) -> T
+    where
+        FnCond: FnMut(T) -> bool,
+    {
+        self.spin_while_with_backoff(SpinBackoff::new(), cond)
+    }
+
+    /// Like `spin_while`, but waits according to `backoff` after every load
+    /// satisfying `cond`.
+    fn spin_while_with_backoff<Bo, FnCond>(
+        &self,
+        backoff: Bo,
+        mut cond: FnCond,
+    ) -> T
+    where
+        Bo: TrBackoff,
+        FnCond: FnMut(T) -> bool,
+    {
+        self.spin_until_with_backoff(backoff, |v| !cond(v))
+    }
+
+    /// Atomically clears the bits of `mask`, and returns those of them that
+    /// were set, i.e. the bits claimed by this call.
+    fn fetch_take(&self, mask: T) -> T
+    where
+        T: BitAnd<Output = T> + Not<Output = T>,
+        <T as TrAtomicData>::AtomicCell: fetch::And<Value = T>,
+    {
+        let prev = fetch::And::fetch_and(
+            self.as_ref(),
+            !mask,
+            O::SWAP_ORDERING,
+        );
+        atomic_cell_::fence_after::<O>();
+        let addr = self.as_ref() as *const _ as usize;
+        atomic_cell_::notify_waiters(addr, O::SWAP_ORDERING);
+        prev & mask
+    }
+
     fn try_spin_compare_exchange_weak<FnExpect, FnDesire>(
         &self,
+        expect: FnExpect,
+        desire: FnDesire,
+    ) -> CmpxchResult<T>
+    where
+        FnExpect: FnMut(T) -> bool,
+        FnDesire: FnMut(T) -> T,
+    {
+        self.try_spin_compare_exchange_weak_with_backoff(
+            NoBackoff,
+            expect,
+            desire,
+        )
+    }
+
+    /// Like `try_spin_compare_exchange_weak`, but waits according to
+    /// `backoff` after every failed attempt.
+    fn try_spin_compare_exchange_weak_with_backoff<Bo, FnExpect, FnDesire>(
+        &self,
+        mut backoff: Bo,
         mut expect: FnExpect,
         mut desire: FnDesire,
     ) -> CmpxchResult<T>
     where
+        Bo: TrBackoff,
         FnExpect: FnMut(T) -> bool,
         FnDesire: FnMut(T) -> T,
     {
@@ -40,12 +132,114 @@ where
             );
             if let CmpxchResult::Fail(x) = r {
                 current = x;
+                backoff.backoff();
             } else {
                 break r;
             }
         }
     }
 
+    /// Like `try_spin_compare_exchange_weak`, but also reports how many
+    /// compare-exchange attempts were made.
+    fn try_spin_compare_exchange_weak_counted<FnExpect, FnDesire>(
+        &self,
+        mut expect: FnExpect,
+        mut desire: FnDesire,
+    ) -> CmpxchOutcome<T>
+    where
+        FnExpect: FnMut(T) -> bool,
+        FnDesire: FnMut(T) -> T,
+    {
+        let atomic = self.as_ref();
+        let mut current = atomic.load(O::LOAD_ORDERING);
+        let mut attempts = 0;
+        loop {
+            let r = self.try_once_compare_exchange_weak(
+                current,
+                &mut expect,
+                &mut desire,
+            );
+            if r.is_unexpected() {
+                break CmpxchOutcome { result: r, attempts };
+            }
+            attempts += 1;
+            if let CmpxchResult::Fail(x) = r {
+                current = x;
+            } else {
+                break CmpxchOutcome { result: r, attempts };
+            }
+        }
+    }
+
+    /// Like `try_spin_compare_exchange_weak`, but with a `desire` closure
+    /// that can fail.
+    ///
+    /// An error from `desire` stops the spinning at once, leaving the value
+    /// untouched, and is returned as `Err`.
+    fn try_spin_compare_exchange_weak_fallible<FnExpect, FnDesire, E>(
+        &self,
+        mut expect: FnExpect,
+        mut desire: FnDesire,
+    ) -> Result<CmpxchResult<T>, E>
+    where
+        FnExpect: FnMut(T) -> bool,
+        FnDesire: FnMut(T) -> Result<T, E>,
+    {
+        let atomic = self.as_ref();
+        let mut current = atomic.load(O::LOAD_ORDERING);
+        loop {
+            if !expect(current) {
+                break Result::Ok(CmpxchResult::Unexpected(current));
+            }
+            let desired = desire(current)?;
+            match atomic.compare_exchange_weak(
+                current,
+                desired,
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            ) {
+                Result::Ok(x) => {
+                    let addr = atomic as *const _ as usize;
+                    atomic_cell_::notify_waiters(addr, O::SUCC_ORDERING);
+                    break Result::Ok(CmpxchResult::Succ(x));
+                }
+                Result::Err(x) => current = x,
+            }
+        }
+    }
+
+    /// Like `try_spin_compare_exchange_weak`, but gives up after
+    /// `max_attempts` failed attempts, returning `Exhausted` with the last
+    /// value observed.
+    ///
+    /// `max_attempts` of zero performs no attempt at all.
+    fn try_spin_compare_exchange_weak_bounded<FnExpect, FnDesire>(
+        &self,
+        max_attempts: usize,
+        mut expect: FnExpect,
+        mut desire: FnDesire,
+    ) -> CmpxchResult<T>
+    where
+        FnExpect: FnMut(T) -> bool,
+        FnDesire: FnMut(T) -> T,
+    {
+        let atomic = self.as_ref();
+        let mut current = atomic.load(O::LOAD_ORDERING);
+        for _ in 0..max_attempts {
+            let r = self.try_once_compare_exchange_weak(
+                current,
+                &mut expect,
+                &mut desire,
+            );
+            if let CmpxchResult::Fail(x) = r {
+                current = x;
+            } else {
+                return r;
+            }
+        }
+        CmpxchResult::Exhausted(current)
+    }
+
     fn try_once_compare_exchange_weak<FnExpect, FnDesire>(
         &self,
         current: T,
@@ -67,12 +261,110 @@ where
             O::SUCC_ORDERING,
             O::FAIL_ORDERING,
         ) {
-            Result::Ok(x) => CmpxchResult::Succ(x),
+            Result::Ok(x) => {
+                let addr = atomic as *const _ as usize;
+                atomic_cell_::notify_waiters(addr, O::SUCC_ORDERING);
+                CmpxchResult::Succ(x)
+            }
             Result::Err(x) => CmpxchResult::Fail(x),
         }
     }
+
+    /// Like `try_spin_compare_exchange_weak`, but with the strong
+    /// `compare_exchange`, so that a retry only happens when the value was
+    /// actually changed by another thread.
+    fn try_spin_compare_exchange<FnExpect, FnDesire>(
+        &self,
+        mut expect: FnExpect,
+        mut desire: FnDesire,
+    ) -> CmpxchResult<T>
+    where
+        FnExpect: FnMut(T) -> bool,
+        FnDesire: FnMut(T) -> T,
+    {
+        let atomic = self.as_ref();
+        let mut current = atomic.load(O::LOAD_ORDERING);
+        loop {
+            let r = self.try_once_compare_exchange(
+                current,
+                &mut expect,
+                &mut desire,
+            );
+            if let CmpxchResult::Fail(x) = r {
+                current = x;
+            } else {
+                break r;
+            }
+        }
+    }
+
+    /// Like `try_once_compare_exchange_weak`, but with the strong
+    /// `compare_exchange`, which never fails spuriously: a `Fail` result
+    /// always means the value was not `current`.
+    fn try_once_compare_exchange<FnExpect, FnDesire>(
+        &self,
+        current: T,
+        mut expect: FnExpect,
+        mut desire: FnDesire,
+    ) -> CmpxchResult<T>
+    where
+        FnExpect: FnMut(T) -> bool,
+        FnDesire: FnMut(T) -> T,
+    {
+        let atomic = self.as_ref();
+        if !expect(current) {
+            return CmpxchResult::Unexpected(current);
+        };
+        let desired = desire(current);
+        match atomic.compare_exchange(
+            current,
+            desired,
+            O::SUCC_ORDERING,
+            O::FAIL_ORDERING,
+        ) {
+            Result::Ok(x) => {
+                let addr = atomic as *const _ as usize;
+                atomic_cell_::notify_waiters(addr, O::SUCC_ORDERING);
+                CmpxchResult::Succ(x)
+            }
+            Result::Err(x) => CmpxchResult::Fail(x),
+        }
+    }
+}
+
+/// The value of an `AtomicFlags` observed at some point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FlagsSnapshot<T>(T);
+
+impl<T> FlagsSnapshot<T>
+where
+    T: Copy + BitAnd<Output = T> + Not<Output = T>,
+{
+    pub const fn new(value: T) -> Self {
+        FlagsSnapshot(value)
+    }
+
+    #[inline(always)]
+    pub fn value(&self) -> T {
+        self.0
+    }
+
+    /// Returns the bits of `current` that are set but were not in the
+    /// snapshot, and those that were set in the snapshot but are not any
+    /// more.
+    pub fn diff(&self, current: T) -> (T, T) {
+        let set_bits = current & !self.0;
+        let cleared_bits = self.0 & !current;
+        (set_bits, cleared_bits)
+    }
 }
 
+/// Flags over an atomic cell, updated with compare-exchange loops.
+///
+/// The flags are `#[repr(transparent)]` over `B`, so that over the cell
+/// itself they have the layout of the cell, and can be laid in memory
+/// shared with other processes with `from_raw_ptr`.
+#[repr(transparent)]
 pub struct AtomicFlags<
     T,
     B = <T as TrAtomicData>::AtomicCell,
@@ -100,6 +392,54 @@ where
         TrAtomicFlags::<T, O>::value(self)
     }
 
+    /// Loads the value with `order` instead of `O::LOAD_ORDERING`.
+    #[inline(always)]
+    pub fn value_with(&self, order: Ordering) -> T {
+        self.as_ref().load(order)
+    }
+
+    #[inline(always)]
+    pub fn spin_until(&self, cond: impl FnMut(T) -> bool) -> T {
+        TrAtomicFlags::spin_until(self, cond)
+    }
+
+    #[inline(always)]
+    pub fn spin_while(&self, cond: impl FnMut(T) -> bool) -> T {
+        TrAtomicFlags::spin_while(self, cond)
+    }
+
+    /// Like `spin_until`, but waits according to `backoff` between the loads.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::AtomicU32;
+    /// use atomex::{AtomicFlags, ExpBackoff, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u32, AtomicU32, StrictOrderings>::new(
+    ///     AtomicU32::new(0b10));
+    /// let v = flags.spin_until_with_backoff(ExpBackoff::new(), |v| v != 0);
+    /// assert_eq!(v, 0b10);
+    /// ```
+    #[inline(always)]
+    pub fn spin_until_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+        cond: impl FnMut(T) -> bool,
+    ) -> T {
+        TrAtomicFlags::spin_until_with_backoff(self, backoff, cond)
+    }
+
+    /// Like `spin_while`, but waits according to `backoff` between the loads.
+    #[inline(always)]
+    pub fn spin_while_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+        cond: impl FnMut(T) -> bool,
+    ) -> T {
+        TrAtomicFlags::spin_while_with_backoff(self, backoff, cond)
+    }
+
     #[inline(always)]
     pub fn try_once_compare_exchange_weak(
         &self,
@@ -123,6 +463,381 @@ where
     ) -> CmpxchResult<T> {
         TrAtomicFlags::try_spin_compare_exchange_weak(self, expect, desire)
     }
+
+    /// Spins the weak compare-exchange with a fallible `desire`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::AtomicU8;
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(200));
+    /// let double = |v: u8| v.checked_mul(2).ok_or("overflow");
+    /// let r = flags.try_spin_compare_exchange_weak_fallible(|_| true, double);
+    /// assert_eq!(r.map(|r| r.is_succ()), Err("overflow"));
+    /// assert_eq!(flags.value(), 200);
+    /// ```
+    #[inline(always)]
+    pub fn try_spin_compare_exchange_weak_fallible<E>(
+        &self,
+        expect: impl FnMut(T) -> bool,
+        desire: impl FnMut(T) -> Result<T, E>,
+    ) -> Result<CmpxchResult<T>, E> {
+        TrAtomicFlags::try_spin_compare_exchange_weak_fallible(
+            self,
+            expect,
+            desire,
+        )
+    }
+
+    /// Spins the weak compare-exchange, counting the attempts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::AtomicU8;
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(1));
+    /// let o = flags.try_spin_compare_exchange_weak_counted(|v| v == 1, |v| v + 1);
+    /// assert!(o.result.is_succ());
+    /// assert!(o.attempts >= 1);
+    /// let o = flags.try_spin_compare_exchange_weak_counted(|v| v == 1, |v| v + 1);
+    /// assert!(o.result.is_unexpected());
+    /// assert_eq!(o.attempts, 0);
+    /// ```
+    #[inline(always)]
+    pub fn try_spin_compare_exchange_weak_counted(
+        &self,
+        expect: impl FnMut(T) -> bool,
+        desire: impl FnMut(T) -> T,
+    ) -> CmpxchOutcome<T> {
+        TrAtomicFlags::try_spin_compare_exchange_weak_counted(
+            self,
+            expect,
+            desire,
+        )
+    }
+
+    /// Spins the weak compare-exchange at most `max_attempts` times.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::AtomicU8;
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(1));
+    /// let r = flags.try_spin_compare_exchange_weak_bounded(0, |_| true, |v| v + 1);
+    /// assert_eq!(r.exhausted(), Some(1));
+    /// let r = flags.try_spin_compare_exchange_weak_bounded(8, |v| v == 2, |v| v + 1);
+    /// assert_eq!(r.unexpected(), Some(1));
+    /// ```
+    #[inline(always)]
+    pub fn try_spin_compare_exchange_weak_bounded(
+        &self,
+        max_attempts: usize,
+        expect: impl FnMut(T) -> bool,
+        desire: impl FnMut(T) -> T,
+    ) -> CmpxchResult<T> {
+        TrAtomicFlags::try_spin_compare_exchange_weak_bounded(
+            self,
+            max_attempts,
+            expect,
+            desire,
+        )
+    }
+
+    /// Like `try_spin_compare_exchange_weak`, but with the orderings `succ`
+    /// and `fail` instead of those of `O`.
+    ///
+    /// The value is initially loaded with the `fail` ordering, since a load
+    /// has the same effect as a failed compare-exchange.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::{AtomicU8, Ordering};
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(1));
+    /// let r = flags.try_spin_compare_exchange_weak_with(
+    ///     Ordering::Relaxed,
+    ///     Ordering::Relaxed,
+    ///     |_| true,
+    ///     |v| v | 2,
+    /// );
+    /// assert!(r.is_succ());
+    /// assert_eq!(flags.value_with(Ordering::Relaxed), 3);
+    /// ```
+    pub fn try_spin_compare_exchange_weak_with(
+        &self,
+        succ: Ordering,
+        fail: Ordering,
+        mut expect: impl FnMut(T) -> bool,
+        mut desire: impl FnMut(T) -> T,
+    ) -> CmpxchResult<T> {
+        let atomic = self.as_ref();
+        let mut current = atomic.load(fail);
+        loop {
+            if !expect(current) {
+                break CmpxchResult::Unexpected(current);
+            }
+            let desired = desire(current);
+            match atomic.compare_exchange_weak(current, desired, succ, fail) {
+                Result::Ok(x) => {
+                    let addr = atomic as *const _ as usize;
+                    atomic_cell_::notify_waiters(addr, succ);
+                    break CmpxchResult::Succ(x);
+                }
+                Result::Err(x) => current = x,
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn try_spin_compare_exchange_weak_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+        expect: impl FnMut(T) -> bool,
+        desire: impl FnMut(T) -> T,
+    ) -> CmpxchResult<T> {
+        TrAtomicFlags::try_spin_compare_exchange_weak_with_backoff(
+            self,
+            backoff,
+            expect,
+            desire,
+        )
+    }
+
+    #[inline(always)]
+    pub fn try_once_compare_exchange(
+        &self,
+        current: T,
+        expect: impl FnMut(T) -> bool,
+        desire: impl FnMut(T) -> T,
+    ) -> CmpxchResult<T> {
+        TrAtomicFlags::try_once_compare_exchange(self, current, expect, desire)
+    }
+
+    #[inline(always)]
+    pub fn try_spin_compare_exchange(
+        &self,
+        expect: impl FnMut(T) -> bool,
+        desire: impl FnMut(T) -> T,
+    ) -> CmpxchResult<T> {
+        TrAtomicFlags::try_spin_compare_exchange(self, expect, desire)
+    }
+
+    /// Returns a future resolved with the first value satisfying `cond`,
+    /// which is checked at once and again after every update.
+    ///
+    /// The updates are noticed when made through the compare-exchange
+    /// methods or `fetch_take`. After modifying the value otherwise, e.g.
+    /// with a plain store on `as_ref()`, call `wake_waiters`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::{
+    ///     future::Future,
+    ///     pin::pin,
+    ///     sync::atomic::AtomicU8,
+    ///     task::{Context, Poll, Waker},
+    /// };
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(0));
+    /// let mut ready = pin!(flags.wait_until(|v| v & 0b10 != 0));
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert!(ready.as_mut().poll(&mut cx).is_pending());
+    ///
+    /// flags.try_spin_compare_exchange_weak(|_| true, |v| v | 0b11);
+    /// assert_eq!(ready.as_mut().poll(&mut cx), Poll::Ready(0b11));
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn wait_until<'a, F>(
+        &'a self,
+        mut cond: F,
+    ) -> impl core::future::Future<Output = T> + 'a
+    where
+        F: FnMut(T) -> bool + 'a,
+    {
+        let addr = self.as_ref() as *const _ as usize;
+        crate::wait_table_::Wait::new(addr, move || {
+            let v = self.value();
+            cond(v).then_some(v)
+        })
+    }
+
+    /// Blocks the thread until the value satisfies `cond`, and returns that
+    /// value.
+    ///
+    /// This is the blocking counterpart of `wait_until`, sleeping on a futex
+    /// instead of spinning, and noticing the same updates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::{sync::atomic::AtomicU8, thread};
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(0));
+    /// thread::scope(|s| {
+    ///     s.spawn(|| flags.try_spin_compare_exchange(|_| true, |v| v | 1));
+    ///     assert_eq!(flags.block_until(|v| v != 0), 1);
+    /// });
+    /// ```
+    #[cfg(feature = "futex")]
+    pub fn block_until(&self, mut cond: impl FnMut(T) -> bool) -> T {
+        let mut v = self.value();
+        let addr = self.as_ref() as *const _ as usize;
+        crate::futex_::block_until(addr, || {
+            v = self.value();
+            cond(v)
+        });
+        v
+    }
+
+    /// Wakes up the waiters in `wait_until` or `block_until`, to check their
+    /// conditions again. Does nothing without the `async` and `futex`
+    /// features.
+    #[inline(always)]
+    pub fn wake_waiters(&self) {
+        let addr = self.as_ref() as *const _ as usize;
+        atomic_cell_::notify_waiters(addr, Ordering::Relaxed)
+    }
+}
+
+impl<T, B, O> AtomicFlags<T, B, O>
+where
+    T: TrAtomicData + Copy + BitAnd<Output = T> + Not<Output = T>,
+    <T as TrAtomicData>::AtomicCell: TrAtomicCell<Value = T>
+        + fetch::And<Value = T>,
+    B: BorrowMut<<T as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    /// Takes a snapshot of the current value to compare with later.
+    #[inline(always)]
+    pub fn snapshot(&self) -> FlagsSnapshot<T> {
+        FlagsSnapshot(self.value())
+    }
+
+    /// Compares the current value with a snapshot taken earlier.
+    ///
+    /// Returns the bits set since the snapshot and the bits cleared since the
+    /// snapshot, in this order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::AtomicU8;
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(0b0011));
+    /// let snapshot = flags.snapshot();
+    /// flags.fetch_take(0b0001);
+    /// flags.try_spin_compare_exchange_weak(|_| true, |v| v | 0b0100);
+    /// assert_eq!(flags.diff_since(&snapshot), (0b0100, 0b0001));
+    /// ```
+    #[inline(always)]
+    pub fn diff_since(&self, snapshot: &FlagsSnapshot<T>) -> (T, T) {
+        snapshot.diff(self.value())
+    }
+
+    /// Atomically clears the bits of `mask`, and returns those of them that
+    /// were set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::AtomicU8;
+    /// use atomex::{AtomicFlags, StrictOrderings};
+    ///
+    /// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+    ///     AtomicU8::new(0b0110));
+    /// assert_eq!(flags.fetch_take(0b0011), 0b0010);
+    /// assert_eq!(flags.value(), 0b0100);
+    /// ```
+    #[inline(always)]
+    pub fn fetch_take(&self, mask: T) -> T {
+        TrAtomicFlags::fetch_take(self, mask)
+    }
+}
+
+impl<T, O> AtomicFlags<T, <T as TrAtomicData>::AtomicCell, O>
+where
+    T: TrAtomicData + Copy,
+    <T as TrAtomicData>::AtomicCell: TrAtomicCell<Value = T>,
+    O: TrCmpxchOrderings,
+{
+    pub fn into_inner(self) -> <T as TrAtomicData>::AtomicCell {
+        self.0
+    }
+
+    /// Returns a mutable reference to the flags value, which needs no atomic
+    /// operation since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T
+    where
+        <T as TrAtomicData>::AtomicCell: TrAtomicCellMut,
+    {
+        TrAtomicCellMut::get_mut(&mut self.0)
+    }
+
+    /// The flags over the value at `ptr`, such as in a memory segment mapped
+    /// by several processes, which all update the same cell.
+    ///
+    /// The cell must have the size of `T`, as those of `core` do. The spin
+    /// loops work across processes, but the waiters woken up by the cell
+    /// under the `futex` or `async` features are those of this process only.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be aligned for the cell, which may be more than for `T`,
+    /// and valid for reads and writes for `'a`, during which the value is
+    /// only accessed atomically. It must hold a valid `T`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::AtomicFlags;
+    ///
+    /// const READY: u32 = 1;
+    ///
+    /// let mut segment = [0u32; 4];
+    /// let base = segment.as_mut_ptr();
+    /// // Safety: the segment outlives the flags and is only updated by them.
+    /// let state = unsafe { AtomicFlags::<u32>::from_raw_ptr(base) };
+    /// assert!(state.try_spin_compare_exchange_weak(
+    ///     |v| v & READY == 0,
+    ///     |v| v | READY,
+    /// ).is_succ());
+    /// assert_eq!(segment[0], READY);
+    /// ```
+    #[cfg(feature = "std")]
+    pub unsafe fn from_raw_ptr<'a>(ptr: *mut T) -> &'a Self {
+        const {
+            assert!(
+                core::mem::size_of::<<T as TrAtomicData>::AtomicCell>()
+                    == core::mem::size_of::<T>()
+            )
+        };
+        debug_assert!(
+            ptr.cast::<Self>().is_aligned(),
+            "[AtomicFlags::from_raw_ptr] misaligned pointer",
+        );
+        // Safety: the flags are transparent over the cell, which has the
+        // size of `T`, and the pointer is valid as required.
+        unsafe { &*ptr.cast::<Self>() }
+    }
 }
 
 impl<T, B, O> AsRef<<T as TrAtomicData>::AtomicCell>
@@ -157,3 +872,8 @@ where
         self.0.borrow().fmt(f)
     }
 }
+
+pub type AtomicFlagsOwned<T, O> =
+    AtomicFlags<T, <T as TrAtomicData>::AtomicCell, O>;
+pub type AtomicFlagsMut<'a, T, O> =
+    AtomicFlags<T, &'a mut <T as TrAtomicData>::AtomicCell, O>;
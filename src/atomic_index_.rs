@@ -0,0 +1,119 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+};
+use crate::{atomics_::AtomicUsize, AcqRelOrderings, TrCmpxchOrderings};
+
+/// An index in `0..N` whose increments wrap around at `N`, such as the head
+/// of a ring buffer whose capacity is not a power of two.
+///
+/// The index is kept below `N` at all times, so that it never has to be
+/// reduced by the readers, and is advanced with a CAS loop.
+///
+/// # Example
+///
+/// ```
+/// use atomex::AtomicIndex;
+///
+/// let head = AtomicIndex::<3>::new(1);
+/// assert_eq!(head.fetch_inc(), 1);
+/// assert_eq!(head.fetch_inc(), 2);
+/// assert_eq!(head.load(), 0);
+/// assert_eq!(head.fetch_add(5), 0);
+/// assert_eq!(head.load(), 2);
+/// ```
+pub struct AtomicIndex<const N: usize, O = AcqRelOrderings> {
+    index: AtomicUsize,
+    _orderings: PhantomData<O>,
+}
+
+impl<const N: usize, O: TrCmpxchOrderings> AtomicIndex<N, O> {
+    /// Creates an index at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `N`.
+    pub const fn new(index: usize) -> Self {
+        assert!(index < N, "[AtomicIndex::new] index out of range");
+        AtomicIndex {
+            index: AtomicUsize::new(index),
+            _orderings: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> usize {
+        self.index.load(O::LOAD_ORDERING)
+    }
+
+    /// Moves the index to `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `N`.
+    pub fn store(&self, index: usize) {
+        assert!(index < N, "[AtomicIndex::store] index out of range");
+        self.index.store(index, O::STORE_ORDERING)
+    }
+
+    /// Advances the index by one, wrapping around at `N`, and returns the
+    /// previous index.
+    #[inline(always)]
+    pub fn fetch_inc(&self) -> usize {
+        self.fetch_add(1)
+    }
+
+    /// Advances the index by `n`, wrapping around at `N`, and returns the
+    /// previous index.
+    pub fn fetch_add(&self, n: usize) -> usize {
+        let n = n % N;
+        let r = self.index.fetch_update(
+            O::SUCC_ORDERING,
+            O::FAIL_ORDERING,
+            |i| Option::Some(Self::wrap(i, n)),
+        );
+        r.unwrap_or_else(|i| i)
+    }
+
+    /// Moves the index from `current` to the one following it, wrapping
+    /// around at `N`.
+    ///
+    /// Returns `Ok(current)` if it moved, else `Err(actual)`.
+    pub fn compare_inc(&self, current: usize) -> Result<usize, usize> {
+        self.index.compare_exchange(
+            current,
+            Self::wrap(current, 1),
+            O::SUCC_ORDERING,
+            O::FAIL_ORDERING,
+        )
+    }
+
+    pub fn into_inner(self) -> usize {
+        self.index.into_inner()
+    }
+
+    /// `(i + n) % N`, for `i` and `n` below `N`, without overflowing.
+    #[inline(always)]
+    const fn wrap(i: usize, n: usize) -> usize {
+        if i >= N - n {
+            i - (N - n)
+        } else {
+            i + n
+        }
+    }
+}
+
+impl<const N: usize, O: TrCmpxchOrderings> Default for AtomicIndex<N, O> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<const N: usize, O: TrCmpxchOrderings> fmt::Debug for AtomicIndex<N, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicIndex")
+            .field("index", &self.load())
+            .field("len", &N)
+            .finish()
+    }
+}
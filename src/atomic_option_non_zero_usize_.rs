@@ -0,0 +1,122 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+    num::NonZeroUsize,
+};
+use crate::{atomics_::AtomicUsize, AcqRelOrderings, TrCmpxchOrderings};
+
+/// An atomic `Option<NonZeroUsize>`, stored as a `usize` with `0` for
+/// `None`, such as a slot holding a token or a handle.
+///
+/// # Example
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use atomex::AtomicOptionNonZeroUsize;
+///
+/// let token = |n| NonZeroUsize::new(n).unwrap();
+/// let owner: AtomicOptionNonZeroUsize = AtomicOptionNonZeroUsize::new(None);
+/// assert_eq!(owner.set_if_none(token(7)), Ok(()));
+/// assert_eq!(owner.set_if_none(token(8)), Err(token(7)));
+/// assert_eq!(
+///     owner.compare_exchange(Some(token(7)), Some(token(9))),
+///     Ok(Some(token(7))),
+/// );
+/// assert_eq!(owner.take(), Some(token(9)));
+/// assert_eq!(owner.load(), None);
+/// ```
+pub struct AtomicOptionNonZeroUsize<O = AcqRelOrderings>(
+    AtomicUsize,
+    PhantomData<O>,
+);
+
+impl<O: TrCmpxchOrderings> AtomicOptionNonZeroUsize<O> {
+    pub const fn new(value: Option<NonZeroUsize>) -> Self {
+        AtomicOptionNonZeroUsize(
+            AtomicUsize::new(Self::encode(value)),
+            PhantomData,
+        )
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(self.0.load(O::LOAD_ORDERING))
+    }
+
+    #[inline(always)]
+    pub fn is_none(&self) -> bool {
+        self.load().is_none()
+    }
+
+    pub fn store(&self, value: Option<NonZeroUsize>) {
+        self.0.store(Self::encode(value), O::STORE_ORDERING)
+    }
+
+    pub fn swap(&self, value: Option<NonZeroUsize>) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(self.0.swap(Self::encode(value), O::SWAP_ORDERING))
+    }
+
+    /// Stores `value` unless there is a value already.
+    ///
+    /// Returns `Err(current)` with the value there is if any.
+    pub fn set_if_none(&self, value: NonZeroUsize) -> Result<(), NonZeroUsize> {
+        match self.compare_exchange(Option::None, Option::Some(value)) {
+            Result::Ok(_) => Result::Ok(()),
+            Result::Err(Option::Some(x)) => Result::Err(x),
+            Result::Err(Option::None) => {
+                unreachable!("[AtomicOptionNonZeroUsize::set_if_none]")
+            }
+        }
+    }
+
+    /// Takes the value out, leaving `None`.
+    #[inline(always)]
+    pub fn take(&self) -> Option<NonZeroUsize> {
+        self.swap(Option::None)
+    }
+
+    /// Replaces the value with `new` if it is `current`.
+    ///
+    /// Returns `Ok(current)` if it was replaced, else `Err(actual)`.
+    pub fn compare_exchange(
+        &self,
+        current: Option<NonZeroUsize>,
+        new: Option<NonZeroUsize>,
+    ) -> Result<Option<NonZeroUsize>, Option<NonZeroUsize>> {
+        self.0
+            .compare_exchange(
+                Self::encode(current),
+                Self::encode(new),
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(NonZeroUsize::new)
+            .map_err(NonZeroUsize::new)
+    }
+
+    pub fn into_inner(self) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(self.0.into_inner())
+    }
+
+    #[inline(always)]
+    const fn encode(value: Option<NonZeroUsize>) -> usize {
+        match value {
+            Option::Some(x) => x.get(),
+            Option::None => 0,
+        }
+    }
+}
+
+impl<O: TrCmpxchOrderings> Default for AtomicOptionNonZeroUsize<O> {
+    fn default() -> Self {
+        Self::new(Option::None)
+    }
+}
+
+impl<O: TrCmpxchOrderings> fmt::Debug for AtomicOptionNonZeroUsize<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicOptionNonZeroUsize")
+            .field(&self.load())
+            .finish()
+    }
+}
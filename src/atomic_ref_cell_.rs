@@ -0,0 +1,228 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+use crate::RawSpinRwLock;
+
+/// A `RefCell` that can be shared among threads: the borrows are checked at
+/// runtime by a `RawSpinRwLock` word holding the writer bit and the number
+/// of readers, but never wait.
+///
+/// A borrow acquires the word and its end releases it, with the fixed
+/// orderings of the lock, so that a borrow sees the writes of the previous
+/// mutable one.
+///
+/// A conflicting borrow panics, or returns an error with `try_borrow` and
+/// `try_borrow_mut`, instead of spinning until the other borrows end, for
+/// values whose accesses are not supposed to overlap.
+///
+/// # Example
+///
+/// ```
+/// use atomex::AtomicRefCell;
+///
+/// let config = AtomicRefCell::<Vec<u8>>::new(vec![1]);
+/// {
+///     let r1 = config.borrow();
+///     let r2 = config.try_borrow().unwrap();
+///     assert_eq!(r1.len() + r2.len(), 2);
+///     assert!(config.try_borrow_mut().is_err());
+/// }
+/// config.borrow_mut().push(2);
+/// assert_eq!(*config.borrow(), [1, 2]);
+/// ```
+pub struct AtomicRefCell<T: ?Sized> {
+    borrow: RawSpinRwLock,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for AtomicRefCell<T> {}
+
+unsafe impl<T: ?Sized + Send + Sync> Sync for AtomicRefCell<T> {}
+
+impl<T> AtomicRefCell<T> {
+    pub const fn new(data: T) -> Self {
+        AtomicRefCell {
+            borrow: RawSpinRwLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> AtomicRefCell<T> {
+    /// Borrows the value immutably.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is mutably borrowed.
+    pub fn borrow(&self) -> AtomicRef<'_, T> {
+        match self.try_borrow() {
+            Result::Ok(r) => r,
+            Result::Err(_) => {
+                panic!("[AtomicRefCell::borrow] already mutably borrowed")
+            }
+        }
+    }
+
+    pub fn try_borrow(&self) -> Result<AtomicRef<'_, T>, BorrowError> {
+        if self.borrow.try_read() {
+            Result::Ok(AtomicRef::new(self))
+        } else {
+            Result::Err(BorrowError)
+        }
+    }
+
+    /// Borrows the value mutably.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is borrowed.
+    pub fn borrow_mut(&self) -> AtomicRefMut<'_, T> {
+        match self.try_borrow_mut() {
+            Result::Ok(r) => r,
+            Result::Err(_) => {
+                panic!("[AtomicRefCell::borrow_mut] already borrowed")
+            }
+        }
+    }
+
+    pub fn try_borrow_mut(
+        &self,
+    ) -> Result<AtomicRefMut<'_, T>, BorrowMutError> {
+        if self.borrow.try_write() {
+            Result::Ok(AtomicRefMut::new(self))
+        } else {
+            Result::Err(BorrowMutError)
+        }
+    }
+
+    /// Returns a mutable reference to the value, which needs no borrow
+    /// checking since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for AtomicRefCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AtomicRefCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("AtomicRefCell");
+        match self.try_borrow() {
+            Result::Ok(r) => d.field("data", &&*r),
+            Result::Err(_) => d.field("data", &format_args!("<borrowed>")),
+        };
+        d.finish()
+    }
+}
+
+/// The error of `AtomicRefCell::try_borrow` when the value is mutably
+/// borrowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BorrowError;
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("already mutably borrowed")
+    }
+}
+
+/// The error of `AtomicRefCell::try_borrow_mut` when the value is borrowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BorrowMutError;
+
+impl fmt::Display for BorrowMutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("already borrowed")
+    }
+}
+
+/// An immutable borrow of the value of an `AtomicRefCell`.
+pub struct AtomicRef<'a, T: ?Sized> {
+    cell: &'a AtomicRefCell<T>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for AtomicRef<'_, T> {}
+
+impl<'a, T: ?Sized> AtomicRef<'a, T> {
+    fn new(cell: &'a AtomicRefCell<T>) -> Self {
+        AtomicRef {
+            cell,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for AtomicRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for AtomicRef<'_, T> {
+    fn drop(&mut self) {
+        unsafe { self.cell.borrow.read_unlock() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AtomicRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// A mutable borrow of the value of an `AtomicRefCell`.
+pub struct AtomicRefMut<'a, T: ?Sized> {
+    cell: &'a AtomicRefCell<T>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for AtomicRefMut<'_, T> {}
+
+impl<'a, T: ?Sized> AtomicRefMut<'a, T> {
+    fn new(cell: &'a AtomicRefCell<T>) -> Self {
+        AtomicRefMut {
+            cell,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for AtomicRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for AtomicRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.cell.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for AtomicRefMut<'_, T> {
+    fn drop(&mut self) {
+        unsafe { self.cell.borrow.write_unlock() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AtomicRefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
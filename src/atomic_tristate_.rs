@@ -0,0 +1,122 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+};
+use crate::{atomics_::AtomicU8, AcqRelOrderings, TrCmpxchOrderings};
+
+/// An atomic `Option<bool>`, for decisions that are unknown until one of
+/// the threads makes them.
+///
+/// # Example
+///
+/// ```
+/// use atomex::AtomicTristate;
+///
+/// let approved: AtomicTristate = AtomicTristate::new(None);
+/// assert_eq!(approved.set_if_unset(false), Ok(()));
+/// assert_eq!(approved.set_if_unset(true), Err(false));
+/// assert_eq!(
+///     approved.compare_exchange(Some(false), Some(true)),
+///     Ok(Some(false)),
+/// );
+/// assert_eq!(approved.take(), Some(true));
+/// assert_eq!(approved.load(), None);
+/// ```
+pub struct AtomicTristate<O = AcqRelOrderings>(AtomicU8, PhantomData<O>);
+
+impl<O: TrCmpxchOrderings> AtomicTristate<O> {
+    const UNSET: u8 = 0;
+    const FALSE: u8 = 1;
+    const TRUE: u8 = 2;
+
+    pub const fn new(state: Option<bool>) -> Self {
+        AtomicTristate(AtomicU8::new(Self::encode(state)), PhantomData)
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> Option<bool> {
+        Self::decode(self.0.load(O::LOAD_ORDERING))
+    }
+
+    #[inline(always)]
+    pub fn is_unset(&self) -> bool {
+        self.load().is_none()
+    }
+
+    pub fn store(&self, state: Option<bool>) {
+        self.0.store(Self::encode(state), O::STORE_ORDERING)
+    }
+
+    pub fn swap(&self, state: Option<bool>) -> Option<bool> {
+        Self::decode(self.0.swap(Self::encode(state), O::SWAP_ORDERING))
+    }
+
+    /// Decides `value` unless a decision was made already.
+    ///
+    /// Returns `Err(decision)` with the earlier decision if there is one.
+    pub fn set_if_unset(&self, value: bool) -> Result<(), bool> {
+        match self.compare_exchange(Option::None, Option::Some(value)) {
+            Result::Ok(_) => Result::Ok(()),
+            Result::Err(x) => Result::Err(x == Option::Some(true)),
+        }
+    }
+
+    /// Takes the decision out, leaving the state unknown.
+    #[inline(always)]
+    pub fn take(&self) -> Option<bool> {
+        self.swap(Option::None)
+    }
+
+    /// Replaces the state with `new` if it is `current`.
+    ///
+    /// Returns `Ok(current)` if it was replaced, else `Err(actual)`.
+    pub fn compare_exchange(
+        &self,
+        current: Option<bool>,
+        new: Option<bool>,
+    ) -> Result<Option<bool>, Option<bool>> {
+        self.0
+            .compare_exchange(
+                Self::encode(current),
+                Self::encode(new),
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(Self::decode)
+            .map_err(Self::decode)
+    }
+
+    pub fn into_inner(self) -> Option<bool> {
+        Self::decode(self.0.into_inner())
+    }
+
+    #[inline(always)]
+    const fn encode(state: Option<bool>) -> u8 {
+        match state {
+            Option::None => Self::UNSET,
+            Option::Some(false) => Self::FALSE,
+            Option::Some(true) => Self::TRUE,
+        }
+    }
+
+    #[inline(always)]
+    const fn decode(byte: u8) -> Option<bool> {
+        match byte {
+            Self::UNSET => Option::None,
+            Self::FALSE => Option::Some(false),
+            _ => Option::Some(true),
+        }
+    }
+}
+
+impl<O: TrCmpxchOrderings> Default for AtomicTristate<O> {
+    fn default() -> Self {
+        Self::new(Option::None)
+    }
+}
+
+impl<O: TrCmpxchOrderings> fmt::Debug for AtomicTristate<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicTristate").field(&self.load()).finish()
+    }
+}
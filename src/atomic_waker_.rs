@@ -0,0 +1,149 @@
+﻿use core::{fmt, ptr, sync::atomic::Ordering, task::Waker};
+use alloc::boxed::Box;
+use crate::{
+    atomics_::{AtomicPtr, AtomicUsize},
+    AcqRelOrderings, AtomexPtr,
+};
+
+/// A slot for the `Waker` of the one task waiting on an event, shared with
+/// the side signaling it.
+///
+/// `register` is meant to be called by the polled task only, while `wake` and
+/// `take` can be called from anywhere at any time. A wake-up racing with a
+/// registration is never lost: the registering task is woken instead.
+///
+/// The waker is boxed behind an `AtomexPtr`, which a state word grants to
+/// one side at a time. A registration only allocates when the waker changes.
+///
+/// # Example
+///
+/// ```
+/// use core::task::{Context, Waker};
+/// use atomex::AtomicWaker;
+///
+/// let slot = AtomicWaker::new();
+/// let cx = Context::from_waker(Waker::noop());
+///
+/// slot.register(cx.waker());
+/// assert!(slot.take().is_some());
+/// assert!(slot.take().is_none());
+/// slot.wake();
+/// ```
+pub struct AtomicWaker {
+    state: AtomicUsize,
+    waker: AtomexPtr<Waker, AtomicPtr<Waker>, AcqRelOrderings>,
+}
+
+impl AtomicWaker {
+    /// Neither registering nor waking.
+    const WAITING: usize = 0;
+
+    /// A registration is replacing the waker.
+    const REGISTERING: usize = 1;
+
+    /// A wake-up is taking the waker, or has raced with a registration.
+    const WAKING: usize = 2;
+
+    pub const fn new() -> Self {
+        AtomicWaker {
+            state: AtomicUsize::new(Self::WAITING),
+            waker: AtomexPtr::new(AtomicPtr::new(ptr::null_mut())),
+        }
+    }
+
+    /// Stores `waker` to be woken by the next `wake`, replacing the one
+    /// stored before, if any.
+    ///
+    /// If a wake-up is in progress, `waker` is woken at once instead.
+    pub fn register(&self, waker: &Waker) {
+        match self.state.compare_exchange(
+            Self::WAITING,
+            Self::REGISTERING,
+            Ordering::Acquire,
+            Ordering::Acquire,
+        ) {
+            Result::Ok(_) => {
+                // Safety: the `REGISTERING` state keeps the wake-ups from
+                // taking the waker, which is only freed by whoever takes it.
+                let same = self
+                    .waker
+                    .load()
+                    .is_some_and(|w| unsafe { w.as_ref() }.will_wake(waker));
+                if !same {
+                    let new = Box::into_raw(Box::new(waker.clone()));
+                    drop(Self::unbox(self.waker.swap(new)));
+                }
+                let r = self.state.compare_exchange(
+                    Self::REGISTERING,
+                    Self::WAITING,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                );
+                if r.is_err() {
+                    // A wake-up came meanwhile and could not take the waker,
+                    // so it is done here.
+                    let w = Self::unbox(self.waker.swap(ptr::null_mut()));
+                    self.state.swap(Self::WAITING, Ordering::AcqRel);
+                    if let Option::Some(w) = w {
+                        w.wake();
+                    }
+                }
+            }
+            Result::Err(Self::WAKING) => waker.wake_by_ref(),
+            // A concurrent registration, which is a misuse; either waker may
+            // end up stored.
+            Result::Err(_) => (),
+        }
+    }
+
+    /// Wakes the registered waker, if any, and empties the slot.
+    pub fn wake(&self) {
+        if let Option::Some(w) = self.take() {
+            w.wake();
+        }
+    }
+
+    /// Takes the registered waker out of the slot, if any.
+    pub fn take(&self) -> Option<Waker> {
+        match self.state.fetch_or(Self::WAKING, Ordering::AcqRel) {
+            Self::WAITING => {
+                // The `WAKING` state keeps the registrations from reading or
+                // replacing the waker.
+                let w = Self::unbox(self.waker.swap(ptr::null_mut()));
+                self.state.fetch_and(!Self::WAKING, Ordering::Release);
+                w
+            }
+            // Either a registration will see the `WAKING` bit and wake its
+            // waker, or another wake-up is taking the slot.
+            _ => Option::None,
+        }
+    }
+
+    /// Takes the waker out of its box, which the caller owns, if any.
+    fn unbox(p: *mut Waker) -> Option<Waker> {
+        // Safety: the pointers stored come from `Box::into_raw`, and each
+        // one is swapped out exactly once.
+        (!p.is_null()).then(|| *unsafe { Box::from_raw(p) })
+    }
+}
+
+impl Default for AtomicWaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for AtomicWaker {
+    fn drop(&mut self) {
+        drop(Self::unbox(*self.waker.get_mut()));
+    }
+}
+
+impl fmt::Debug for AtomicWaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicWaker")
+            .field("state", &self.state.load(Ordering::Relaxed))
+            .field("is_registered", &self.waker.load().is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,35 @@
+﻿//! The atomic types the crate is built on.
+//!
+//! These are the ones of `core` for the widths the target has native
+//! compare-and-swap instructions for. With the `portable-atomic` feature,
+//! the other widths are taken from `portable-atomic`, which emulates them,
+//! e.g. in critical sections on AVR and MSP430 with its `critical-section`
+//! feature, that the `critical-section` feature of this crate turns on.
+//!
+//! The types of the widths the target has natively are never replaced, so
+//! that enabling the feature changes no type on such targets.
+
+#[cfg(target_has_atomic = "8")]
+pub(crate) use core::sync::atomic::{AtomicBool, AtomicI8, AtomicU8};
+#[cfg(all(feature = "portable-atomic", not(target_has_atomic = "8")))]
+pub(crate) use portable_atomic::{AtomicBool, AtomicI8, AtomicU8};
+
+#[cfg(target_has_atomic = "16")]
+pub(crate) use core::sync::atomic::{AtomicI16, AtomicU16};
+#[cfg(all(feature = "portable-atomic", not(target_has_atomic = "16")))]
+pub(crate) use portable_atomic::{AtomicI16, AtomicU16};
+
+#[cfg(target_has_atomic = "32")]
+pub(crate) use core::sync::atomic::{AtomicI32, AtomicU32};
+#[cfg(all(feature = "portable-atomic", not(target_has_atomic = "32")))]
+pub(crate) use portable_atomic::{AtomicI32, AtomicU32};
+
+#[cfg(target_has_atomic = "64")]
+pub(crate) use core::sync::atomic::{AtomicI64, AtomicU64};
+#[cfg(all(feature = "portable-atomic", not(target_has_atomic = "64")))]
+pub(crate) use portable_atomic::{AtomicI64, AtomicU64};
+
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) use core::sync::atomic::{AtomicIsize, AtomicPtr, AtomicUsize};
+#[cfg(all(feature = "portable-atomic", not(target_has_atomic = "ptr")))]
+pub(crate) use portable_atomic::{AtomicIsize, AtomicPtr, AtomicUsize};
@@ -0,0 +1,113 @@
+﻿//! Recording of the atomic operations performed through atomex types, to
+//! review whether the orderings pair up as intended.
+//!
+//! Every operation made through `TrAtomicCell` or one of the `fetch` traits
+//! on a cell of the crate, be it a `core` atomic, an `Emulated64`, a `CsCell`,
+//! a `RegisterCell` or a `RadiumCell`, calls the hook installed with
+//! [`set_hook`], right after the operation is performed and on the thread
+//! performing it, so the hook can record the thread itself. Operations made
+//! directly on the `core` atomics, bypassing the traits, are not recorded.
+//!
+//! # Example
+//!
+//! ```
+//! use core::sync::atomic::{AtomicUsize, Ordering};
+//! use atomex::{audit::{self, AuditOp, AuditRecord}, AtomicCountOwned};
+//!
+//! static LOADS: AtomicUsize = AtomicUsize::new(0);
+//!
+//! fn on_op(r: &AuditRecord) {
+//!     if r.op == AuditOp::Load && r.cell.ends_with("AtomicU32") {
+//!         LOADS.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! audit::set_hook(Some(on_op));
+//! let cnt = AtomicCountOwned::<u32>::default();
+//! cnt.val();
+//! audit::set_hook(None);
+//! assert_eq!(LOADS.load(Ordering::Relaxed), 1);
+//! ```
+use core::{
+    any, mem, ptr,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::AtomicPtr;
+
+/// The kind of an audited atomic operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AuditOp {
+    Load,
+    Store,
+    Swap,
+    CompareExchange,
+    CompareExchangeWeak,
+    FetchAnd,
+    FetchNand,
+    FetchOr,
+    FetchXor,
+    FetchAdd,
+    FetchSub,
+    FetchUpdate,
+    FetchMax,
+    FetchMin,
+}
+
+/// An atomic operation as passed to the audit hook.
+#[derive(Clone, Copy, Debug)]
+pub struct AuditRecord {
+    /// The type name of the atomic cell.
+    pub cell: &'static str,
+
+    /// The address of the atomic cell.
+    pub addr: *const (),
+
+    pub op: AuditOp,
+
+    /// The ordering of the operation, which is the success ordering for the
+    /// compare-exchange operations and the set ordering for `FetchUpdate`.
+    pub order: Ordering,
+
+    /// The failure ordering for the compare-exchange operations and the
+    /// fetch ordering for `FetchUpdate`.
+    pub fail_order: Option<Ordering>,
+}
+
+pub type AuditHook = fn(&AuditRecord);
+
+static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Installs the hook called on every audited operation, or removes it with
+/// `None`, returning the previous one.
+pub fn set_hook(hook: Option<AuditHook>) -> Option<AuditHook> {
+    let p = hook.map_or(ptr::null_mut(), |f| f as *mut ());
+    let prev = HOOK.swap(p, Ordering::AcqRel);
+    to_hook(prev)
+}
+
+pub(crate) fn record<C>(
+    cell: &C,
+    op: AuditOp,
+    order: Ordering,
+    fail_order: Option<Ordering>,
+) {
+    let Option::Some(hook) = to_hook(HOOK.load(Ordering::Acquire)) else {
+        return;
+    };
+    hook(&AuditRecord {
+        cell: any::type_name::<C>(),
+        addr: cell as *const C as *const (),
+        op,
+        order,
+        fail_order,
+    })
+}
+
+fn to_hook(p: *mut ()) -> Option<AuditHook> {
+    if p.is_null() {
+        Option::None
+    } else {
+        // Only `set_hook` stores into `HOOK`, always from an `AuditHook`.
+        Option::Some(unsafe { mem::transmute::<*mut (), AuditHook>(p) })
+    }
+}
@@ -0,0 +1,426 @@
+﻿use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use core::time::Duration;
+
+use crate::relax::{DefaultRelax, TrRelax};
+
+/// The policy of waiting between two attempts of a spin loop.
+pub trait TrBackoff {
+    /// Waits after a failed attempt, for longer as the failures accumulate.
+    fn backoff(&mut self);
+
+    /// Starts over, as if no attempt had failed yet.
+    fn reset(&mut self);
+}
+
+impl<B: TrBackoff + ?Sized> TrBackoff for &mut B {
+    #[inline(always)]
+    fn backoff(&mut self) {
+        (**self).backoff()
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// Retries at once, which is the behaviour of the spin loops without a
+/// backoff policy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoBackoff;
+
+impl TrBackoff for NoBackoff {
+    #[inline(always)]
+    fn backoff(&mut self) {}
+
+    #[inline(always)]
+    fn reset(&mut self) {}
+}
+
+/// Issues a single hint of the relax policy `R` between two attempts.
+#[derive(Clone, Copy, Debug)]
+pub struct SpinBackoff<R: TrRelax = DefaultRelax>(PhantomData<R>);
+
+impl SpinBackoff {
+    pub const fn new() -> Self {
+        SpinBackoff(PhantomData)
+    }
+}
+
+impl<R: TrRelax> SpinBackoff<R> {
+    /// Issues the hints of the relax policy `R2` instead.
+    pub const fn with_relax<R2: TrRelax>(self) -> SpinBackoff<R2> {
+        SpinBackoff(PhantomData)
+    }
+}
+
+impl<R: TrRelax> Default for SpinBackoff<R> {
+    fn default() -> Self {
+        SpinBackoff::new().with_relax()
+    }
+}
+
+impl<R: TrRelax> TrBackoff for SpinBackoff<R> {
+    #[inline(always)]
+    fn backoff(&mut self) {
+        R::relax()
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {}
+}
+
+/// Waits for an event, with the `wfe` instruction of ARM targets when the
+/// `wfe` feature is on, else issues a spin hint.
+///
+/// The core sleeps until an interrupt, or an event sent by `send_event` from
+/// another core, which costs far less power than spinning. It may also wake
+/// up spuriously, so the condition waited for has to be checked again.
+#[inline(always)]
+pub fn wait_for_event() {
+    #[cfg(all(
+        feature = "wfe",
+        any(target_arch = "arm", target_arch = "aarch64"),
+    ))]
+    // Safety: `wfe` only pauses the core.
+    unsafe {
+        core::arch::asm!("wfe", options(nomem, nostack, preserves_flags))
+    };
+    #[cfg(not(all(
+        feature = "wfe",
+        any(target_arch = "arm", target_arch = "aarch64"),
+    )))]
+    crate::relax::relax();
+}
+
+/// Wakes up the cores waiting in `wait_for_event`, with the `sev`
+/// instruction of ARM targets when the `wfe` feature is on, else does
+/// nothing.
+///
+/// The stores before it are completed before the event is sent, so that the
+/// cores woken up see them.
+#[inline(always)]
+pub fn send_event() {
+    #[cfg(all(feature = "wfe", target_arch = "aarch64"))]
+    // Safety: a barrier and an event only.
+    unsafe {
+        core::arch::asm!("dsb ishst", "sev", options(nostack, preserves_flags))
+    };
+    #[cfg(all(feature = "wfe", target_arch = "arm"))]
+    // Safety: as above.
+    unsafe {
+        core::arch::asm!("dsb", "sev", options(nostack, preserves_flags))
+    };
+}
+
+/// Spins with the relax policy `R` for the first `spin_steps` failures, and
+/// then waits for an event with `wait_for_event` after every failure, for
+/// spin loops on ARM cores whose power matters.
+///
+/// The waiting only ends early if the thread making progress sends an event
+/// when it is done. The modifications of `AtomicFlags` that wake up the
+/// waiters, such as the unlocking of `SpinLock` and `SpinRwLock`, do so;
+/// after other ones, call `send_event`.
+///
+/// # Example
+///
+/// ```
+/// use atomex::{SpinLock, WfeBackoff};
+///
+/// let lock = SpinLock::<u32>::new(0);
+/// *lock.lock_with_backoff(WfeBackoff::new()) += 1;
+/// assert_eq!(*lock.lock(), 1);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct WfeBackoff<R: TrRelax = DefaultRelax> {
+    failures: u32,
+    spin_steps: u32,
+    relax: PhantomData<R>,
+}
+
+impl WfeBackoff {
+    pub const DEFAULT_SPIN_STEPS: u32 = 4;
+
+    pub const fn new() -> Self {
+        Self::with_spin_steps(Self::DEFAULT_SPIN_STEPS)
+    }
+
+    /// Create an instance spinning once per failure for the first
+    /// `spin_steps` failures.
+    pub const fn with_spin_steps(spin_steps: u32) -> Self {
+        WfeBackoff {
+            failures: 0,
+            spin_steps,
+            relax: PhantomData,
+        }
+    }
+}
+
+impl<R: TrRelax> WfeBackoff<R> {
+    /// Spins with the relax policy `R2` instead.
+    pub const fn with_relax<R2: TrRelax>(self) -> WfeBackoff<R2> {
+        WfeBackoff {
+            failures: self.failures,
+            spin_steps: self.spin_steps,
+            relax: PhantomData,
+        }
+    }
+
+    /// Returns `true` once done spinning.
+    #[inline(always)]
+    pub const fn is_waiting(&self) -> bool {
+        self.failures >= self.spin_steps
+    }
+}
+
+impl<R: TrRelax> Default for WfeBackoff<R> {
+    fn default() -> Self {
+        WfeBackoff::new().with_relax()
+    }
+}
+
+impl<R: TrRelax> TrBackoff for WfeBackoff<R> {
+    fn backoff(&mut self) {
+        if self.is_waiting() {
+            wait_for_event();
+        } else {
+            R::relax();
+            self.failures += 1;
+        }
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// Doubles the number of hints of the relax policy `R` after every failed
+/// attempt, up to `2^max_step`, and then calls the yield hook, if any,
+/// instead of spinning any longer.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicU8;
+/// use atomex::{AtomicFlags, ExpBackoff, StrictOrderings};
+///
+/// let flags = AtomicFlags::<u8, AtomicU8, StrictOrderings>::new(
+///     AtomicU8::new(0));
+/// let mut backoff = ExpBackoff::new();
+/// let r = flags.try_spin_compare_exchange_weak_with_backoff(
+///     &mut backoff,
+///     |_| true,
+///     |v| v + 1,
+/// );
+/// assert!(r.is_succ());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ExpBackoff<R: TrRelax = DefaultRelax> {
+    step: u32,
+    max_step: u32,
+    on_yield: Option<fn()>,
+    relax: PhantomData<R>,
+}
+
+impl ExpBackoff {
+    pub const DEFAULT_MAX_STEP: u32 = 6;
+
+    /// The bound `max_step` is clamped to, beyond which a single step would
+    /// spin for so long that it had better yield.
+    pub const MAX_STEP: u32 = 16;
+
+    pub const fn new() -> Self {
+        Self::with_max_step(Self::DEFAULT_MAX_STEP)
+    }
+
+    /// Create an instance spinning at most `2^max_step` times in a row, with
+    /// `max_step` clamped to `MAX_STEP`.
+    pub const fn with_max_step(max_step: u32) -> Self {
+        ExpBackoff {
+            step: 0,
+            max_step: if max_step > Self::MAX_STEP {
+                Self::MAX_STEP
+            } else {
+                max_step
+            },
+            on_yield: Option::None,
+            relax: PhantomData,
+        }
+    }
+}
+
+impl<R: TrRelax> ExpBackoff<R> {
+    /// Spins with the relax policy `R2` instead.
+    pub const fn with_relax<R2: TrRelax>(self) -> ExpBackoff<R2> {
+        ExpBackoff {
+            step: self.step,
+            max_step: self.max_step,
+            on_yield: self.on_yield,
+            relax: PhantomData,
+        }
+    }
+
+    /// Sets the hook called instead of spinning once the spinning is at its
+    /// longest, such as a yield to the scheduler of the platform.
+    pub const fn with_yield(mut self, on_yield: fn()) -> Self {
+        self.on_yield = Option::Some(on_yield);
+        self
+    }
+
+    /// Returns `true` once the spinning is at its longest.
+    #[inline(always)]
+    pub const fn is_saturated(&self) -> bool {
+        self.step >= self.max_step
+    }
+}
+
+impl<R: TrRelax> Default for ExpBackoff<R> {
+    fn default() -> Self {
+        ExpBackoff::new().with_relax()
+    }
+}
+
+impl<R: TrRelax> TrBackoff for ExpBackoff<R> {
+    fn backoff(&mut self) {
+        if self.is_saturated() {
+            if let Option::Some(on_yield) = self.on_yield {
+                return on_yield();
+            }
+        }
+        for _ in 0..(1u32 << self.step) {
+            R::relax();
+        }
+        if !self.is_saturated() {
+            self.step += 1;
+        }
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.step = 0;
+    }
+}
+
+/// Escalates from spin hints to yielding the thread to the scheduler, and
+/// optionally to short sleeps, as the failed attempts accumulate.
+///
+/// It spins as `ExpBackoff` does, with the relax policy `R`, for the first
+/// `spin_steps` failures, then yields for the next `yield_steps` failures,
+/// and then sleeps for the configured duration if any, else keeps yielding.
+///
+/// # Example
+///
+/// ```
+/// use core::time::Duration;
+/// use atomex::{TrBackoff, YieldBackoff};
+///
+/// let mut backoff = YieldBackoff::new()
+///     .with_spin_steps(2)
+///     .with_yield_steps(1)
+///     .with_sleep(Duration::from_micros(50));
+/// for _ in 0..4 {
+///     backoff.backoff();
+/// }
+/// assert!(backoff.is_sleeping());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct YieldBackoff<R: TrRelax = DefaultRelax> {
+    failures: u32,
+    spin_steps: u32,
+    yield_steps: u32,
+    sleep: Option<Duration>,
+    relax: PhantomData<R>,
+}
+
+#[cfg(feature = "std")]
+impl YieldBackoff {
+    pub const DEFAULT_SPIN_STEPS: u32 = 6;
+    pub const DEFAULT_YIELD_STEPS: u32 = 10;
+
+    pub const fn new() -> Self {
+        YieldBackoff {
+            failures: 0,
+            spin_steps: Self::DEFAULT_SPIN_STEPS,
+            yield_steps: Self::DEFAULT_YIELD_STEPS,
+            sleep: Option::None,
+            relax: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: TrRelax> YieldBackoff<R> {
+    /// Spins with the relax policy `R2` instead.
+    pub const fn with_relax<R2: TrRelax>(self) -> YieldBackoff<R2> {
+        YieldBackoff {
+            failures: self.failures,
+            spin_steps: self.spin_steps,
+            yield_steps: self.yield_steps,
+            sleep: self.sleep,
+            relax: PhantomData,
+        }
+    }
+
+    /// Sets the number of failures to spin after, the last one spinning
+    /// `2^(spin_steps - 1)` times, with `spin_steps` clamped to 32.
+    pub const fn with_spin_steps(mut self, spin_steps: u32) -> Self {
+        self.spin_steps = if spin_steps > 32 { 32 } else { spin_steps };
+        self
+    }
+
+    /// Sets the number of failures to yield after, once done spinning.
+    pub const fn with_yield_steps(mut self, yield_steps: u32) -> Self {
+        self.yield_steps = yield_steps;
+        self
+    }
+
+    /// Sets the duration to sleep after every failure, once done yielding.
+    pub const fn with_sleep(mut self, sleep: Duration) -> Self {
+        self.sleep = Option::Some(sleep);
+        self
+    }
+
+    /// Returns `true` once done spinning and yielding.
+    pub const fn is_sleeping(&self) -> bool {
+        self.sleep.is_some()
+            && self.failures >= self.yielded_after()
+    }
+
+    /// The number of failures after which the yielding is over.
+    const fn yielded_after(&self) -> u32 {
+        self.spin_steps.saturating_add(self.yield_steps)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: TrRelax> Default for YieldBackoff<R> {
+    fn default() -> Self {
+        YieldBackoff::new().with_relax()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: TrRelax> TrBackoff for YieldBackoff<R> {
+    fn backoff(&mut self) {
+        if self.failures < self.spin_steps {
+            for _ in 0..(1u64 << self.failures) {
+                R::relax();
+            }
+        } else if self.failures < self.yielded_after() {
+            std::thread::yield_now();
+        } else if let Option::Some(d) = self.sleep {
+            std::thread::sleep(d);
+        } else {
+            std::thread::yield_now();
+        }
+        self.failures = self.failures.saturating_add(1);
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
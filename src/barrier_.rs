@@ -0,0 +1,116 @@
+﻿use core::fmt;
+use crate::{
+    atomics_::AtomicUsize, waiters_::Waiters, AcqRelOrderings, AtomicFlags,
+};
+
+/// A reusable barrier making a fixed number of participants wait for each
+/// other, phase after phase.
+///
+/// The count of arrived participants is kept in the lower half of a word and
+/// the generation, i.e. the number of completed phases, in the upper half,
+/// so that both change in a single compare-exchange.
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use atomex::Barrier;
+///
+/// let barrier = Arc::new(Barrier::new(4));
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let barrier = barrier.clone();
+///         thread::spawn(move || {
+///             let a = barrier.wait().is_leader();
+///             let b = barrier.wait().is_leader();
+///             usize::from(a) + usize::from(b)
+///         })
+///     })
+///     .collect();
+/// let leaders: usize = workers.into_iter().map(|w| w.join().unwrap()).sum();
+/// assert_eq!(leaders, 2);
+/// assert_eq!(barrier.generation(), 2);
+/// ```
+pub struct Barrier {
+    state: AtomicFlags<usize, AtomicUsize, AcqRelOrderings>,
+    n: usize,
+    waiters: Waiters,
+}
+
+/// Returned by `Barrier::wait`, telling apart the one participant that
+/// completed the phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    #[inline(always)]
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    const SHIFT: u32 = usize::BITS / 2;
+    const ARRIVED_MASK: usize = (1 << Self::SHIFT) - 1;
+
+    /// Creates a barrier for `n` participants, where `0` is taken as `1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` does not fit in half a `usize`.
+    pub const fn new(n: usize) -> Self {
+        assert!(
+            n <= Self::ARRIVED_MASK,
+            "[Barrier::new] too many participants",
+        );
+        Barrier {
+            state: AtomicFlags::new(AtomicUsize::new(0)),
+            n: if n == 0 { 1 } else { n },
+            waiters: Waiters::new(),
+        }
+    }
+
+    /// The number of completed phases, wrapping around at half a `usize`.
+    #[inline(always)]
+    pub fn generation(&self) -> usize {
+        self.state.value() >> Self::SHIFT
+    }
+
+    /// Waits until all the participants have arrived at the barrier.
+    ///
+    /// The last participant to arrive completes the phase without waiting,
+    /// and is the leader. The others sleep until the leader wakes them up
+    /// with the `std` feature, and spin on the generation without it.
+    pub fn wait(&self) -> BarrierWaitResult {
+        let n = self.n;
+        let r = self.state.try_spin_compare_exchange_weak(
+            |_| true,
+            |s| {
+                if (s & Self::ARRIVED_MASK) + 1 == n {
+                    (s >> Self::SHIFT).wrapping_add(1) << Self::SHIFT
+                } else {
+                    s + 1
+                }
+            },
+        );
+        let prev = r.into_inner();
+        if (prev & Self::ARRIVED_MASK) + 1 == n {
+            self.waiters.notify_all();
+            return BarrierWaitResult(true);
+        }
+        let generation = prev >> Self::SHIFT;
+        self.waiters.wait_until(|| self.generation() != generation);
+        BarrierWaitResult(false)
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.state.value();
+        f.debug_struct("Barrier")
+            .field("participants", &self.n)
+            .field("arrived", &(s & Self::ARRIVED_MASK))
+            .field("generation", &(s >> Self::SHIFT))
+            .finish()
+    }
+}
@@ -0,0 +1,372 @@
+﻿use core::{borrow::BorrowMut, cell::Cell, fmt, marker::PhantomData};
+use crate::{
+    AtomicFlags, CmpxchResult, TrAtomicCell, TrAtomicData, TrCmpxchOrderings,
+};
+
+/// Describes a field of `width` bits starting at bit `offset` of an integer
+/// word.
+pub struct BitField<T> {
+    offset: u32,
+    width: u32,
+    _word: PhantomData<T>,
+}
+
+impl<T> BitField<T> {
+    /// # Panics
+    ///
+    /// Panics if the field is empty or does not fit in `T`, at compile time
+    /// when evaluated in a constant.
+    ///
+    /// ```should_panic
+    /// use atomex::BitField;
+    ///
+    /// let _ = BitField::<u8>::new(4, 5);
+    /// ```
+    pub const fn new(offset: u32, width: u32) -> Self {
+        let bits = (core::mem::size_of::<T>() * 8) as u32;
+        assert!(
+            width > 0 && width <= bits && offset <= bits - width,
+            "[BitField::new] field out of the word",
+        );
+        BitField {
+            offset,
+            width,
+            _word: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    #[inline(always)]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The bit next to the last bit of the field, which is also the offset
+    /// of a field packed right after this one.
+    #[inline(always)]
+    pub const fn end(&self) -> u32 {
+        self.offset + self.width
+    }
+}
+
+impl<T: funty::Integral> BitField<T> {
+    /// The largest value the field can hold.
+    pub fn max_value(&self) -> T {
+        if self.width >= T::BITS {
+            !T::ZERO
+        } else {
+            (T::ONE << self.width as usize) - T::ONE
+        }
+    }
+
+    /// The mask selecting the bits of the field in the word.
+    #[inline(always)]
+    pub fn mask(&self) -> T {
+        self.max_value() << self.offset as usize
+    }
+
+    /// Reads the field out of a word.
+    #[inline(always)]
+    pub fn extract(&self, word: T) -> T {
+        (word & self.mask()) >> self.offset as usize
+    }
+
+    /// Returns the word with the field replaced by `value`.
+    ///
+    /// The bits of `value` that do not fit in the field are discarded, which
+    /// is a programming error detected in debug builds.
+    pub fn insert(&self, word: T, value: T) -> T {
+        debug_assert!(
+            value <= self.max_value(),
+            "[BitField::insert] value overflows the field",
+        );
+        (word & !self.mask()) | ((value << self.offset as usize) & self.mask())
+    }
+}
+
+impl<T> Clone for BitField<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BitField<T> {}
+
+impl<T> PartialEq for BitField<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset && self.width == other.width
+    }
+}
+
+impl<T> Eq for BitField<T> {}
+
+impl<T> fmt::Debug for BitField<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BitField[{}..{}]", self.offset, self.end())
+    }
+}
+
+impl<T, B, O> AtomicFlags<T, B, O>
+where
+    T: TrAtomicData + funty::Integral,
+    <T as TrAtomicData>::AtomicCell: TrAtomicCell<Value = T>,
+    B: BorrowMut<<T as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    /// Loads the word and reads the field out of it.
+    #[inline(always)]
+    pub fn load_field(&self, field: BitField<T>) -> T {
+        field.extract(self.value())
+    }
+
+    /// Replaces the field with `value`, leaving the other bits of the word
+    /// untouched, and returns the previous value of the field.
+    pub fn store_field(&self, field: BitField<T>, value: T) -> T {
+        let r = self.try_spin_compare_exchange_weak(
+            |_| true,
+            |w| field.insert(w, value),
+        );
+        field.extract(r.into_inner())
+    }
+
+    /// Stores the value of the field computed by `f` from the current one,
+    /// unless `f` returns `None`.
+    ///
+    /// Returns `Ok(previous_value)` of the field if it was updated, else
+    /// `Err(current_value)`.
+    pub fn update_field<F>(&self, field: BitField<T>, mut f: F) -> Result<T, T>
+    where
+        F: FnMut(T) -> Option<T>,
+    {
+        let desired = Cell::new(Option::None);
+        let r = self.try_spin_compare_exchange_weak(
+            |w| {
+                desired.set(f(field.extract(w)));
+                desired.get().is_some()
+            },
+            |w| match desired.get() {
+                Option::Some(v) => field.insert(w, v),
+                Option::None => w,
+            },
+        );
+        match r {
+            CmpxchResult::Succ(w) => Result::Ok(field.extract(w)),
+            r => Result::Err(field.extract(r.into_inner())),
+        }
+    }
+
+    /// Replaces the field with `desired` if it is currently `current`.
+    pub fn compare_exchange_field(
+        &self,
+        field: BitField<T>,
+        current: T,
+        desired: T,
+    ) -> Result<T, T> {
+        self.update_field(field, |v| (v == current).then_some(desired))
+    }
+
+    /// Adds `val` to the field, failing instead of overflowing into the
+    /// neighbouring bits.
+    pub fn fetch_add_field(&self, field: BitField<T>, val: T) -> Result<T, T> {
+        let max = field.max_value();
+        self.update_field(field, |v| v.checked_add(val).filter(|x| *x <= max))
+    }
+
+    /// Subtracts `val` from the field, failing instead of underflowing.
+    pub fn fetch_sub_field(&self, field: BitField<T>, val: T) -> Result<T, T> {
+        self.update_field(field, |v| v.checked_sub(val))
+    }
+}
+
+/// Declares several multi-bit fields packed into one atomic word.
+///
+/// Fields are packed from the least significant bit in declaration order,
+/// each one becoming an associated `BitField` constant of the generated type.
+/// The total width is checked against the width of the integer at compile
+/// time. The generated type forwards the field operations of `AtomicFlags` as
+/// `get`, `set`, `update`, `compare_exchange`, `fetch_add` and `fetch_sub`.
+///
+/// # Example
+///
+/// ```
+/// atomex::define_bitfields! {
+///     pub struct RtState: u32 {
+///         STATE: 3;
+///         REFS: 20;
+///         GEN: 9;
+///     }
+/// }
+///
+/// type S = RtState;
+/// let s: RtState = RtState::new(0);
+/// assert_eq!(s.set(S::STATE, 5), 0);
+/// assert_eq!(s.fetch_add(S::REFS, 2), Ok(0));
+/// assert_eq!(s.compare_exchange(S::STATE, 5, 1), Ok(5));
+/// assert_eq!(s.get(S::STATE), 1);
+/// assert_eq!(s.get(S::REFS), 2);
+/// assert_eq!(s.fetch_add(S::GEN, 512), Err(0));
+/// assert_eq!(s.value(), 1 | 2 << 3);
+/// ```
+#[macro_export]
+macro_rules! define_bitfields {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident : $int:ty {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident : $width:expr;
+            )*
+        }
+    ) => {
+        const _: () = ::core::assert!(
+            0 $(+ $width)* <= <$int>::BITS,
+            ::core::concat!(
+                "fields of ",
+                ::core::stringify!($name),
+                " overflow the word",
+            ),
+        );
+
+        $(#[$meta])*
+        $vis struct $name<O = $crate::StrictOrderings>(
+            $crate::AtomicFlags<
+                $int,
+                <$int as $crate::TrAtomicData>::AtomicCell,
+                O,
+            >,
+        )
+        where
+            O: $crate::TrCmpxchOrderings;
+
+        #[allow(dead_code)]
+        impl<O> $name<O>
+        where
+            O: $crate::TrCmpxchOrderings,
+        {
+            $crate::define_bitfields!(
+                @fields $int, 0;
+                $( $(#[$field_meta])* $field : $width; )*
+            );
+
+            /// Create an instance with the initial packed word.
+            pub fn new(word: $int) -> Self {
+                $name($crate::AtomicFlags::new(
+                    <<$int as $crate::TrAtomicData>::AtomicCell
+                        as $crate::TrAtomicCell>::new(word),
+                ))
+            }
+
+            /// Loads the whole packed word.
+            #[inline(always)]
+            pub fn value(&self) -> $int {
+                self.0.value()
+            }
+
+            /// The underlying `AtomicFlags`, for operations over the word.
+            #[inline(always)]
+            pub fn as_flags(&self) -> &$crate::AtomicFlags<
+                $int,
+                <$int as $crate::TrAtomicData>::AtomicCell,
+                O,
+            > {
+                &self.0
+            }
+
+            #[inline(always)]
+            pub fn get(&self, field: $crate::BitField<$int>) -> $int {
+                self.0.load_field(field)
+            }
+
+            #[inline(always)]
+            pub fn set(&self, field: $crate::BitField<$int>, v: $int) -> $int {
+                self.0.store_field(field, v)
+            }
+
+            #[inline(always)]
+            pub fn update<F>(
+                &self,
+                field: $crate::BitField<$int>,
+                f: F,
+            ) -> ::core::result::Result<$int, $int>
+            where
+                F: FnMut($int) -> ::core::option::Option<$int>,
+            {
+                self.0.update_field(field, f)
+            }
+
+            #[inline(always)]
+            pub fn compare_exchange(
+                &self,
+                field: $crate::BitField<$int>,
+                current: $int,
+                desired: $int,
+            ) -> ::core::result::Result<$int, $int> {
+                self.0.compare_exchange_field(field, current, desired)
+            }
+
+            #[inline(always)]
+            pub fn fetch_add(
+                &self,
+                field: $crate::BitField<$int>,
+                val: $int,
+            ) -> ::core::result::Result<$int, $int> {
+                self.0.fetch_add_field(field, val)
+            }
+
+            #[inline(always)]
+            pub fn fetch_sub(
+                &self,
+                field: $crate::BitField<$int>,
+                val: $int,
+            ) -> ::core::result::Result<$int, $int> {
+                self.0.fetch_sub_field(field, val)
+            }
+        }
+
+        impl<O> ::core::default::Default for $name<O>
+        where
+            O: $crate::TrCmpxchOrderings,
+        {
+            fn default() -> Self {
+                Self::new(0)
+            }
+        }
+
+        impl<O> ::core::fmt::Debug for $name<O>
+        where
+            O: $crate::TrCmpxchOrderings,
+        {
+            fn fmt(
+                &self,
+                f: &mut ::core::fmt::Formatter<'_>,
+            ) -> ::core::fmt::Result {
+                let word = self.value();
+                f.debug_struct(::core::stringify!($name))
+                    $(.field(
+                        ::core::stringify!($field),
+                        &Self::$field.extract(word),
+                    ))*
+                    .finish()
+            }
+        }
+    };
+
+    (@fields $int:ty, $offset:expr; ) => {};
+
+    (
+        @fields $int:ty, $offset:expr;
+        $(#[$field_meta:meta])*
+        $field:ident : $width:expr;
+        $($rest:tt)*
+    ) => {
+        $(#[$field_meta])*
+        pub const $field: $crate::BitField<$int> =
+            $crate::BitField::new($offset, $width);
+
+        $crate::define_bitfields!(@fields $int, Self::$field.end(); $($rest)*);
+    };
+}
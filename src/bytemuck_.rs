@@ -0,0 +1,128 @@
+﻿//! Implementations of `bytemuck::Zeroable` for the types whose all-zero
+//! bytes are a valid value, and the constructors of that value, so that they
+//! can be placed in zeroed memory such as static arenas and DMA buffers.
+//!
+//! The zero value is the default one of every such type: a zero count, no
+//! flag set, a null pointer with a zero tag.
+//!
+//! ```
+//! use atomex::{x_deps::bytemuck, AtomicFlags, CachePadded};
+//!
+//! type Slot = CachePadded<AtomicFlags<u32>>;
+//!
+//! static SLOTS: [Slot; 4] = [const { Slot::zeroed() }; 4];
+//!
+//! SLOTS[1].try_spin_compare_exchange_weak(|_| true, |v| v | 1);
+//! assert_eq!(SLOTS[1].value(), 1);
+//!
+//! let arena: [Slot; 2] = bytemuck::Zeroable::zeroed();
+//! assert_eq!(arena[0].value(), 0);
+//! ```
+
+use core::mem;
+
+use bytemuck::Zeroable;
+
+use crate::{
+    fetch, AtomicCount, AtomicFlags, CachePadded, DwCas, TrAtomicCell,
+    TrAtomicData, TrCmpxchOrderings, TrCountOrderings,
+};
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+use crate::LwwRegister;
+#[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+use crate::{AtomexPackedPtr, PackedPtrTag};
+
+// Safety: the padding is not part of the value.
+unsafe impl<T: Zeroable> Zeroable for CachePadded<T> {}
+
+impl<T: Zeroable> CachePadded<T> {
+    pub const fn zeroed() -> Self {
+        // Safety: as above.
+        unsafe { mem::zeroed() }
+    }
+}
+
+// Safety: the pair `(0, 0)`, with the sequence of the emulation, if any, at
+// `0`.
+unsafe impl Zeroable for DwCas {}
+
+impl DwCas {
+    pub const fn zeroed() -> Self {
+        // Safety: as above.
+        unsafe { mem::zeroed() }
+    }
+}
+
+// Safety: a timestamp and a value of `0`.
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+unsafe impl<O: TrCmpxchOrderings> Zeroable for LwwRegister<O> {}
+
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+impl<O: TrCmpxchOrderings> LwwRegister<O> {
+    pub const fn zeroed() -> Self {
+        LwwRegister::new(0, 0)
+    }
+}
+
+// Safety: the null pointer with the tag `0`.
+#[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+unsafe impl<T> Zeroable for PackedPtrTag<T> {}
+
+// Safety: as above.
+#[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+unsafe impl<T, O: TrCmpxchOrderings> Zeroable for AtomexPackedPtr<T, O> {}
+
+#[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+impl<T, O: TrCmpxchOrderings> AtomexPackedPtr<T, O> {
+    pub const fn zeroed() -> Self {
+        // Safety: as above.
+        unsafe { mem::zeroed() }
+    }
+}
+
+// Safety: the cells of the integers are all those of the crate, which hold
+// `0` when zeroed.
+unsafe impl<T, O> Zeroable
+for AtomicFlags<T, <T as TrAtomicData>::AtomicCell, O>
+where
+    T: TrAtomicData + funty::Integral,
+    <T as TrAtomicData>::AtomicCell: TrAtomicCell<Value = T>,
+    O: TrCmpxchOrderings,
+{}
+
+impl<T, O> AtomicFlags<T, <T as TrAtomicData>::AtomicCell, O>
+where
+    T: TrAtomicData + funty::Integral,
+    <T as TrAtomicData>::AtomicCell: TrAtomicCell<Value = T>,
+    O: TrCmpxchOrderings,
+{
+    pub const fn zeroed() -> Self {
+        // Safety: as above.
+        unsafe { mem::zeroed() }
+    }
+}
+
+// Safety: as above.
+unsafe impl<V, O> Zeroable
+for AtomicCount<V, <V as TrAtomicData>::AtomicCell, O>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{}
+
+impl<V, O> AtomicCount<V, <V as TrAtomicData>::AtomicCell, O>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    pub const fn zeroed() -> Self {
+        // Safety: as above.
+        unsafe { mem::zeroed() }
+    }
+}
@@ -0,0 +1,111 @@
+﻿use core::{
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomics_::{AtomicBool, AtomicUsize}, AtomicCount, RelaxedCountOrderings,
+};
+
+type Hand = AtomicCount<usize, AtomicUsize, RelaxedCountOrderings>;
+
+/// The hand of the clock (second chance) eviction policy over `N` slots,
+/// such as the entries of a fixed-size cache.
+///
+/// Accessing a slot sets its reference bit. Looking for a victim moves the
+/// hand over the slots, clearing the reference bits it passes, and stops at
+/// the first slot whose bit was already clear: a slot is only evicted if it
+/// was not accessed since the hand last passed it. Several threads may look
+/// for victims at once, each getting different slots unless the hand goes
+/// full circle meanwhile.
+///
+/// # Example
+///
+/// ```
+/// use atomex::ClockHand;
+///
+/// let clock = ClockHand::<4>::new();
+/// clock.touch(0);
+/// clock.touch(1);
+/// assert_eq!(clock.next_victim(), 2);
+/// clock.touch(3);
+/// assert_eq!(clock.next_victim(), 0);
+/// assert!(!clock.is_referenced(1));
+/// ```
+pub struct ClockHand<const N: usize> {
+    hand: Hand,
+    referenced: [AtomicBool; N],
+}
+
+impl<const N: usize> ClockHand<N> {
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "[ClockHand] no slot") };
+        ClockHand {
+            hand: AtomicCount::new(AtomicUsize::new(0)),
+            referenced: [const { AtomicBool::new(false) }; N],
+        }
+    }
+
+    #[inline(always)]
+    pub const fn slot_count(&self) -> usize {
+        N
+    }
+
+    /// The slot the hand points at, to be examined next.
+    #[inline(always)]
+    pub fn hand(&self) -> usize {
+        self.hand.val() % N
+    }
+
+    /// Marks the slot at `index` as accessed, giving it a second chance.
+    #[inline(always)]
+    pub fn touch(&self, index: usize) {
+        self.referenced[index].store(true, Ordering::Relaxed);
+    }
+
+    /// Clears the reference bit of the slot at `index`, such as when its
+    /// entry is removed.
+    #[inline(always)]
+    pub fn untouch(&self, index: usize) {
+        self.referenced[index].store(false, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn is_referenced(&self, index: usize) -> bool {
+        self.referenced[index].load(Ordering::Relaxed)
+    }
+
+    /// Moves the hand to the next slot not accessed since it last passed,
+    /// clearing the reference bits on the way, and returns its index.
+    ///
+    /// Loops as long as other threads keep touching the slots ahead of the
+    /// hand, at most twice around the clock otherwise.
+    pub fn next_victim(&self) -> usize {
+        loop {
+            let index = self.hand.wrapping_add(1) % N;
+            if !self.referenced[index].swap(false, Ordering::Relaxed) {
+                return index;
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for ClockHand<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ClockHand<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let referenced = self
+            .referenced
+            .iter()
+            .filter(|r| r.load(Ordering::Relaxed))
+            .count();
+        f.debug_struct("ClockHand")
+            .field("slot_count", &N)
+            .field("hand", &self.hand())
+            .field("referenced", &referenced)
+            .finish()
+    }
+}
@@ -9,6 +9,10 @@ pub enum CmpxchResult<T> {
 
     /// The compare_exchange is not performed because the value is unexpected.
     Unexpected(T),
+
+    /// The compare_exchange kept failing in contending until the bounded
+    /// number of attempts ran out, with the last value observed.
+    Exhausted(T),
 }
 
 impl<T> CmpxchResult<T> {
@@ -17,6 +21,17 @@ impl<T> CmpxchResult<T> {
             CmpxchResult::Succ(t) => t,
             CmpxchResult::Fail(t) => t,
             CmpxchResult::Unexpected(t) => t,
+            CmpxchResult::Exhausted(t) => t,
+        }
+    }
+
+    /// Maps the contained value while keeping the variant.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> CmpxchResult<U> {
+        match self {
+            CmpxchResult::Succ(t) => CmpxchResult::Succ(f(t)),
+            CmpxchResult::Fail(t) => CmpxchResult::Fail(f(t)),
+            CmpxchResult::Unexpected(t) => CmpxchResult::Unexpected(f(t)),
+            CmpxchResult::Exhausted(t) => CmpxchResult::Exhausted(f(t)),
         }
     }
 
@@ -32,6 +47,10 @@ impl<T> CmpxchResult<T> {
         matches!(self, CmpxchResult::Unexpected(_))
     }
 
+    pub const fn is_exhausted(&self) -> bool {
+        matches!(self, CmpxchResult::Exhausted(_))
+    }
+
     pub fn succ(self) -> Option<T> {
         match self {
             CmpxchResult::Succ(t) => Option::Some(t),
@@ -52,6 +71,13 @@ impl<T> CmpxchResult<T> {
             _ => Option::None,
         }
     }
+
+    pub fn exhausted(self) -> Option<T> {
+        match self {
+            CmpxchResult::Exhausted(t) => Option::Some(t),
+            _ => Option::None,
+        }
+    }
 }
 
 impl<T> From<CmpxchResult<T>> for Result<T, T> {
@@ -60,6 +86,18 @@ impl<T> From<CmpxchResult<T>> for Result<T, T> {
             CmpxchResult::Succ(t) => Result::Ok(t),
             CmpxchResult::Fail(t) => Result::Err(t),
             CmpxchResult::Unexpected(t) => Result::Err(t),
+            CmpxchResult::Exhausted(t) => Result::Err(t),
         }
     }
 }
+
+/// The result of a spinning compare-exchange together with the number of
+/// compare-exchange attempts it took.
+#[derive(Debug, Clone)]
+pub struct CmpxchOutcome<T> {
+    pub result: CmpxchResult<T>,
+
+    /// The number of compare-exchange operations performed, which is zero if
+    /// the first value loaded was already unexpected.
+    pub attempts: usize,
+}
@@ -0,0 +1,79 @@
+﻿use core::fmt;
+use crate::{
+    atomics_::AtomicUsize, waiters_::Waiters, AtomicCount, RefCountOrderings,
+    TrBackoff,
+};
+
+/// A latch opened once it has been counted down from its initial count to
+/// zero, e.g. to wait for a number of workers to start up.
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use atomex::CountdownLatch;
+///
+/// let started = Arc::new(CountdownLatch::new(4));
+/// for _ in 0..4 {
+///     let started = started.clone();
+///     thread::spawn(move || started.count_down());
+/// }
+/// started.wait();
+/// assert!(started.is_open());
+/// ```
+pub struct CountdownLatch {
+    count: AtomicCount<usize, AtomicUsize, RefCountOrderings>,
+    waiters: Waiters,
+}
+
+impl CountdownLatch {
+    pub const fn new(count: usize) -> Self {
+        CountdownLatch {
+            count: AtomicCount::new(AtomicUsize::new(count)),
+            waiters: Waiters::new(),
+        }
+    }
+
+    /// The remaining count before the latch opens.
+    #[inline(always)]
+    pub fn count(&self) -> usize {
+        self.count.val()
+    }
+
+    #[inline(always)]
+    pub fn is_open(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// Decrements the count, opening the latch and waking up the waiters if
+    /// it reaches zero. Does nothing if the latch is already open.
+    pub fn count_down(&self) {
+        if self.count.try_dec_nonzero() == Result::Ok(1) {
+            self.waiters.notify_all();
+        }
+    }
+
+    /// Waits until the latch is open.
+    ///
+    /// With the `std` feature, the thread sleeps until the last `count_down`
+    /// wakes it up. Without it, the thread spins on the count.
+    pub fn wait(&self) {
+        self.waiters.wait_until(|| self.is_open())
+    }
+
+    /// Spins until the latch is open, waiting according to `backoff` between
+    /// two checks.
+    pub fn wait_with_backoff(&self, mut backoff: impl TrBackoff) {
+        while !self.is_open() {
+            backoff.backoff();
+        }
+    }
+}
+
+impl fmt::Debug for CountdownLatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountdownLatch")
+            .field("count", &self.count())
+            .finish()
+    }
+}
@@ -0,0 +1,222 @@
+﻿//! Implementations of the cell traits for the `AtomicCell` of
+//! `crossbeam-utils`, so that the code generic over them accepts cells
+//! shared with code using crossbeam.
+//!
+//! `TrAtomicCell` is implemented for any `AtomicCell<T>` of a `Copy + Eq`
+//! value, which crossbeam guards with a lock when no atomic instruction fits
+//! `T`. The fetch traits are implemented for the integers and `bool` only
+//! where the target has atomics of their width, so that they are lock-free.
+//!
+//! The orderings are ignored: crossbeam always loads with `Acquire`, stores
+//! with `Release`, and operates the rest with `AcqRel`.
+//!
+//! ```
+//! use core::sync::atomic::Ordering;
+//! use atomex::{
+//!     fetch::Add, x_deps::crossbeam_utils::atomic::AtomicCell, TrAtomicCell,
+//! };
+//!
+//! fn bump<C>(hits: &C) -> u32
+//! where
+//!     C: TrAtomicCell<Value = u32> + Add<Value = u32>,
+//! {
+//!     hits.fetch_add(1, Ordering::Relaxed)
+//! }
+//!
+//! let hits = AtomicCell::new(41u32);
+//! assert_eq!(bump(&hits), 41);
+//! assert_eq!(hits.load(), 42);
+//! ```
+
+use core::sync::atomic::Ordering;
+
+use crossbeam_utils::atomic::AtomicCell;
+
+use crate::{fetch, Bitwise, NumOps, TrAtomicCell, TrAtomicCellMut};
+
+impl<T: Copy + Eq> TrAtomicCell for AtomicCell<T> {
+    type Value = T;
+
+    #[inline(always)]
+    fn new(val: T) -> Self {
+        AtomicCell::new(val)
+    }
+
+    #[inline(always)]
+    fn into_inner(self) -> T {
+        AtomicCell::into_inner(self)
+    }
+
+    #[inline(always)]
+    fn load(&self, _: Ordering) -> T {
+        AtomicCell::load(self)
+    }
+
+    #[inline(always)]
+    fn store(&self, val: T, _: Ordering) {
+        AtomicCell::store(self, val)
+    }
+
+    #[inline(always)]
+    fn swap(&self, val: T, _: Ordering) -> T {
+        AtomicCell::swap(self, val)
+    }
+
+    #[inline(always)]
+    fn compare_exchange(
+        &self,
+        current: T,
+        desired: T,
+        _: Ordering,
+        _: Ordering,
+    ) -> Result<T, T> {
+        AtomicCell::compare_exchange(self, current, desired)
+    }
+
+    #[inline(always)]
+    fn compare_exchange_weak(
+        &self,
+        current: T,
+        desired: T,
+        _: Ordering,
+        _: Ordering,
+    ) -> Result<T, T> {
+        AtomicCell::compare_exchange(self, current, desired)
+    }
+}
+
+impl<T: Copy + Eq> TrAtomicCellMut for AtomicCell<T> {
+    #[inline(always)]
+    fn get_mut(&mut self) -> &mut T {
+        // Safety: the access is exclusive.
+        unsafe { &mut *self.as_ptr() }
+    }
+}
+
+macro_rules! impl_crossbeam_cell {
+    ($width:literal : $primitive:ty ; $( $traits:tt ),*) => {
+        $(
+            impl_crossbeam_cell!(__impl $traits $width $primitive);
+        )*
+    };
+
+    (__impl bitwise $width:literal $primitive:ty) => {
+        #[cfg(target_has_atomic = $width)]
+        impl Bitwise for AtomicCell<$primitive> {}
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::And for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_and(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_and(self, val)
+            }
+        }
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Nand for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_nand(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_nand(self, val)
+            }
+        }
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Or for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_or(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_or(self, val)
+            }
+        }
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Xor for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_xor(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_xor(self, val)
+            }
+        }
+    };
+
+    (__impl numops $width:literal $primitive:ty) => {
+        #[cfg(target_has_atomic = $width)]
+        impl NumOps for AtomicCell<$primitive> {}
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Add for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_add(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_add(self, val)
+            }
+        }
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Sub for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_sub(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_sub(self, val)
+            }
+        }
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Update for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_update<F>(
+                &self,
+                _: Ordering,
+                _: Ordering,
+                f: F,
+            ) -> Result<Self::Value, Self::Value>
+            where
+                F: FnMut(Self::Value) -> Option<Self::Value>,
+            {
+                AtomicCell::<$primitive>::fetch_update(self, f)
+            }
+        }
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Max for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_max(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_max(self, val)
+            }
+        }
+
+        #[cfg(target_has_atomic = $width)]
+        impl fetch::Min for AtomicCell<$primitive> {
+            type Value = $primitive;
+
+            #[inline(always)]
+            fn fetch_min(&self, val: Self::Value, _: Ordering) -> Self::Value {
+                AtomicCell::<$primitive>::fetch_min(self, val)
+            }
+        }
+    };
+}
+
+impl_crossbeam_cell!("8": bool; bitwise);
+impl_crossbeam_cell!("8": i8; bitwise, numops);
+impl_crossbeam_cell!("16": i16; bitwise, numops);
+impl_crossbeam_cell!("32": i32; bitwise, numops);
+impl_crossbeam_cell!("64": i64; bitwise, numops);
+impl_crossbeam_cell!("ptr": isize; bitwise, numops);
+impl_crossbeam_cell!("8": u8; bitwise, numops);
+impl_crossbeam_cell!("16": u16; bitwise, numops);
+impl_crossbeam_cell!("32": u32; bitwise, numops);
+impl_crossbeam_cell!("64": u64; bitwise, numops);
+impl_crossbeam_cell!("ptr": usize; bitwise, numops);
@@ -0,0 +1,352 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomic_cell_::audit, fetch, Bitwise, NumOps, TrAtomicCell, TrAtomicCellMut,
+};
+
+/// An atomic cell emulated by accessing a plain value inside a critical
+/// section, for targets with no compare-and-swap instructions, such as AVR,
+/// MSP430 or `thumbv6m`.
+///
+/// Every operation runs in `critical_section::with`, so that it is atomic
+/// with regard to the other operations on the cell, including from interrupt
+/// handlers, as long as the critical section implementation linked in covers
+/// them. The orderings are ignored, since entering and leaving a critical
+/// section are full barriers.
+///
+/// On the targets without native atomics of a width, `TrAtomicData` maps the
+/// integers and `bool` of that width to their `CsCell`, so that the wrappers
+/// of this crate take it as their `B` parameter there.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use atomex::{fetch::Add, CsCell, TrAtomicCell};
+///
+/// static TICKS: CsCell<u32> = CsCell::new(0);
+///
+/// TICKS.fetch_add(3, Ordering::Relaxed);
+/// let r = TICKS.compare_exchange(3, 5, Ordering::AcqRel, Ordering::Acquire);
+/// assert_eq!(r, Ok(3));
+/// assert_eq!(TICKS.load(Ordering::Acquire), 5);
+/// ```
+pub struct CsCell<T: Copy>(UnsafeCell<T>);
+
+// Safety: the value is only accessed in critical sections.
+unsafe impl<T: Copy + Send> Sync for CsCell<T> {}
+
+impl<T: Copy> CsCell<T> {
+    pub const fn new(value: T) -> Self {
+        CsCell(UnsafeCell::new(value))
+    }
+
+    /// Calls `f` on the value inside a critical section, and returns what it
+    /// returns.
+    #[inline(always)]
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        // Safety: nothing else accesses the value during the critical
+        // section.
+        critical_section::with(|_| f(unsafe { &mut *self.0.get() }))
+    }
+
+    /// Replaces the value with `f` of it, and returns the previous one.
+    #[inline(always)]
+    fn fetch_apply(&self, f: impl FnOnce(T) -> T) -> T {
+        self.with(|x| {
+            let prev = *x;
+            *x = f(prev);
+            prev
+        })
+    }
+}
+
+impl<T: Copy + PartialEq> CsCell<T> {
+    /// Replaces the value with `desired` if it is `current`.
+    fn compare_and_set(&self, current: T, desired: T) -> Result<T, T> {
+        self.with(|x| {
+            if *x == current {
+                *x = desired;
+                Result::Ok(current)
+            } else {
+                Result::Err(*x)
+            }
+        })
+    }
+}
+
+impl<T: Copy + Default> Default for CsCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for CsCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CsCell").field(&self.with(|x| *x)).finish()
+    }
+}
+
+impl<T: Copy + PartialEq> TrAtomicCell for CsCell<T> {
+    type Value = T;
+
+    #[inline(always)]
+    fn new(val: T) -> Self {
+        CsCell::new(val)
+    }
+
+    #[inline(always)]
+    fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+
+    #[inline(always)]
+    fn load(&self, order: Ordering) -> T {
+        let r = self.with(|x| *x);
+        audit!(self, Load, order);
+        r
+    }
+
+    #[inline(always)]
+    fn store(&self, val: T, order: Ordering) {
+        self.with(|x| *x = val);
+        audit!(self, Store, order);
+    }
+
+    #[inline(always)]
+    fn swap(&self, val: T, order: Ordering) -> T {
+        let r = self.fetch_apply(|_| val);
+        audit!(self, Swap, order);
+        r
+    }
+
+    fn compare_exchange(
+        &self,
+        current: T,
+        desired: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        let r = self.compare_and_set(current, desired);
+        audit!(self, CompareExchange, success, Option::Some(failure));
+        r
+    }
+
+    #[inline(always)]
+    fn compare_exchange_weak(
+        &self,
+        current: T,
+        desired: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        let r = self.compare_and_set(current, desired);
+        audit!(self, CompareExchangeWeak, success, Option::Some(failure));
+        r
+    }
+}
+
+impl<T: Copy + PartialEq> TrAtomicCellMut for CsCell<T> {
+    #[inline(always)]
+    fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut()
+    }
+}
+
+macro_rules! impl_cs_cell {
+    ($primitive:ty ; $( $traits:tt ),*) => {
+        $(
+            impl_cs_cell!(__impl $traits $primitive);
+        )*
+    };
+
+    (__impl bitwise $primitive:ty) => {
+        impl Bitwise for CsCell<$primitive> {}
+
+        impl fetch::And for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_and(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| x & val);
+                audit!(self, FetchAnd, order);
+                r
+            }
+        }
+
+        impl fetch::Nand for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_nand(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| !(x & val));
+                audit!(self, FetchNand, order);
+                r
+            }
+        }
+
+        impl fetch::Or for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_or(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| x | val);
+                audit!(self, FetchOr, order);
+                r
+            }
+        }
+
+        impl fetch::Xor for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_xor(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| x ^ val);
+                audit!(self, FetchXor, order);
+                r
+            }
+        }
+    };
+
+    (__impl numops $primitive:ty) => {
+        impl NumOps for CsCell<$primitive> {}
+
+        impl fetch::Add for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_add(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| x.wrapping_add(val));
+                audit!(self, FetchAdd, order);
+                r
+            }
+        }
+
+        impl fetch::Sub for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_sub(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| x.wrapping_sub(val));
+                audit!(self, FetchSub, order);
+                r
+            }
+        }
+
+        impl fetch::Update for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                mut f: F,
+            ) -> Result<Self::Value, Self::Value>
+            where
+                F: FnMut(Self::Value) -> Option<Self::Value>,
+            {
+                let r = self.with(|x| match f(*x) {
+                    Option::Some(new) => {
+                        let prev = *x;
+                        *x = new;
+                        Result::Ok(prev)
+                    }
+                    Option::None => Result::Err(*x),
+                });
+                audit!(self, FetchUpdate, set_order, Option::Some(fetch_order));
+                r
+            }
+        }
+
+        impl fetch::Max for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_max(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| x.max(val));
+                audit!(self, FetchMax, order);
+                r
+            }
+        }
+
+        impl fetch::Min for CsCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_min(
+                &self,
+                val: Self::Value,
+                order: Ordering,
+            ) -> Self::Value {
+                let r = self.fetch_apply(|x| x.min(val));
+                audit!(self, FetchMin, order);
+                r
+            }
+        }
+    };
+}
+
+impl_cs_cell!(bool; bitwise);
+impl_cs_cell!(i8; bitwise, numops);
+impl_cs_cell!(i16; bitwise, numops);
+impl_cs_cell!(i32; bitwise, numops);
+impl_cs_cell!(i64; bitwise, numops);
+impl_cs_cell!(isize; bitwise, numops);
+impl_cs_cell!(u8; bitwise, numops);
+impl_cs_cell!(u16; bitwise, numops);
+impl_cs_cell!(u32; bitwise, numops);
+impl_cs_cell!(u64; bitwise, numops);
+impl_cs_cell!(usize; bitwise, numops);
+
+macro_rules! impl_cs_data {
+    ($width:literal : $( $primitive:ty ),*) => {
+        $(
+            #[cfg(not(any(
+                target_has_atomic = $width,
+                feature = "portable-atomic",
+            )))]
+            impl crate::TrAtomicData for $primitive {
+                type AtomicCell = CsCell<$primitive>;
+            }
+        )*
+    };
+}
+
+impl_cs_data!("8": bool, i8, u8);
+impl_cs_data!("16": i16, u16);
+impl_cs_data!("32": i32, u32);
+impl_cs_data!("64": i64);
+impl_cs_data!("ptr": isize, usize);
+
+// With 32-bit atomics, `Emulated64` is lock-free for the readers.
+#[cfg(not(any(
+    target_has_atomic = "64",
+    target_has_atomic = "32",
+    feature = "portable-atomic",
+)))]
+impl crate::TrAtomicData for u64 {
+    type AtomicCell = CsCell<u64>;
+}
@@ -0,0 +1,155 @@
+﻿/// Declares a typed flag word over `AtomicFlags` with named bit positions.
+///
+/// Each flag is declared with its bit position followed by the names of the
+/// methods to generate for it, in the order `is`, `set`, `clear`, `take`:
+///
+/// - `is_*` loads the word and tests the bit;
+/// - `set_*` sets the bit, returning whether it was set before;
+/// - `clear_*` clears the bit;
+/// - `take_*` clears the bit, returning whether it was set before.
+///
+/// Like the modifications made on the `AtomicFlags` itself, those made by
+/// `set_*`, `clear_*` and `take_*` wake up its waiters.
+///
+/// The generated type is generic over the orderings with `StrictOrderings`
+/// as default, and also gets an associated constant for every flag mask.
+///
+/// # Example
+///
+/// ```
+/// atomex::define_flags! {
+///     /// The lifecycle flags of a task.
+///     pub struct TaskFlags: u8 {
+///         READY = 0 => is_ready, set_ready, clear_ready, take_ready;
+///         CLOSED = 1 => is_closed, set_closed, clear_closed, take_closed;
+///     }
+/// }
+///
+/// let flags: TaskFlags = TaskFlags::new(0);
+/// assert!(!flags.set_ready());
+/// assert!(flags.is_ready());
+/// assert!(!flags.is_closed());
+/// assert_eq!(flags.value(), TaskFlags::<atomex::StrictOrderings>::READY);
+/// assert_eq!(format!("{flags:?}"), "{READY}");
+/// assert!(flags.take_ready());
+/// assert!(!flags.take_ready());
+/// ```
+#[macro_export]
+macro_rules! define_flags {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident : $int:ty {
+            $(
+                $(#[$flag_meta:meta])*
+                $flag:ident = $bit:expr =>
+                    $is:ident, $set:ident, $clear:ident, $take:ident;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name<O = $crate::StrictOrderings>(
+            $crate::AtomicFlags<
+                $int,
+                <$int as $crate::TrAtomicData>::AtomicCell,
+                O,
+            >,
+        )
+        where
+            O: $crate::TrCmpxchOrderings;
+
+        #[allow(dead_code)]
+        impl<O> $name<O>
+        where
+            O: $crate::TrCmpxchOrderings,
+        {
+            $(
+                $(#[$flag_meta])*
+                pub const $flag: $int = 1 << $bit;
+            )*
+
+            /// Create an instance with the initial flag bits.
+            pub fn new(bits: $int) -> Self {
+                $name($crate::AtomicFlags::new(
+                    <<$int as $crate::TrAtomicData>::AtomicCell
+                        as $crate::TrAtomicCell>::new(bits),
+                ))
+            }
+
+            /// Loads all the flag bits.
+            #[inline(always)]
+            pub fn value(&self) -> $int {
+                self.0.value()
+            }
+
+            /// The underlying `AtomicFlags`, for operations over several flags.
+            #[inline(always)]
+            pub fn as_flags(&self) -> &$crate::AtomicFlags<
+                $int,
+                <$int as $crate::TrAtomicData>::AtomicCell,
+                O,
+            > {
+                &self.0
+            }
+
+            $(
+                #[inline(always)]
+                pub fn $is(&self) -> bool {
+                    self.value() & Self::$flag != 0
+                }
+
+                #[inline(always)]
+                pub fn $set(&self) -> bool {
+                    // Through the compare-exchange of `AtomicFlags`, so that
+                    // the waiters on the word see the change.
+                    self.0
+                        .try_spin_compare_exchange_weak(
+                            |v| v & Self::$flag == 0,
+                            |v| v | Self::$flag,
+                        )
+                        .is_unexpected()
+                }
+
+                #[inline(always)]
+                pub fn $clear(&self) {
+                    let _ = self.$take();
+                }
+
+                #[inline(always)]
+                pub fn $take(&self) -> bool {
+                    self.0.fetch_take(Self::$flag) != 0
+                }
+            )*
+        }
+
+        impl<O> ::core::default::Default for $name<O>
+        where
+            O: $crate::TrCmpxchOrderings,
+        {
+            fn default() -> Self {
+                Self::new(0)
+            }
+        }
+
+        impl<O> ::core::fmt::Debug for $name<O>
+        where
+            O: $crate::TrCmpxchOrderings,
+        {
+            fn fmt(
+                &self,
+                f: &mut ::core::fmt::Formatter<'_>,
+            ) -> ::core::fmt::Result {
+                let value = self.value();
+                let mut set = f.debug_set();
+                $(
+                    if value & Self::$flag != 0 {
+                        set.entry(&::core::format_args!(
+                            "{}",
+                            ::core::stringify!($flag),
+                        ));
+                    }
+                )*
+                set.finish()
+            }
+        }
+    };
+}
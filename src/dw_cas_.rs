@@ -0,0 +1,477 @@
+﻿use core::{cell::UnsafeCell, fmt, sync::atomic::Ordering};
+
+use crate::{relax, TrAtomicCell, TrAtomicCellMut, TrAtomicData};
+
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "cmpxchg16b"),
+    target_arch = "aarch64",
+)))]
+use crate::SeqCount;
+
+type Pair = (usize, usize);
+
+/// A pair of words compared and exchanged as a whole, such as a pointer and
+/// the version stamp guarding it against ABA, without stealing any of the
+/// pointer bits.
+///
+/// On x86_64 with the `cmpxchg16b` target feature, the pair is operated with
+/// that 128-bit compare-exchange instruction. On little-endian 64-bit AArch64,
+/// it is operated with the `caspal` instruction of LSE when the `lse` target
+/// feature is enabled or, with the `std` feature, detected at runtime, and
+/// else, as on the other AArch64 targets, with a lock-free loop of exclusive
+/// loads and stores. Elsewhere it is emulated with a
+/// sequence lock: loads retry while a writer is active, and writers serialize
+/// with each other, so that `IS_LOCK_FREE` is `false`. Still, on x86_64 with
+/// the `std` feature, `cmpxchg16b` is probed at runtime and used if present,
+/// as `dwcas::is_native` tells.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use atomex::{DwCas, TrAtomicCell};
+///
+/// let head = DwCas::new((0x1000, 0));
+/// let (ptr, stamp) = head.load(Ordering::Acquire);
+/// let r = head.compare_exchange(
+///     (ptr, stamp),
+///     (0x2000, stamp + 1),
+///     Ordering::AcqRel,
+///     Ordering::Acquire,
+/// );
+/// assert_eq!(r, Ok((0x1000, 0)));
+///
+/// // Same pointer, but stale stamp.
+/// let r = head.compare_exchange(
+///     (0x2000, 0),
+///     (0x3000, 1),
+///     Ordering::AcqRel,
+///     Ordering::Acquire,
+/// );
+/// assert_eq!(r, Err((0x2000, 1)));
+/// ```
+#[repr(C, align(16))]
+pub struct DwCas {
+    pair: UnsafeCell<Pair>,
+    #[cfg(not(any(
+        all(target_arch = "x86_64", target_feature = "cmpxchg16b"),
+        target_arch = "aarch64",
+    )))]
+    seq: SeqCount,
+}
+
+unsafe impl Send for DwCas {}
+unsafe impl Sync for DwCas {}
+
+impl DwCas {
+    /// Whether the pair is operated without any lock on this target.
+    pub const IS_LOCK_FREE: bool = cfg!(any(
+        all(target_arch = "x86_64", target_feature = "cmpxchg16b"),
+        target_arch = "aarch64",
+    ));
+
+    pub const fn new(pair: Pair) -> Self {
+        DwCas {
+            pair: UnsafeCell::new(pair),
+            #[cfg(not(any(
+                all(target_arch = "x86_64", target_feature = "cmpxchg16b"),
+                target_arch = "aarch64",
+            )))]
+            seq: SeqCount::new(),
+        }
+    }
+}
+
+#[cfg(any(
+    all(target_arch = "x86_64", target_feature = "cmpxchg16b"),
+    target_arch = "aarch64",
+))]
+impl DwCas {
+    fn load_pair(&self, order: Ordering) -> Pair {
+        // A failed exchange of the pair with itself reads it atomically, and
+        // a successful one leaves it unchanged.
+        let current = (0, 0);
+        match self.cas_pair(current, current, order, order) {
+            Result::Ok(x) | Result::Err(x) => x,
+        }
+    }
+
+    fn swap_pair(&self, val: Pair, order: Ordering) -> Pair {
+        let mut current = self.load_pair(Ordering::Relaxed);
+        loop {
+            match self.cas_pair(current, val, order, Ordering::Relaxed) {
+                Result::Ok(x) => return x,
+                Result::Err(x) => current = x,
+            }
+            relax::relax();
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn cas_pair(
+        &self,
+        current: Pair,
+        desired: Pair,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Pair, Pair> {
+        let _ = (success, failure);
+        // The instruction is enabled at compile time.
+        unsafe { cmpxchg16b_pair(self.pair.get(), current, desired) }
+    }
+
+    /// Always acquires and releases, whatever the orderings asked for.
+    ///
+    /// Issues the `caspal` instruction of LSE when the target feature is
+    /// enabled at compile time or, with the `std` feature, detected at
+    /// runtime, and an exclusive load and store loop otherwise.
+    #[cfg(target_arch = "aarch64")]
+    fn cas_pair(
+        &self,
+        current: Pair,
+        desired: Pair,
+        _: Ordering,
+        _: Ordering,
+    ) -> Result<Pair, Pair> {
+        let dst = self.pair.get();
+        // The pair is aligned by the cell, and only accessed atomically.
+        #[cfg(all(
+            target_feature = "lse",
+            target_endian = "little",
+            target_pointer_width = "64",
+        ))]
+        let prev = unsafe { caspal_pair(dst, current, desired) };
+        #[cfg(all(
+            not(target_feature = "lse"),
+            feature = "std",
+            target_endian = "little",
+            target_pointer_width = "64",
+        ))]
+        let prev = if std::arch::is_aarch64_feature_detected!("lse") {
+            unsafe { caspal_pair(dst, current, desired) }
+        } else {
+            unsafe { ldaxp_stlxp_pair(dst, current, desired) }
+        };
+        #[cfg(not(all(
+            any(target_feature = "lse", feature = "std"),
+            target_endian = "little",
+            target_pointer_width = "64",
+        )))]
+        let prev = unsafe { ldaxp_stlxp_pair(dst, current, desired) };
+        if prev == current {
+            Result::Ok(prev)
+        } else {
+            Result::Err(prev)
+        }
+    }
+}
+
+/// Compares and exchanges the pair at `dst` with the `caspal` instruction,
+/// and returns the pair it held.
+///
+/// # Safety
+///
+/// The machine must support LSE, and `dst` must be valid, aligned to 16
+/// bytes, and only ever accessed atomically while shared.
+#[cfg(all(
+    target_arch = "aarch64",
+    any(target_feature = "lse", feature = "std"),
+    target_endian = "little",
+    target_pointer_width = "64",
+))]
+#[target_feature(enable = "lse")]
+unsafe fn caspal_pair(dst: *mut Pair, current: Pair, desired: Pair) -> Pair {
+    use core::{arch::asm, mem::transmute};
+
+    // Words in memory order, whatever the layout of the tuple. The first
+    // register of each pair holds the word at the lower address on a
+    // little-endian target with 64-bit words.
+    let [c0, c1] = unsafe { transmute::<Pair, [usize; 2]>(current) };
+    let [d0, d1] = unsafe { transmute::<Pair, [usize; 2]>(desired) };
+    let (p0, p1): (usize, usize);
+    // Both pairs must be held by an even register and the next one.
+    unsafe {
+        asm!(
+            "caspal x4, x5, x6, x7, [{dst}]",
+            dst = in(reg) dst,
+            inout("x4") c0 => p0,
+            inout("x5") c1 => p1,
+            in("x6") d0,
+            in("x7") d1,
+            options(nostack, preserves_flags),
+        );
+        transmute::<[usize; 2], Pair>([p0, p1])
+    }
+}
+
+/// Compares and exchanges the pair at `dst` with a loop of `ldaxp` and
+/// `stlxp`, and returns the pair it held.
+///
+/// # Safety
+///
+/// `dst` must be valid, aligned to 16 bytes, and only ever accessed
+/// atomically while shared.
+#[cfg(all(
+    target_arch = "aarch64",
+    not(all(
+        target_feature = "lse",
+        target_endian = "little",
+        target_pointer_width = "64",
+    )),
+))]
+unsafe fn ldaxp_stlxp_pair(
+    dst: *mut Pair,
+    current: Pair,
+    desired: Pair,
+) -> Pair {
+    use core::{arch::asm, mem::transmute};
+
+    // Words in memory order, whatever the layout of the tuple.
+    let [c0, c1] = unsafe { transmute::<Pair, [usize; 2]>(current) };
+    let [d0, d1] = unsafe { transmute::<Pair, [usize; 2]>(desired) };
+    let (p0, p1): (usize, usize);
+    // The failed path stores the loaded pair back, since a pair loaded by
+    // `ldaxp` is only known to be single-copy atomic once the paired store
+    // succeeds.
+    unsafe {
+        asm!(
+            "2:",
+            "ldaxp {p0}, {p1}, [{dst}]",
+            "cmp {p0}, {c0}",
+            "ccmp {p1}, {c1}, #0, eq",
+            "b.ne 3f",
+            "stlxp {st:w}, {d0}, {d1}, [{dst}]",
+            "cbnz {st:w}, 2b",
+            "b 4f",
+            "3:",
+            "stlxp {st:w}, {p0}, {p1}, [{dst}]",
+            "cbnz {st:w}, 2b",
+            "4:",
+            dst = in(reg) dst,
+            c0 = in(reg) c0,
+            c1 = in(reg) c1,
+            d0 = in(reg) d0,
+            d1 = in(reg) d1,
+            p0 = out(reg) p0,
+            p1 = out(reg) p1,
+            st = out(reg) _,
+            options(nostack),
+        );
+        transmute::<[usize; 2], Pair>([p0, p1])
+    }
+}
+
+/// Compares and exchanges the pair at `dst` with `cmpxchg16b`, which is
+/// sequentially consistent whatever the orderings asked for.
+///
+/// The instruction is issued with inline assembly rather than with the
+/// intrinsic, which needs the target feature enabled at compile time to be
+/// lowered without a call to `__atomic_compare_exchange_16`.
+///
+/// # Safety
+///
+/// The machine must support `cmpxchg16b`, and `dst` must be valid, aligned
+/// to 16 bytes, and only ever accessed atomically while shared.
+#[cfg(all(
+    target_arch = "x86_64",
+    any(target_feature = "cmpxchg16b", feature = "std"),
+))]
+unsafe fn cmpxchg16b_pair(
+    dst: *mut Pair,
+    current: Pair,
+    desired: Pair,
+) -> Result<Pair, Pair> {
+    use core::{arch::asm, mem::transmute};
+
+    // Words in memory order, whatever the layout of the tuple.
+    let [c0, c1] = unsafe { transmute::<Pair, [usize; 2]>(current) };
+    let [d0, d1] = unsafe { transmute::<Pair, [usize; 2]>(desired) };
+    let (p0, p1): (usize, usize);
+    // `rbx` is reserved by LLVM, so the low word of `desired` is swapped in
+    // and out of it around the instruction.
+    unsafe {
+        asm!(
+            "xchg {d0}, rbx",
+            "lock cmpxchg16b xmmword ptr [{dst}]",
+            "mov rbx, {d0}",
+            dst = in(reg) dst,
+            d0 = inout(reg) d0 => _,
+            in("rcx") d1,
+            inout("rax") c0 => p0,
+            inout("rdx") c1 => p1,
+            options(nostack),
+        );
+    }
+    let prev = unsafe { transmute::<[usize; 2], Pair>([p0, p1]) };
+    if prev == current {
+        Result::Ok(prev)
+    } else {
+        Result::Err(prev)
+    }
+}
+
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "cmpxchg16b"),
+    target_arch = "aarch64",
+)))]
+impl DwCas {
+    fn load_pair(&self, order: Ordering) -> Pair {
+        #[cfg(all(target_arch = "x86_64", feature = "std"))]
+        if crate::dwcas::is_native() {
+            let current = (0, 0);
+            return match self.cas_native(current, current, order, order) {
+                Result::Ok(x) | Result::Err(x) => x,
+            };
+        }
+        let _ = order;
+        loop {
+            let s = self.seq.read_begin();
+            // The copy may race with a writer, so it is kept uninitialized,
+            // and discarded below without being looked at.
+            let pair = unsafe {
+                let p = self.pair.get().cast::<core::mem::MaybeUninit<Pair>>();
+                core::ptr::read_volatile(p)
+            };
+            if !self.seq.read_retry(s) {
+                // Safety: no writer was active, so the copy is not torn.
+                return unsafe { pair.assume_init() };
+            }
+            relax::relax();
+        }
+    }
+
+    fn swap_pair(&self, val: Pair, order: Ordering) -> Pair {
+        #[cfg(all(target_arch = "x86_64", feature = "std"))]
+        if crate::dwcas::is_native() {
+            let mut current = self.load_pair(Ordering::Relaxed);
+            loop {
+                match self.cas_native(current, val, order, Ordering::Relaxed) {
+                    Result::Ok(x) => return x,
+                    Result::Err(x) => current = x,
+                }
+                relax::relax();
+            }
+        }
+        let _ = order;
+        let write = self.seq.begin_write();
+        // Writers are excluded by the sequence.
+        let prev = unsafe { core::ptr::replace(self.pair.get(), val) };
+        self.seq.end_write(write);
+        prev
+    }
+
+    fn cas_pair(
+        &self,
+        current: Pair,
+        desired: Pair,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Pair, Pair> {
+        #[cfg(all(target_arch = "x86_64", feature = "std"))]
+        if crate::dwcas::is_native() {
+            return self.cas_native(current, desired, success, failure);
+        }
+        let _ = (success, failure);
+        let write = self.seq.begin_write();
+        // Writers are excluded by the sequence.
+        let prev = unsafe { *self.pair.get() };
+        if prev != current {
+            self.seq.cancel_write(write);
+            return Result::Err(prev);
+        }
+        unsafe { *self.pair.get() = desired };
+        self.seq.end_write(write);
+        Result::Ok(prev)
+    }
+
+    /// The pair is either always operated natively, or always with the
+    /// sequence, since the probe gives the same answer all along.
+    #[cfg(all(target_arch = "x86_64", feature = "std"))]
+    fn cas_native(
+        &self,
+        current: Pair,
+        desired: Pair,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Pair, Pair> {
+        let _ = (success, failure);
+        // The instruction was probed, and the pair is aligned by the cell.
+        unsafe { cmpxchg16b_pair(self.pair.get(), current, desired) }
+    }
+}
+
+impl TrAtomicCell for DwCas {
+    type Value = Pair;
+
+    #[inline(always)]
+    fn new(val: Pair) -> Self {
+        DwCas::new(val)
+    }
+
+    #[inline(always)]
+    fn into_inner(self) -> Pair {
+        self.pair.into_inner()
+    }
+
+    #[inline(always)]
+    fn load(&self, order: Ordering) -> Pair {
+        self.load_pair(order)
+    }
+
+    #[inline(always)]
+    fn store(&self, val: Pair, order: Ordering) {
+        let _ = self.swap_pair(val, order);
+    }
+
+    #[inline(always)]
+    fn swap(&self, val: Pair, order: Ordering) -> Pair {
+        self.swap_pair(val, order)
+    }
+
+    #[inline(always)]
+    fn compare_exchange(
+        &self,
+        current: Pair,
+        desired: Pair,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Pair, Pair> {
+        self.cas_pair(current, desired, success, failure)
+    }
+
+    #[inline(always)]
+    fn compare_exchange_weak(
+        &self,
+        current: Pair,
+        desired: Pair,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Pair, Pair> {
+        self.cas_pair(current, desired, success, failure)
+    }
+}
+
+impl TrAtomicCellMut for DwCas {
+    #[inline(always)]
+    fn get_mut(&mut self) -> &mut Pair {
+        self.pair.get_mut()
+    }
+}
+
+impl TrAtomicData for Pair {
+    type AtomicCell = DwCas;
+}
+
+impl Default for DwCas {
+    fn default() -> Self {
+        DwCas::new((0, 0))
+    }
+}
+
+impl fmt::Debug for DwCas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DwCas")
+            .field(&self.load_pair(Ordering::Relaxed))
+            .finish()
+    }
+}
@@ -0,0 +1,43 @@
+﻿//! Capabilities of the double-word compare-and-swap of `DwCas` on the
+//! running machine.
+
+/// Returns `true` if `DwCas` operates its pair natively, without any lock,
+/// on the running machine.
+///
+/// This is always the case on AArch64, with `caspal` or with a loop of
+/// exclusive loads and stores, and on x86_64 built with the `cmpxchg16b`
+/// target feature, as `DwCas::IS_LOCK_FREE` tells at compile time. Else on
+/// x86_64 with the `std` feature, the instruction is probed at runtime, once.
+/// Elsewhere, `DwCas` is emulated with a sequence lock.
+///
+/// Data structures may check this to pick a layout, e.g. a pointer paired
+/// with a full version word when native, else a tagged pointer in a single
+/// word.
+///
+/// # Example
+///
+/// ```
+/// use atomex::{dwcas, DwCas};
+///
+/// if DwCas::IS_LOCK_FREE {
+///     assert!(dwcas::is_native());
+/// }
+/// ```
+#[inline(always)]
+pub fn is_native() -> bool {
+    #[cfg(any(
+        all(target_arch = "x86_64", target_feature = "cmpxchg16b"),
+        target_arch = "aarch64",
+    ))]
+    return true;
+
+    #[cfg(all(
+        target_arch = "x86_64",
+        not(target_feature = "cmpxchg16b"),
+        feature = "std",
+    ))]
+    return std::is_x86_feature_detected!("cmpxchg16b");
+
+    #[allow(unreachable_code)]
+    false
+}
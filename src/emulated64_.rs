@@ -0,0 +1,283 @@
+﻿use core::{
+    cell::UnsafeCell, fmt, mem::MaybeUninit, ptr, sync::atomic::Ordering,
+};
+
+use crate::{
+    atomic_cell_::audit, fetch, relax, Bitwise, NumOps, SeqCount, TrAtomicCell,
+    TrAtomicCellMut,
+};
+
+/// A `u64` cell emulating the 64-bit atomics with a sequence lock, for
+/// 32-bit targets without them such as `thumbv7m`, so that `AtomicCount`,
+/// `GenerationClock` and the other cells over `u64` work there too.
+///
+/// Loads are lock-free but retry while a writer is active, and writers
+/// serialize with each other, spinning while another one is active. Every
+/// operation acquires and releases, whatever the orderings asked for.
+///
+/// On the targets with 32-bit atomics but without 64-bit ones,
+/// `TrAtomicData` maps `u64` to this cell, unless the `portable-atomic`
+/// feature provides `AtomicU64`.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use atomex::{fetch::Add, Emulated64, TrAtomicCell};
+///
+/// static UPTIME_US: Emulated64 = Emulated64::new(u32::MAX as u64);
+///
+/// UPTIME_US.fetch_add(1, Ordering::Relaxed);
+/// assert_eq!(UPTIME_US.load(Ordering::Acquire), 1 << 32);
+/// ```
+pub struct Emulated64 {
+    value: UnsafeCell<u64>,
+    seq: SeqCount,
+}
+
+unsafe impl Send for Emulated64 {}
+unsafe impl Sync for Emulated64 {}
+
+impl Emulated64 {
+    pub const fn new(value: u64) -> Self {
+        Emulated64 {
+            value: UnsafeCell::new(value),
+            seq: SeqCount::new(),
+        }
+    }
+
+    fn read(&self) -> u64 {
+        loop {
+            let s = self.seq.read_begin();
+            // The copy may race with a writer, so it is kept uninitialized, and
+            // discarded below without being looked at.
+            let value = unsafe {
+                ptr::read_volatile(self.value.get().cast::<MaybeUninit<u64>>())
+            };
+            if !self.seq.read_retry(s) {
+                // Safety: no writer was active, so the copy is not torn.
+                return unsafe { value.assume_init() };
+            }
+            relax::relax();
+        }
+    }
+
+    /// Replaces the value with `f` of it unless `f` returns `None`.
+    ///
+    /// Returns `Ok(previous)` if it was replaced, else `Err(current)`.
+    fn update(
+        &self,
+        f: impl FnOnce(u64) -> Option<u64>,
+    ) -> Result<u64, u64> {
+        let write = self.seq.begin_write();
+        // Writers are excluded by the sequence.
+        let prev = unsafe { *self.value.get() };
+        match f(prev) {
+            Option::Some(x) => {
+                unsafe { *self.value.get() = x };
+                self.seq.end_write(write);
+                Result::Ok(prev)
+            }
+            Option::None => {
+                self.seq.cancel_write(write);
+                Result::Err(prev)
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn fetch_apply(&self, f: impl FnOnce(u64) -> u64) -> u64 {
+        match self.update(|x| Option::Some(f(x))) {
+            Result::Ok(x) | Result::Err(x) => x,
+        }
+    }
+}
+
+impl Default for Emulated64 {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl fmt::Debug for Emulated64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Emulated64").field(&self.read()).finish()
+    }
+}
+
+impl TrAtomicCell for Emulated64 {
+    type Value = u64;
+
+    #[inline(always)]
+    fn new(val: u64) -> Self {
+        Emulated64::new(val)
+    }
+
+    #[inline(always)]
+    fn into_inner(self) -> u64 {
+        self.value.into_inner()
+    }
+
+    #[inline(always)]
+    fn load(&self, order: Ordering) -> u64 {
+        let r = self.read();
+        audit!(self, Load, order);
+        r
+    }
+
+    #[inline(always)]
+    fn store(&self, val: u64, order: Ordering) {
+        let _ = self.fetch_apply(|_| val);
+        audit!(self, Store, order);
+    }
+
+    #[inline(always)]
+    fn swap(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|_| val);
+        audit!(self, Swap, order);
+        r
+    }
+
+    fn compare_exchange(
+        &self,
+        current: u64,
+        desired: u64,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u64, u64> {
+        let r = self.update(|x| (x == current).then_some(desired));
+        audit!(self, CompareExchange, success, Option::Some(failure));
+        r
+    }
+
+    #[inline(always)]
+    fn compare_exchange_weak(
+        &self,
+        current: u64,
+        desired: u64,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u64, u64> {
+        let r = self.update(|x| (x == current).then_some(desired));
+        audit!(self, CompareExchangeWeak, success, Option::Some(failure));
+        r
+    }
+}
+
+impl TrAtomicCellMut for Emulated64 {
+    #[inline(always)]
+    fn get_mut(&mut self) -> &mut u64 {
+        self.value.get_mut()
+    }
+}
+
+impl Bitwise for Emulated64 {}
+
+impl fetch::And for Emulated64 {
+    type Value = u64;
+
+    fn fetch_and(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| x & val);
+        audit!(self, FetchAnd, order);
+        r
+    }
+}
+
+impl fetch::Nand for Emulated64 {
+    type Value = u64;
+
+    fn fetch_nand(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| !(x & val));
+        audit!(self, FetchNand, order);
+        r
+    }
+}
+
+impl fetch::Or for Emulated64 {
+    type Value = u64;
+
+    fn fetch_or(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| x | val);
+        audit!(self, FetchOr, order);
+        r
+    }
+}
+
+impl fetch::Xor for Emulated64 {
+    type Value = u64;
+
+    fn fetch_xor(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| x ^ val);
+        audit!(self, FetchXor, order);
+        r
+    }
+}
+
+impl NumOps for Emulated64 {}
+
+impl fetch::Add for Emulated64 {
+    type Value = u64;
+
+    fn fetch_add(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| x.wrapping_add(val));
+        audit!(self, FetchAdd, order);
+        r
+    }
+}
+
+impl fetch::Sub for Emulated64 {
+    type Value = u64;
+
+    fn fetch_sub(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| x.wrapping_sub(val));
+        audit!(self, FetchSub, order);
+        r
+    }
+}
+
+impl fetch::Update for Emulated64 {
+    type Value = u64;
+
+    fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        f: F,
+    ) -> Result<u64, u64>
+    where
+        F: FnMut(u64) -> Option<u64>,
+    {
+        let r = self.update(f);
+        audit!(self, FetchUpdate, set_order, Option::Some(fetch_order));
+        r
+    }
+}
+
+impl fetch::Max for Emulated64 {
+    type Value = u64;
+
+    fn fetch_max(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| x.max(val));
+        audit!(self, FetchMax, order);
+        r
+    }
+}
+
+impl fetch::Min for Emulated64 {
+    type Value = u64;
+
+    fn fetch_min(&self, val: u64, order: Ordering) -> u64 {
+        let r = self.fetch_apply(|x| x.min(val));
+        audit!(self, FetchMin, order);
+        r
+    }
+}
+
+#[cfg(all(
+    not(target_has_atomic = "64"),
+    target_has_atomic = "32",
+    not(feature = "portable-atomic"),
+))]
+impl crate::TrAtomicData for u64 {
+    type AtomicCell = Emulated64;
+}
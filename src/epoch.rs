@@ -0,0 +1,322 @@
+﻿//! Epoch-based reclamation of the pointees unlinked from shared structures.
+//!
+//! Every thread accessing the structures registers a participant to a
+//! [`Collector`], and pins it for the duration of each access. The pointees
+//! unlinked meanwhile are deferred to the collector with the global epoch of
+//! the time, and destroyed once the epoch has advanced twice since: the
+//! epoch only advances when every pinned participant has observed it, so no
+//! access started before the unlinking can still be running by then.
+//!
+//! Unlike hazard pointers, pinning costs a single store, whatever the number
+//! of pointers loaded, but a participant staying pinned holds back all the
+//! reclamation of its collector.
+//!
+//! # Example
+//!
+//! ```
+//! use core::{ptr, sync::atomic::{AtomicPtr, AtomicUsize, Ordering}};
+//! use atomex::epoch::Collector;
+//!
+//! static DROPPED: AtomicUsize = AtomicUsize::new(0);
+//! struct Config(u32);
+//! impl Drop for Config {
+//!     fn drop(&mut self) {
+//!         DROPPED.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! let collector = Collector::<4>::new();
+//! let shared = AtomicPtr::new(Box::into_raw(Box::new(Config(1))));
+//! let handle = collector.register();
+//! {
+//!     let guard = handle.pin();
+//!     let new = Box::into_raw(Box::new(Config(2)));
+//!     let old = shared.swap(new, Ordering::AcqRel);
+//!     unsafe { guard.defer_destroy(old) };
+//!     assert!(collector.collect());
+//!     assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+//! }
+//! while DROPPED.load(Ordering::Relaxed) == 0 {
+//!     collector.collect();
+//! }
+//! let last = shared.swap(ptr::null_mut(), Ordering::AcqRel);
+//! drop(unsafe { Box::from_raw(last) });
+//! ```
+use core::{
+    cell::Cell,
+    fmt,
+    sync::atomic::{self, Ordering},
+};
+use crate::atomics_::{AtomicBool, AtomicUsize};
+use alloc::boxed::Box;
+use crate::{
+    relax,
+    retired_::{Drain, Retired, RetiredList},
+    AtomicCount, CachePadded, StrictOrderings,
+};
+
+type Epoch = AtomicCount<usize, AtomicUsize, StrictOrderings>;
+
+/// The state word of a participant pinned at an epoch.
+const PINNED: usize = 1;
+
+/// The number of pins of a participant between two attempts to advance the
+/// epoch.
+const PINS_PER_COLLECT: usize = 64;
+
+struct Participant {
+    claimed: AtomicBool,
+    /// The epoch observed by the last pin, shifted left, with `PINNED` set
+    /// while pinned.
+    state: Epoch,
+}
+
+/// The global epoch, the `N` participant slots and the deferred pointees
+/// shared by the threads accessing the same structures.
+pub struct Collector<const N: usize = 32> {
+    epoch: CachePadded<Epoch>,
+    advancing: AtomicBool,
+    participants: [CachePadded<Participant>; N],
+    /// The pointees deferred at each epoch, modulo 3.
+    bags: [RetiredList; 3],
+}
+
+impl<const N: usize> Collector<N> {
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "[Collector] no participant slot") };
+        Collector {
+            epoch: CachePadded::new(AtomicCount::new(AtomicUsize::new(0))),
+            advancing: AtomicBool::new(false),
+            participants: [const {
+                CachePadded::new(Participant {
+                    claimed: AtomicBool::new(false),
+                    state: AtomicCount::new(AtomicUsize::new(0)),
+                })
+            }; N],
+            bags: [const { RetiredList::new() }; 3],
+        }
+    }
+
+    /// The current global epoch.
+    #[inline(always)]
+    pub fn epoch(&self) -> usize {
+        self.epoch.val()
+    }
+
+    /// Claims a participant slot, spinning until one is free.
+    pub fn register(&self) -> LocalHandle<'_, N> {
+        loop {
+            if let Option::Some(h) = self.try_register() {
+                return h;
+            }
+            relax::relax();
+        }
+    }
+
+    /// Claims a participant slot, or returns `None` if all `N` are claimed.
+    pub fn try_register(&self) -> Option<LocalHandle<'_, N>> {
+        let index = self.participants.iter().position(|p| {
+            p.claimed
+                .compare_exchange(
+                    false,
+                    true,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+        })?;
+        Option::Some(LocalHandle {
+            collector: self,
+            index,
+            guards: Cell::new(0),
+            pins: Cell::new(0),
+        })
+    }
+
+    /// Tries to advance the global epoch, destroying the pointees deferred
+    /// two epochs before the current one.
+    ///
+    /// Returns `false` if a participant is pinned at an older epoch, or
+    /// another thread is advancing the epoch.
+    pub fn collect(&self) -> bool {
+        if self.advancing.swap(true, Ordering::Acquire) {
+            return false;
+        }
+        // Pairs with the fence of `pin`, ordering the unlinking of the
+        // deferred pointees before the loads of the participants.
+        atomic::fence(Ordering::SeqCst);
+        let epoch = self.epoch.val();
+        let current = epoch.wrapping_shl(1) | PINNED;
+        let lagging = self.participants.iter().any(|p| {
+            let state = p.state.val();
+            state & PINNED != 0 && state != current
+        });
+        if !lagging {
+            // Every pinned participant has observed `epoch`, after all the
+            // pointees of the bag were unlinked.
+            Self::destroy_all(self.bags[epoch.wrapping_add(2) % 3].take());
+            self.epoch.wrapping_add(1);
+        }
+        self.advancing.store(false, Ordering::Release);
+        !lagging
+    }
+
+    fn defer(&self, deferred: Box<Retired>) {
+        self.bags[self.epoch.val() % 3].push(deferred);
+    }
+
+    fn destroy_all(drain: Drain) {
+        for deferred in drain {
+            // Taken out of its bag after the grace period.
+            unsafe { deferred.destroy() };
+        }
+    }
+}
+
+impl<const N: usize> Default for Collector<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Drop for Collector<N> {
+    fn drop(&mut self) {
+        // No participant can be pinned while the collector is borrowed
+        // mutably.
+        for bag in self.bags.iter_mut() {
+            Self::destroy_all(bag.take_mut());
+        }
+    }
+}
+
+impl<const N: usize> fmt::Debug for Collector<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let registered = self
+            .participants
+            .iter()
+            .filter(|p| p.claimed.load(Ordering::Relaxed))
+            .count();
+        f.debug_struct("Collector")
+            .field("epoch", &self.epoch())
+            .field("participants", &N)
+            .field("registered", &registered)
+            .finish()
+    }
+}
+
+/// A participant of a `Collector`, to be used by one thread at a time, which
+/// releases its slot when dropped.
+pub struct LocalHandle<'c, const N: usize = 32> {
+    collector: &'c Collector<N>,
+    index: usize,
+    /// The number of guards alive.
+    guards: Cell<usize>,
+    pins: Cell<usize>,
+}
+
+impl<'c, const N: usize> LocalHandle<'c, N> {
+    #[inline(always)]
+    pub fn collector(&self) -> &'c Collector<N> {
+        self.collector
+    }
+
+    #[inline(always)]
+    pub fn is_pinned(&self) -> bool {
+        self.guards.get() > 0
+    }
+
+    /// Pins the participant at the current epoch until the guard, and any
+    /// other guard nested in it, is dropped.
+    pub fn pin(&self) -> Guard<'_, 'c, N> {
+        let guards = self.guards.get();
+        self.guards.set(guards + 1);
+        if guards == 0 {
+            let epoch = self.collector.epoch.val();
+            self.participant().state.set(epoch.wrapping_shl(1) | PINNED);
+            // Orders the pin before the loads from the structures, which a
+            // `SeqCst` store alone does not for the weaker loads, so that a
+            // collector either sees the pin or the thread sees the unlinking.
+            atomic::fence(Ordering::SeqCst);
+            let pins = self.pins.get().wrapping_add(1);
+            self.pins.set(pins);
+            if pins.is_multiple_of(PINS_PER_COLLECT) {
+                self.collector.collect();
+            }
+        }
+        Guard { handle: self }
+    }
+
+    fn participant(&self) -> &Participant {
+        &self.collector.participants[self.index]
+    }
+
+    fn unpin(&self) {
+        let guards = self.guards.get() - 1;
+        self.guards.set(guards);
+        if guards == 0 {
+            let state = self.participant().state.val();
+            self.participant().state.set(state & !PINNED);
+        }
+    }
+}
+
+impl<const N: usize> Drop for LocalHandle<'_, N> {
+    fn drop(&mut self) {
+        self.participant().claimed.store(false, Ordering::Release);
+    }
+}
+
+impl<const N: usize> fmt::Debug for LocalHandle<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalHandle")
+            .field("index", &self.index)
+            .field("is_pinned", &self.is_pinned())
+            .finish()
+    }
+}
+
+/// Keeps a participant pinned, so that the pointees loaded meanwhile are not
+/// destroyed until it is dropped.
+pub struct Guard<'h, 'c, const N: usize = 32> {
+    handle: &'h LocalHandle<'c, N>,
+}
+
+impl<const N: usize> Guard<'_, '_, N> {
+    /// Defers the destruction of `p` with `dtor` until no participant pinned
+    /// now or earlier can still be reading it.
+    ///
+    /// # Safety
+    ///
+    /// `p` must have been unlinked from every place a reader could load it
+    /// from, and must not be deferred again. `dtor` must be safe to call on
+    /// it from any thread.
+    pub unsafe fn defer<T>(&self, p: *mut T, dtor: unsafe fn(*mut T)) {
+        self.handle.collector.defer(Retired::new(p, dtor, 0));
+    }
+
+    /// Defers dropping the box `p` was made from.
+    ///
+    /// # Safety
+    ///
+    /// As for `defer`, and `p` must come from `Box::into_raw`.
+    pub unsafe fn defer_destroy<T>(&self, p: *mut T) {
+        unsafe fn drop_box<T>(p: *mut T) {
+            drop(unsafe { Box::from_raw(p) });
+        }
+        unsafe { self.defer(p, drop_box::<T>) }
+    }
+}
+
+impl<const N: usize> Drop for Guard<'_, '_, N> {
+    fn drop(&mut self) {
+        self.handle.unpin();
+    }
+}
+
+impl<const N: usize> fmt::Debug for Guard<'_, '_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Guard")
+            .field("handle", &self.handle.index)
+            .finish()
+    }
+}
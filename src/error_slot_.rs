@@ -0,0 +1,116 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::AtomicU32;
+
+/// The first of the errors reported by parallel workers, stored as a
+/// nonzero `u32` code, with `0` for no error.
+///
+/// Only the first error recorded is kept: the later ones are discarded, so
+/// that the error reported is the one that caused the others, if any.
+///
+/// # Example
+///
+/// ```
+/// use atomex::ErrorSlot;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Failure { Timeout = 1, Refused = 2 }
+///
+/// impl From<Failure> for u32 {
+///     fn from(f: Failure) -> u32 { f as u32 }
+/// }
+///
+/// impl TryFrom<u32> for Failure {
+///     type Error = u32;
+///     fn try_from(code: u32) -> Result<Self, u32> {
+///         match code {
+///             1 => Ok(Failure::Timeout),
+///             2 => Ok(Failure::Refused),
+///             _ => Err(code),
+///         }
+///     }
+/// }
+///
+/// let first = ErrorSlot::<Failure>::new();
+/// assert!(first.record(Failure::Refused));
+/// assert!(!first.record(Failure::Timeout));
+/// assert_eq!(first.get(), Some(Failure::Refused));
+/// assert_eq!(first.take(), Some(Failure::Refused));
+/// assert!(!first.is_set());
+/// ```
+pub struct ErrorSlot<E>(AtomicU32, PhantomData<fn(E) -> E>)
+where
+    E: Into<u32> + TryFrom<u32>;
+
+impl<E> ErrorSlot<E>
+where
+    E: Into<u32> + TryFrom<u32>,
+{
+    pub const fn new() -> Self {
+        ErrorSlot(AtomicU32::new(0), PhantomData)
+    }
+
+    /// Records `error` unless an error was recorded already, and returns
+    /// whether it was recorded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the code of `error` is `0`.
+    pub fn record(&self, error: E) -> bool {
+        let code = error.into();
+        assert!(code != 0, "[ErrorSlot::record] zero error code");
+        self.0
+            .compare_exchange(0, code, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    #[inline(always)]
+    pub fn is_set(&self) -> bool {
+        self.code() != 0
+    }
+
+    /// The code of the error recorded, `0` if none is.
+    #[inline(always)]
+    pub fn code(&self) -> u32 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// The error recorded, or `None` if none is or its code does not
+    /// convert back to `E`.
+    pub fn get(&self) -> Option<E> {
+        Self::decode(self.code())
+    }
+
+    /// Takes the error out, so that the next one is recorded.
+    pub fn take(&self) -> Option<E> {
+        Self::decode(self.0.swap(0, Ordering::AcqRel))
+    }
+
+    fn decode(code: u32) -> Option<E> {
+        if code == 0 {
+            return Option::None;
+        }
+        E::try_from(code).ok()
+    }
+}
+
+impl<E> Default for ErrorSlot<E>
+where
+    E: Into<u32> + TryFrom<u32>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> fmt::Debug for ErrorSlot<E>
+where
+    E: Into<u32> + TryFrom<u32>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ErrorSlot").field(&self.code()).finish()
+    }
+}
@@ -0,0 +1,117 @@
+﻿use core::fmt;
+use crate::{
+    atomics_::AtomicBool, waiters_::Waiters, AcqRelOrderings, AtomicFlags,
+    TrBackoff,
+};
+
+/// A one-shot event, such as a shutdown signal, which stays set once set.
+///
+/// Any number of observers can check or wait for it, directly or through
+/// an `EventListener`.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+/// use atomex::Event;
+///
+/// let shutdown = Event::new();
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         let listener = shutdown.subscribe();
+///         s.spawn(move || listener.wait());
+///     }
+///     assert!(shutdown.set());
+///     assert!(!shutdown.set());
+/// });
+/// assert!(shutdown.is_set());
+/// ```
+pub struct Event {
+    flag: AtomicFlags<bool, AtomicBool, AcqRelOrderings>,
+    waiters: Waiters,
+}
+
+impl Event {
+    pub const fn new() -> Self {
+        Event {
+            flag: AtomicFlags::new(AtomicBool::new(false)),
+            waiters: Waiters::new(),
+        }
+    }
+
+    /// Returns `true` if the event is set, with an `Acquire` load.
+    #[inline(always)]
+    pub fn is_set(&self) -> bool {
+        self.flag.value()
+    }
+
+    /// Sets the event and wakes up the waiters, returning `false` if it was
+    /// already set.
+    pub fn set(&self) -> bool {
+        let r = self.flag.try_once_compare_exchange(false, |v| !v, |_| true);
+        if r.is_succ() {
+            self.waiters.notify_all();
+        }
+        r.is_succ()
+    }
+
+    /// Waits until the event is set.
+    ///
+    /// Unless the event is set already, the thread sleeps until `set` wakes
+    /// it up with the `std` feature, and spins on the flag without it.
+    pub fn wait(&self) {
+        self.waiters.wait_until(|| self.is_set())
+    }
+
+    /// Spins until the event is set, waiting according to `backoff` between
+    /// two checks.
+    pub fn wait_with_backoff(&self, mut backoff: impl TrBackoff) {
+        while !self.is_set() {
+            backoff.backoff();
+        }
+    }
+
+    /// Returns a handle that can only observe the event, not set it.
+    pub fn subscribe(&self) -> EventListener<'_> {
+        EventListener(self)
+    }
+}
+
+impl Default for Event {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Event").field("set", &self.is_set()).finish()
+    }
+}
+
+/// An observer of an `Event`.
+#[derive(Clone, Copy)]
+pub struct EventListener<'a>(&'a Event);
+
+impl EventListener<'_> {
+    #[inline(always)]
+    pub fn is_set(&self) -> bool {
+        self.0.is_set()
+    }
+
+    #[inline(always)]
+    pub fn wait(&self) {
+        self.0.wait()
+    }
+
+    #[inline(always)]
+    pub fn wait_with_backoff(&self, backoff: impl TrBackoff) {
+        self.0.wait_with_backoff(backoff)
+    }
+}
+
+impl fmt::Debug for EventListener<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EventListener").field(self.0).finish()
+    }
+}
@@ -0,0 +1,128 @@
+﻿use core::{
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::AtomicU64;
+
+/// The number of fractional bits of the average.
+const FRAC_BITS: u32 = 16;
+
+/// The largest sample, which the greater ones are clamped to.
+const MAX_SAMPLE: u64 = u64::MAX >> FRAC_BITS;
+
+/// The average before the first sample.
+const EMPTY: u64 = u64::MAX;
+
+/// An exponentially weighted moving average of samples recorded by many
+/// threads, such as queue depths or latencies.
+///
+/// Each sample moves the average by `alpha` of its difference to it: the
+/// average is kept in fixed point with 16 fractional bits in a single
+/// `AtomicU64`, updated with a CAS loop, and `alpha` is expressed in
+/// 1/65536ths. The first sample sets the average outright. Samples above
+/// `2^48 - 1` are clamped.
+///
+/// # Example
+///
+/// ```
+/// use atomex::EwmaCell;
+///
+/// // alpha = 1/4
+/// let depth = EwmaCell::with_alpha_shift(2);
+/// assert_eq!(depth.get(), None);
+/// depth.record(100);
+/// depth.record(200);
+/// assert_eq!(depth.get(), Some(125));
+/// depth.record(200);
+/// assert_eq!(depth.get(), Some(144));
+/// ```
+pub struct EwmaCell {
+    average: AtomicU64,
+    alpha: u64,
+}
+
+impl EwmaCell {
+    /// The `alpha` of an average following the last sample only.
+    pub const ALPHA_ONE: u32 = 1 << FRAC_BITS;
+
+    /// Creates an average weighting each sample by `alpha / 65536`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` is `0` or above `ALPHA_ONE`.
+    pub const fn new(alpha: u32) -> Self {
+        assert!(
+            alpha > 0 && alpha <= Self::ALPHA_ONE,
+            "[EwmaCell::new] alpha out of (0, 1]",
+        );
+        EwmaCell {
+            average: AtomicU64::new(EMPTY),
+            alpha: alpha as u64,
+        }
+    }
+
+    /// Creates an average weighting each sample by `1 / 2^shift`.
+    pub const fn with_alpha_shift(shift: u32) -> Self {
+        assert!(
+            shift <= FRAC_BITS,
+            "[EwmaCell::with_alpha_shift] shift above 16",
+        );
+        Self::new(Self::ALPHA_ONE >> shift)
+    }
+
+    /// The weight of each sample, in 1/65536ths.
+    #[inline(always)]
+    pub const fn alpha(&self) -> u32 {
+        self.alpha as u32
+    }
+
+    /// Moves the average towards `sample`.
+    pub fn record(&self, sample: u64) {
+        let sample = sample.min(MAX_SAMPLE) << FRAC_BITS;
+        let _ = self.average.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |avg| Option::Some(self.next(avg, sample)),
+        );
+    }
+
+    /// The average rounded to the nearest integer, or `None` before the
+    /// first sample.
+    pub fn get(&self) -> Option<u64> {
+        let avg = self.get_fixed()?;
+        let half = 1 << (FRAC_BITS - 1);
+        Option::Some((avg + half) >> FRAC_BITS)
+    }
+
+    /// The average in fixed point with 16 fractional bits, or `None` before
+    /// the first sample.
+    pub fn get_fixed(&self) -> Option<u64> {
+        let avg = self.average.load(Ordering::Relaxed);
+        (avg != EMPTY).then_some(avg)
+    }
+
+    /// Forgets the samples, so that the next one sets the average again.
+    pub fn reset(&self) {
+        self.average.store(EMPTY, Ordering::Relaxed);
+    }
+
+    fn next(&self, avg: u64, sample: u64) -> u64 {
+        if avg == EMPTY {
+            return sample;
+        }
+        let one = Self::ALPHA_ONE as u128;
+        let alpha = self.alpha as u128;
+        let x = avg as u128 * (one - alpha) + sample as u128 * alpha;
+        // Both terms are below 2^64 times their weight, which sum to `one`.
+        ((x + one / 2) >> FRAC_BITS) as u64
+    }
+}
+
+impl fmt::Debug for EwmaCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EwmaCell")
+            .field("average", &self.get())
+            .field("alpha", &self.alpha)
+            .finish()
+    }
+}
@@ -0,0 +1,106 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+};
+use crate::{atomics_::AtomicBool, AcqRelOrderings, TrCmpxchOrderings};
+
+/// A boolean gate, set and cleared by single atomic operations with the
+/// orderings of `O`.
+///
+/// Every modification returns the previous state, so that of several
+/// threads setting or clearing the flag at once, exactly one sees the state
+/// change.
+///
+/// # Example
+///
+/// ```
+/// use atomex::Flag;
+///
+/// static SHUTDOWN: Flag = Flag::new(false);
+///
+/// assert!(!SHUTDOWN.set());
+/// assert!(SHUTDOWN.test_and_set());
+/// assert!(SHUTDOWN.toggle());
+/// assert!(!SHUTDOWN.is_set());
+/// SHUTDOWN.set();
+/// assert!(SHUTDOWN.take());
+/// assert!(!SHUTDOWN.take());
+/// ```
+pub struct Flag<O = AcqRelOrderings>(AtomicBool, PhantomData<O>);
+
+impl<O: TrCmpxchOrderings> Flag<O> {
+    pub const fn new(set: bool) -> Self {
+        Flag(AtomicBool::new(set), PhantomData)
+    }
+
+    #[inline(always)]
+    pub fn is_set(&self) -> bool {
+        self.0.load(O::LOAD_ORDERING)
+    }
+
+    pub fn store(&self, set: bool) {
+        self.0.store(set, O::STORE_ORDERING)
+    }
+
+    /// Sets the flag, and returns whether it was set already.
+    #[inline(always)]
+    pub fn set(&self) -> bool {
+        self.0.swap(true, O::SWAP_ORDERING)
+    }
+
+    /// Clears the flag, and returns whether it was set.
+    #[inline(always)]
+    pub fn clear(&self) -> bool {
+        self.0.swap(false, O::SWAP_ORDERING)
+    }
+
+    /// Inverts the flag, and returns whether it was set.
+    #[inline(always)]
+    pub fn toggle(&self) -> bool {
+        self.0.fetch_xor(true, O::SWAP_ORDERING)
+    }
+
+    /// Like `set`, but only writes if the flag is clear, so that spinning on
+    /// it does not keep taking the cache line away from the other threads.
+    pub fn test_and_set(&self) -> bool {
+        self.is_set() || self.set()
+    }
+
+    /// Like `clear`, but only writes if the flag is set, for polling a
+    /// signal consumed once.
+    pub fn take(&self) -> bool {
+        self.is_set() && self.clear()
+    }
+
+    /// Replaces the state with `new` if it is `current`.
+    ///
+    /// Returns `Ok(current)` if it was replaced, else `Err(actual)`.
+    pub fn compare_exchange(
+        &self,
+        current: bool,
+        new: bool,
+    ) -> Result<bool, bool> {
+        self.0.compare_exchange(
+            current,
+            new,
+            O::SUCC_ORDERING,
+            O::FAIL_ORDERING,
+        )
+    }
+
+    pub fn into_inner(self) -> bool {
+        self.0.into_inner()
+    }
+}
+
+impl<O: TrCmpxchOrderings> Default for Flag<O> {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl<O: TrCmpxchOrderings> fmt::Debug for Flag<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Flag").field(&self.is_set()).finish()
+    }
+}
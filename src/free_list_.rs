@@ -0,0 +1,168 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::{self, MaybeUninit},
+    ptr::NonNull,
+    sync::atomic::Ordering,
+};
+use crate::{atomics_::AtomicUsize, AcqRelOrderings, AtomicFlags, BitField};
+
+/// The index of the first free block plus one in the head word, `0` when
+/// the list is exhausted.
+const INDEX: BitField<usize> = BitField::new(0, usize::BITS / 2);
+
+/// The number of times the head word has been replaced, wrapping, so that
+/// a head popped and pushed back in between can not be mistaken for the
+/// one first loaded.
+const TAG: BitField<usize> = BitField::new(usize::BITS / 2, usize::BITS / 2);
+
+#[repr(C, align(16))]
+struct Block<const BLOCK: usize>(UnsafeCell<MaybeUninit<[u8; BLOCK]>>);
+
+/// A pool of `N` blocks of `BLOCK` bytes each, aligned to 16 bytes, which
+/// requires no allocation.
+///
+/// The free blocks form a lock-free stack whose head packs the index of the
+/// first block with a tag bumped at every change, to rule out ABA. The links
+/// are kept aside from the blocks, so that a popper reading the link of a
+/// block which has just been handed out never races with its new owner.
+///
+/// # Example
+///
+/// ```
+/// use atomex::FreeList;
+///
+/// static POOL: FreeList<2, 64> = FreeList::new();
+///
+/// let a = POOL.alloc().unwrap();
+/// let b = POOL.alloc().unwrap();
+/// assert_ne!(a, b);
+/// assert!(POOL.alloc().is_none());
+///
+/// unsafe { POOL.free(a) };
+/// assert_eq!(POOL.alloc(), Some(a));
+/// ```
+pub struct FreeList<const N: usize, const BLOCK: usize> {
+    head: AtomicFlags<usize, AtomicUsize, AcqRelOrderings>,
+    /// The index plus one of the free block following each free block.
+    links: [AtomicUsize; N],
+    blocks: [Block<BLOCK>; N],
+}
+
+unsafe impl<const N: usize, const BLOCK: usize> Send for FreeList<N, BLOCK> {}
+unsafe impl<const N: usize, const BLOCK: usize> Sync for FreeList<N, BLOCK> {}
+
+impl<const N: usize, const BLOCK: usize> FreeList<N, BLOCK> {
+    pub const fn new() -> Self {
+        const {
+            assert!(BLOCK > 0, "FreeList requires non-empty blocks");
+            assert!(
+                N < 1 << (usize::BITS / 2),
+                "FreeList capacity out of the index field",
+            );
+        };
+        let mut links = [const { AtomicUsize::new(0) }; N];
+        let mut i = 0;
+        while i + 1 < N {
+            links[i] = AtomicUsize::new(i + 2);
+            i += 1;
+        }
+        let first = if N > 0 { 1 } else { 0 };
+        FreeList {
+            head: AtomicFlags::new(AtomicUsize::new(first)),
+            links,
+            blocks: [const {
+                Block(UnsafeCell::new(MaybeUninit::uninit()))
+            }; N],
+        }
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[inline(always)]
+    pub const fn block_size(&self) -> usize {
+        BLOCK
+    }
+
+    /// Returns `true` if no block is free right now.
+    #[inline(always)]
+    pub fn is_exhausted(&self) -> bool {
+        self.head.load_field(INDEX) == 0
+    }
+
+    /// Returns `true` if `block` points to the start of a block of the pool.
+    pub fn contains(&self, block: NonNull<u8>) -> bool {
+        self.index_of(block).is_some()
+    }
+
+    /// Takes a free block of uninitialized bytes, or returns `None` if all of
+    /// them are in use.
+    pub fn alloc(&self) -> Option<NonNull<u8>> {
+        let r = self.head.try_spin_compare_exchange_weak(
+            |w| INDEX.extract(w) != 0,
+            |w| {
+                // Possibly outdated if the block has been taken meanwhile,
+                // in which case the tag makes the exchange fail.
+                let i = INDEX.extract(w) - 1;
+                let next = self.links[i].load(Ordering::Relaxed);
+                Self::bump(INDEX.insert(w, next))
+            },
+        );
+        let i = INDEX.extract(r.succ()?) - 1;
+        let p = self.blocks[i].0.get().cast::<u8>();
+        // Within the array, hence not null.
+        Option::Some(unsafe { NonNull::new_unchecked(p) })
+    }
+
+    /// Gives `block` back to the pool.
+    ///
+    /// # Safety
+    ///
+    /// `block` must have been returned by `alloc` of this pool and not been
+    /// freed since; it must not be used afterwards.
+    pub unsafe fn free(&self, block: NonNull<u8>) {
+        let Option::Some(i) = self.index_of(block) else {
+            panic!("[FreeList::free] block not from this pool")
+        };
+        let _ = self.head.try_spin_compare_exchange_weak(
+            |_| true,
+            |w| {
+                // Published by the release of the exchange.
+                self.links[i].store(INDEX.extract(w), Ordering::Relaxed);
+                Self::bump(INDEX.insert(w, i + 1))
+            },
+        );
+    }
+
+    fn bump(w: usize) -> usize {
+        let tag = TAG.extract(w).wrapping_add(1) & TAG.max_value();
+        TAG.insert(w, tag)
+    }
+
+    fn index_of(&self, block: NonNull<u8>) -> Option<usize> {
+        let base = self.blocks.as_ptr() as usize;
+        let size = mem::size_of::<Block<BLOCK>>();
+        let offset = (block.as_ptr() as usize).checked_sub(base)?;
+        let i = offset / size;
+        (i < N && offset % size == 0).then_some(i)
+    }
+}
+
+impl<const N: usize, const BLOCK: usize> Default for FreeList<N, BLOCK> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const BLOCK: usize> fmt::Debug for FreeList<N, BLOCK> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FreeList")
+            .field("capacity", &N)
+            .field("block_size", &BLOCK)
+            .field("is_exhausted", &self.is_exhausted())
+            .finish()
+    }
+}
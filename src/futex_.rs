@@ -0,0 +1,141 @@
+﻿use core::sync::atomic::{fence, Ordering};
+use crate::{
+    atomics_::{AtomicU32, AtomicUsize},
+    wait_::{Platform, TrWait},
+    CachePadded,
+};
+
+const BUCKET_BITS: u32 = 6;
+
+/// The number of threads blocked in the whole table, which spares the
+/// notifiers the bucket probe, and their modifications made `SeqCst` the
+/// fence, as long as no thread sleeps at all.
+static SLEEPING: AtomicUsize = AtomicUsize::new(0);
+
+/// The threads blocked on the atomics whose addresses hash to the same
+/// bucket, sleeping on a shared futex word bumped at every notification.
+struct Bucket {
+    /// The number of threads blocked, for the notifiers to skip the wake-up
+    /// of an empty bucket.
+    sleepers: AtomicUsize,
+    epoch: AtomicU32,
+}
+
+static TABLE: [CachePadded<Bucket>; 1 << BUCKET_BITS] = [const {
+    CachePadded::new(Bucket {
+        sleepers: AtomicUsize::new(0),
+        epoch: AtomicU32::new(0),
+    })
+}; 1 << BUCKET_BITS];
+
+fn bucket_of(addr: usize) -> &'static Bucket {
+    // Fibonacci hashing, keeping the upper bits which are the best mixed.
+    let h = addr.wrapping_mul(0x9E37_79B9_7F4A_7C15_u64 as usize);
+    &TABLE[h >> (usize::BITS - BUCKET_BITS)]
+}
+
+/// Blocks the thread until `ready` returns `true`, checking it again every
+/// time the atomic at `addr` is notified.
+pub(crate) fn block_until(addr: usize, mut ready: impl FnMut() -> bool) {
+    let bucket = bucket_of(addr);
+    while !ready() {
+        let epoch = bucket.epoch.load(Ordering::Relaxed);
+        bucket.sleepers.fetch_add(1, Ordering::Relaxed);
+        SLEEPING.fetch_add(1, Ordering::SeqCst);
+        // Pairs with the fence of `notify`: either we see the modification,
+        // or the notifier sees us and bumps the epoch we are about to wait
+        // on.
+        fence(Ordering::SeqCst);
+        if !ready() {
+            Platform::wait(&bucket.epoch, epoch);
+        }
+        SLEEPING.fetch_sub(1, Ordering::Relaxed);
+        bucket.sleepers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Wakes up the threads blocked on the atomic at `addr`, and those on the
+/// atomics sharing its bucket.
+///
+/// Must be called after the atomic was modified with `order`.
+pub(crate) fn notify(addr: usize, order: Ordering) {
+    // A `SeqCst` modification is already ordered before the `SeqCst` load
+    // below.
+    if order != Ordering::SeqCst {
+        fence(Ordering::SeqCst);
+    }
+    if SLEEPING.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+    // The count of the bucket was raised before `SLEEPING`, so it is seen by
+    // now if the sleeper is.
+    let bucket = bucket_of(addr);
+    if bucket.sleepers.load(Ordering::Relaxed) != 0 {
+        bucket.epoch.fetch_add(1, Ordering::Relaxed);
+        Platform::wake_all(&bucket.epoch);
+    }
+}
+
+/// Sleeps with the `futex` system call.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) struct Futex;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl TrWait for Futex {
+    #[inline]
+    fn wait(word: &AtomicU32, expected: u32) {
+        unsafe {
+            // Interruptions and mismatches are spurious returns to the
+            // caller.
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                expected,
+                core::ptr::null::<libc::timespec>(),
+            );
+        }
+    }
+
+    #[inline]
+    fn wake_all(word: &AtomicU32) {
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                i32::MAX,
+            );
+        }
+    }
+}
+
+/// Sleeps with `WaitOnAddress`.
+#[cfg(windows)]
+pub(crate) struct WaitOnAddress;
+
+#[cfg(windows)]
+impl TrWait for WaitOnAddress {
+    #[inline]
+    fn wait(word: &AtomicU32, expected: u32) {
+        use windows_sys::Win32::System::Threading as threading;
+
+        unsafe {
+            threading::WaitOnAddress(
+                word.as_ptr().cast(),
+                (&expected as *const u32).cast(),
+                core::mem::size_of::<u32>(),
+                threading::INFINITE,
+            );
+        }
+    }
+
+    #[inline]
+    fn wake_all(word: &AtomicU32) {
+        unsafe {
+            windows_sys::Win32::System::Threading::WakeByAddressAll(
+                word.as_ptr().cast(),
+            );
+        }
+    }
+}
@@ -0,0 +1,143 @@
+﻿use core::{
+    fmt::{self, Debug},
+    sync::atomic::Ordering,
+};
+use crate::{
+    fetch::{self, Max},
+    AtomicCountOwned, RelaxedCountOrderings, TrAtomicCell, TrAtomicData,
+};
+
+/// A metric of a current amount, such as the connections or the bytes in
+/// use, also tracking the highest amount seen.
+///
+/// Unlike `AtomicCount`, the amount never goes below zero, since releasing
+/// more than was acquired clamps it, and it can be set outright. Updates are
+/// relaxed: a gauge is meant to be observed, not to synchronize.
+///
+/// # Example
+///
+/// ```
+/// use atomex::Gauge;
+///
+/// let in_use = Gauge::<usize>::new();
+/// in_use.add(3);
+/// in_use.sub(1);
+/// assert_eq!(in_use.get(), 2);
+/// in_use.sub(5);
+/// assert_eq!(in_use.get(), 0);
+/// in_use.set(4);
+/// assert_eq!(in_use.max_seen(), 4);
+/// assert_eq!(in_use.reset_max_seen(), 4);
+/// assert_eq!(in_use.max_seen(), 4);
+/// ```
+pub struct Gauge<V>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>
+        + fetch::Max<Value = V>,
+{
+    value: AtomicCountOwned<V, RelaxedCountOrderings>,
+    max_seen: <V as TrAtomicData>::AtomicCell,
+}
+
+impl<V> Gauge<V>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>
+        + fetch::Max<Value = V>,
+{
+    pub fn new() -> Self {
+        Gauge {
+            value: AtomicCountOwned::new(TrAtomicCell::new(V::ZERO)),
+            max_seen: TrAtomicCell::new(V::ZERO),
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self) -> V {
+        self.value.val()
+    }
+
+    /// The highest amount since the gauge was created or the last
+    /// `reset_max_seen`.
+    #[inline(always)]
+    pub fn max_seen(&self) -> V {
+        self.max_seen.load(Ordering::Relaxed)
+    }
+
+    /// Sets the amount, and returns the previous one.
+    pub fn set(&self, val: V) -> V {
+        let prev = self.value.swap(val);
+        self.see(val);
+        prev
+    }
+
+    #[inline(always)]
+    pub fn inc(&self) -> V {
+        self.add(V::ONE)
+    }
+
+    /// Adds `val` to the amount, clamping at the numeric bound, and returns
+    /// the previous amount.
+    pub fn add(&self, val: V) -> V {
+        let prev = self.value.saturating_add(val);
+        self.see(prev.saturating_add(val));
+        prev
+    }
+
+    #[inline(always)]
+    pub fn dec(&self) -> V {
+        self.sub(V::ONE)
+    }
+
+    /// Subtracts `val` from the amount, clamping at zero, and returns the
+    /// previous amount.
+    #[inline(always)]
+    pub fn sub(&self, val: V) -> V {
+        self.value.saturating_sub(val)
+    }
+
+    /// Restarts tracking the highest amount from the current one, and
+    /// returns the highest amount seen until then.
+    pub fn reset_max_seen(&self) -> V {
+        self.max_seen.swap(self.get(), Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    fn see(&self, val: V) {
+        self.max_seen.fetch_max(val, Ordering::Relaxed);
+    }
+}
+
+impl<V> Default for Gauge<V>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>
+        + fetch::Max<Value = V>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Debug for Gauge<V>
+where
+    V: TrAtomicData + funty::Unsigned,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>
+        + fetch::Max<Value = V>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gauge")
+            .field("value", &self.get())
+            .field("max_seen", &self.max_seen())
+            .finish()
+    }
+}
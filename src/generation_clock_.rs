@@ -0,0 +1,80 @@
+﻿use core::fmt;
+use crate::{atomics_::AtomicU64, AtomicCount, RefCountOrderings};
+
+/// A global generation counter, advanced by writers and compared by readers
+/// to tell whether what they cached is still current.
+///
+/// Advancing releases the writes made before it, and reading the current
+/// generation acquires them. Generations are compared with serial number
+/// arithmetic, so that the comparisons stay correct across the wraparound
+/// of the counter, as long as the generations compared are less than `2^63`
+/// apart.
+///
+/// # Example
+///
+/// ```
+/// use atomex::GenerationClock;
+///
+/// static CLOCK: GenerationClock = GenerationClock::new();
+///
+/// let cached_at = CLOCK.current();
+/// assert!(!CLOCK.has_advanced_since(cached_at));
+/// let now = CLOCK.advance();
+/// assert!(CLOCK.has_advanced_since(cached_at));
+/// assert!(GenerationClock::is_before(cached_at, now));
+/// assert!(GenerationClock::is_after(0, u64::MAX));
+/// ```
+pub struct GenerationClock(AtomicCount<u64, AtomicU64, RefCountOrderings>);
+
+impl GenerationClock {
+    /// Creates a clock at generation `0`.
+    pub const fn new() -> Self {
+        Self::starting_at(0)
+    }
+
+    pub const fn starting_at(generation: u64) -> Self {
+        GenerationClock(AtomicCount::new(AtomicU64::new(generation)))
+    }
+
+    /// The current generation.
+    #[inline(always)]
+    pub fn current(&self) -> u64 {
+        self.0.val()
+    }
+
+    /// Moves on to the next generation, wrapping around, and returns it.
+    #[inline(always)]
+    pub fn advance(&self) -> u64 {
+        self.0.wrapping_add(1).wrapping_add(1)
+    }
+
+    /// Returns `true` if the clock has advanced past `generation`.
+    #[inline(always)]
+    pub fn has_advanced_since(&self, generation: u64) -> bool {
+        Self::is_after(self.current(), generation)
+    }
+
+    /// Returns `true` if generation `a` comes before generation `b`.
+    #[inline(always)]
+    pub const fn is_before(a: u64, b: u64) -> bool {
+        (a.wrapping_sub(b) as i64) < 0
+    }
+
+    /// Returns `true` if generation `a` comes after generation `b`.
+    #[inline(always)]
+    pub const fn is_after(a: u64, b: u64) -> bool {
+        Self::is_before(b, a)
+    }
+}
+
+impl Default for GenerationClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for GenerationClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("GenerationClock").field(&self.current()).finish()
+    }
+}
@@ -0,0 +1,217 @@
+﻿use core::{fmt, ptr::{self, NonNull}};
+use crate::{AcqRelOrderings, AtomexMarkedPtr};
+
+type Link<K> = AtomexMarkedPtr<HarrisNode<K>, AcqRelOrderings>;
+
+/// The node embedded in the items of a `HarrisList`, holding the key the
+/// list is ordered by.
+///
+/// To get back to an item from its node, put the node first in a
+/// `#[repr(C)]` item and cast the pointer.
+pub struct HarrisNode<K> {
+    key: K,
+    /// The successor, marked once the node is logically removed.
+    next: Link<K>,
+}
+
+impl<K> HarrisNode<K> {
+    pub const fn new(key: K) -> Self {
+        HarrisNode {
+            key,
+            next: AtomexMarkedPtr::new(ptr::null_mut(), false),
+        }
+    }
+
+    #[inline(always)]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns `true` if the node has been removed from a list, which it may
+    /// still be physically linked in.
+    #[inline(always)]
+    pub fn is_removed(&self) -> bool {
+        self.next.is_marked()
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for HarrisNode<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HarrisNode")
+            .field("key", &self.key)
+            .field("is_removed", &self.is_removed())
+            .finish()
+    }
+}
+
+/// An intrusive lock-free ordered set (Harris's list), requiring no
+/// allocation.
+///
+/// A removal first marks the successor link of the node, which removes it
+/// logically and freezes the link, then unlinks it physically. Any traversal
+/// finding a marked node on its way unlinks it before going further, so a
+/// removal whose unlinking failed is completed by the next operation.
+///
+/// The list does not reclaim the nodes. A removed node may still be read by
+/// the operations already running, so it must not be freed, moved or
+/// inserted again until they have all returned.
+///
+/// # Example
+///
+/// ```
+/// use core::ptr::NonNull;
+/// use atomex::{HarrisList, HarrisNode};
+///
+/// let nodes = [3, 1, 2].map(HarrisNode::new);
+/// let list = HarrisList::new();
+/// for n in nodes.iter() {
+///     assert!(unsafe { list.insert(NonNull::from(n)) });
+/// }
+/// let dup = HarrisNode::new(2);
+/// assert!(!unsafe { list.insert(NonNull::from(&dup)) });
+///
+/// let removed = list.remove(&1).unwrap();
+/// assert_eq!(unsafe { removed.as_ref() }.key(), &1);
+/// assert!(!list.contains(&1));
+/// assert!(list.contains(&2) && list.contains(&3));
+/// assert!(list.remove(&1).is_none());
+/// ```
+pub struct HarrisList<K> {
+    head: Link<K>,
+}
+
+unsafe impl<K: Send + Sync> Send for HarrisList<K> {}
+unsafe impl<K: Send + Sync> Sync for HarrisList<K> {}
+
+impl<K: Ord> HarrisList<K> {
+    pub const fn new() -> Self {
+        HarrisList {
+            head: AtomexMarkedPtr::new(ptr::null_mut(), false),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let mut curr = self.head.pointer();
+        // Skips the removed nodes not unlinked yet.
+        while !curr.is_null() {
+            let (next, removed) = unsafe { (*curr).next.load() };
+            if !removed {
+                return false;
+            }
+            curr = next;
+        }
+        true
+    }
+
+    /// Links `node` in order of its key, unless a node with an equal key is
+    /// in the list, in which case `false` is returned.
+    ///
+    /// # Safety
+    ///
+    /// `node` must not be in any list, and must stay valid and in place as
+    /// long as the list may read it: until the list is dropped, or until it
+    /// has been removed and the operations running at that time returned.
+    pub unsafe fn insert(&self, node: NonNull<HarrisNode<K>>) -> bool {
+        let node = node.as_ptr();
+        loop {
+            let (prev, curr) = self.search(unsafe { &(*node).key });
+            if !curr.is_null() && unsafe { (*curr).key == (*node).key } {
+                return false;
+            }
+            // The node is not shared until the exchange succeeds.
+            unsafe { (*node).next.store(curr, false) };
+            let linked = unsafe { &*prev }
+                .compare_exchange((curr, false), (node, false))
+                .is_ok();
+            if linked {
+                return true;
+            }
+        }
+    }
+
+    /// Removes the node with a key equal to `key`, returning it.
+    pub fn remove(&self, key: &K) -> Option<NonNull<HarrisNode<K>>> {
+        loop {
+            let (prev, curr) = self.search(key);
+            if curr.is_null() || unsafe { &(*curr).key } != key {
+                return Option::None;
+            }
+            let (next, removed) = unsafe { (*curr).next.fetch_mark() };
+            if removed {
+                // Removed by another thread meanwhile, which may have left it
+                // for the next search to unlink.
+                continue;
+            }
+            let unlinked = unsafe { &*prev }
+                .compare_exchange((curr, false), (next, false))
+                .is_ok();
+            if !unlinked {
+                let _ = self.search(key);
+            }
+            // Within a list, hence not null.
+            return Option::Some(unsafe { NonNull::new_unchecked(curr) });
+        }
+    }
+
+    /// Returns `true` if a node with a key equal to `key` is in the list.
+    ///
+    /// Wait-free, as it neither unlinks nor restarts.
+    pub fn contains(&self, key: &K) -> bool {
+        let mut curr = self.head.pointer();
+        while !curr.is_null() {
+            let node = unsafe { &*curr };
+            let (next, removed) = node.next.load();
+            if node.key >= *key {
+                return node.key == *key && !removed;
+            }
+            curr = next;
+        }
+        false
+    }
+
+    /// Finds the first node whose key is not less than `key`, returning it
+    /// with the link pointing to it, while unlinking the removed nodes on
+    /// its way.
+    fn search(&self, key: &K) -> (*const Link<K>, *mut HarrisNode<K>) {
+        'retry: loop {
+            let mut prev: *const Link<K> = &self.head;
+            let mut curr = self.head.pointer();
+            loop {
+                if curr.is_null() {
+                    return (prev, curr);
+                }
+                let (next, removed) = unsafe { (*curr).next.load() };
+                if removed {
+                    // Fails if `prev` has been removed or changed meanwhile.
+                    let unlinked = unsafe { &*prev }
+                        .compare_exchange((curr, false), (next, false))
+                        .is_ok();
+                    if !unlinked {
+                        continue 'retry;
+                    }
+                    curr = next;
+                    continue;
+                }
+                if unsafe { &(*curr).key } >= key {
+                    return (prev, curr);
+                }
+                prev = unsafe { &(*curr).next };
+                curr = next;
+            }
+        }
+    }
+}
+
+impl<K: Ord> Default for HarrisList<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord> fmt::Debug for HarrisList<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HarrisList")
+            .field("is_empty", &self.is_empty())
+            .finish()
+    }
+}
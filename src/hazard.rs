@@ -0,0 +1,374 @@
+﻿//! Hazard pointers protecting the pointee loaded from an `AtomexPtr` against
+//! reclamation.
+//!
+//! A reader publishes the pointer it is about to dereference into a slot of a
+//! [`HazardDomain`]. A reclaimer, after unlinking a pointer from every
+//! `AtomexPtr` it was reachable through, checks the domain with
+//! [`HazardDomain::is_protected`] and only frees the pointee once no slot
+//! holds it any longer.
+//!
+//! # Example
+//!
+//! ```
+//! use core::{ptr::NonNull, sync::atomic::AtomicPtr};
+//! use atomex::{hazard::HazardDomain, AtomexPtr, StrictOrderings};
+//!
+//! let domain = HazardDomain::<4>::new();
+//! let mut value = 42usize;
+//! let p = NonNull::from(&mut value);
+//! let atom = AtomexPtr::<usize, AtomicPtr<usize>, StrictOrderings>::new(
+//!     AtomicPtr::new(p.as_ptr()));
+//!
+//! let guard = atom.load_guarded(&domain);
+//! assert_eq!(unsafe { guard.as_ref() }, Some(&42));
+//!
+//! assert!(atom.try_reset().is_ok());
+//! assert!(domain.is_protected(p.as_ptr()));
+//! drop(guard);
+//! assert!(!domain.is_protected(p.as_ptr()));
+//! ```
+//!
+//! With the `alloc` feature, a reclaimer can instead hand the unlinked pointer
+//! over to [`HazardDomain::retire`], which frees it in a later scan of the
+//! domain, once it is no longer protected.
+use core::{
+    borrow::BorrowMut,
+    fmt,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+    sync::atomic::{self, Ordering},
+};
+use crate::atomics_::{AtomicBool, AtomicPtr};
+use crate::{relax, AtomexPtr, TrCmpxchOrderings};
+#[cfg(feature = "alloc")]
+use crate::atomics_::AtomicUsize;
+#[cfg(feature = "alloc")]
+use crate::retired_::{Retired, RetiredList};
+
+struct HazardSlot {
+    claimed: AtomicBool,
+    hazard: AtomicPtr<()>,
+}
+
+impl HazardSlot {
+    const fn new() -> Self {
+        HazardSlot {
+            claimed: AtomicBool::new(false),
+            hazard: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Publishes the pointer returned by `load`, once two loads in a row
+    /// return it around its publication, and returns it.
+    fn publish<T>(&self, mut load: impl FnMut() -> *mut T) -> *mut T {
+        let mut pointer = load();
+        loop {
+            self.hazard.store(pointer as *mut (), Ordering::SeqCst);
+            // Orders the publication before the reload, whatever its
+            // ordering, so that a reclaimer either sees the hazard or the
+            // reload sees the unlinking.
+            atomic::fence(Ordering::SeqCst);
+            let reloaded = load();
+            if ptr::eq(reloaded, pointer) {
+                break pointer;
+            }
+            pointer = reloaded;
+        }
+    }
+}
+
+/// A fixed set of `N` hazard slots shared by readers and reclaimers of the
+/// same pointers.
+///
+/// A slot is claimed by a guard for its lifetime rather than by a thread,
+/// which needs no thread-local storage. At most `N` guards can be alive at
+/// the same time; acquiring one more spins until another guard is dropped.
+pub struct HazardDomain<const N: usize = 32> {
+    slots: [HazardSlot; N],
+    #[cfg(feature = "alloc")]
+    retired: RetiredList,
+    #[cfg(feature = "alloc")]
+    retired_count: AtomicUsize,
+}
+
+impl<const N: usize> HazardDomain<N> {
+    /// The number of retired pointers triggering a scan of the domain.
+    #[cfg(feature = "alloc")]
+    const SCAN_THRESHOLD: usize = 2 * N + 1;
+
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "[HazardDomain] no hazard slot") };
+        HazardDomain {
+            slots: [const { HazardSlot::new() }; N],
+            #[cfg(feature = "alloc")]
+            retired: RetiredList::new(),
+            #[cfg(feature = "alloc")]
+            retired_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Loads the pointer of `atom` and protects it, so that it will not be
+    /// reclaimed until the returned guard is dropped.
+    pub fn protect<T, B, O>(
+        &self,
+        atom: &AtomexPtr<T, B, O>,
+    ) -> HazardGuard<'_, T>
+    where
+        B: BorrowMut<AtomicPtr<T>>,
+        O: TrCmpxchOrderings,
+    {
+        self.protect_with(|| atom.pointer())
+    }
+
+    /// Returns `true` if any guard of this domain currently protects `p`.
+    pub fn is_protected<T>(&self, p: *mut T) -> bool {
+        let p = p as *mut ();
+        if p.is_null() {
+            return false;
+        }
+        self.slots
+            .iter()
+            .any(|s| ptr::eq(s.hazard.load(Ordering::SeqCst), p))
+    }
+
+    /// Spins until no guard of this domain protects `p` any longer.
+    ///
+    /// The caller must have unlinked `p` from every place a reader could load
+    /// it from, otherwise new guards may keep showing up.
+    pub fn wait_unprotected<T>(&self, p: *mut T) {
+        while self.is_protected(p) {
+            relax::relax();
+        }
+    }
+
+    fn claim_slot(&self) -> &HazardSlot {
+        &self.slots[self.claim_index()]
+    }
+
+    fn claim_index(&self) -> usize {
+        loop {
+            for (i, slot) in self.slots.iter().enumerate() {
+                if slot.claimed.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if slot
+                    .claimed
+                    .compare_exchange_weak(
+                        false,
+                        true,
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+                {
+                    return i;
+                }
+            }
+            relax::relax();
+        }
+    }
+
+    /// Claims the slots of the same index in both domains of `pair`, for a
+    /// traversal protecting two pointers at a time.
+    ///
+    /// The slots of `pair[1]` are only ever claimed along with those of
+    /// `pair[0]`, so that a traversal never holds a slot while waiting for
+    /// another one.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn claim_pair(pair: &[Self; 2]) -> HazardPair<'_> {
+        let i = pair[0].claim_index();
+        HazardPair {
+            slots: [&pair[0].slots[i], &pair[1].slots[i]],
+        }
+    }
+
+    /// Protects the pointer returned by `load`, once two loads in a row
+    /// return it around its publication.
+    pub(crate) fn protect_with<T>(
+        &self,
+        load: impl FnMut() -> *mut T,
+    ) -> HazardGuard<'_, T> {
+        let slot = self.claim_slot();
+        let pointer = slot.publish(load);
+        HazardGuard {
+            slot,
+            pointer,
+            _pointee: PhantomData,
+        }
+    }
+
+    /// Hands `p` over to the domain, which destroys it with `dtor` once no
+    /// guard protects it any longer.
+    ///
+    /// The retired pointers are scanned every time their number reaches
+    /// about twice the number of slots, so that the cost of a scan is spread
+    /// over the retirements.
+    ///
+    /// # Safety
+    ///
+    /// `p` must have been unlinked from every place a reader could load it
+    /// from, and must not be retired again. `dtor` must be safe to call on it
+    /// from any thread.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::{ptr, sync::atomic::AtomicPtr};
+    /// use atomex::{hazard::HazardDomain, AtomexPtr, StrictOrderings};
+    ///
+    /// unsafe fn free(p: *mut u32) {
+    ///     drop(unsafe { Box::from_raw(p) });
+    /// }
+    ///
+    /// let domain = HazardDomain::<4>::new();
+    /// let atom = AtomexPtr::<u32, AtomicPtr<u32>, StrictOrderings>::new(
+    ///     AtomicPtr::new(Box::into_raw(Box::new(7))));
+    ///
+    /// let guard = domain.protect(&atom);
+    /// let old = atom.swap(ptr::null_mut());
+    /// unsafe { domain.retire(old, free) };
+    /// domain.reclaim();
+    /// assert_eq!(domain.retired_count(), 1);
+    ///
+    /// assert_eq!(unsafe { guard.as_ref() }, Some(&7));
+    /// drop(guard);
+    /// domain.reclaim();
+    /// assert_eq!(domain.retired_count(), 0);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub unsafe fn retire<T>(&self, p: *mut T, dtor: unsafe fn(*mut T)) {
+        self.retired.push(Retired::new(p, dtor, 0));
+        let count = self.retired_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if count >= Self::SCAN_THRESHOLD {
+            self.reclaim();
+        }
+    }
+
+    /// Destroys the retired pointers which are no longer protected.
+    #[cfg(feature = "alloc")]
+    pub fn reclaim(&self) {
+        // Orders the unlinking of the retired pointers before the loads of
+        // the hazards.
+        atomic::fence(Ordering::SeqCst);
+        for retired in self.retired.take() {
+            if self.is_protected(retired.pointer()) {
+                self.retired.push(retired);
+                continue;
+            }
+            self.retired_count.fetch_sub(1, Ordering::Relaxed);
+            unsafe { retired.destroy() };
+        }
+    }
+
+    /// The number of retired pointers not destroyed yet.
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    pub fn retired_count(&self) -> usize {
+        self.retired_count.load(Ordering::Relaxed)
+    }
+
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> Drop for HazardDomain<N> {
+    fn drop(&mut self) {
+        // No guard can be alive while the domain is borrowed mutably.
+        for retired in self.retired.take_mut() {
+            unsafe { retired.destroy() };
+        }
+    }
+}
+
+impl<const N: usize> Default for HazardDomain<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for HazardDomain<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let in_use = self
+            .slots
+            .iter()
+            .filter(|s| s.claimed.load(Ordering::Relaxed))
+            .count();
+        f.debug_struct("HazardDomain")
+            .field("slots", &N)
+            .field("in_use", &in_use)
+            .finish()
+    }
+}
+
+/// Keeps a pointer loaded from an `AtomexPtr` protected from reclamation
+/// until dropped.
+pub struct HazardGuard<'d, T> {
+    slot: &'d HazardSlot,
+    pointer: *mut T,
+    _pointee: PhantomData<*mut T>,
+}
+
+impl<T> HazardGuard<'_, T> {
+    /// The protected pointer, which may be null.
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *mut T {
+        self.pointer
+    }
+
+    #[inline(always)]
+    pub fn as_nonnull(&self) -> Option<NonNull<T>> {
+        NonNull::new(self.pointer)
+    }
+
+    /// Dereferences the protected pointer.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have been valid when it was stored, and every
+    /// reclaimer of it must consult the same `HazardDomain` before freeing.
+    pub unsafe fn as_ref(&self) -> Option<&T> {
+        unsafe { self.pointer.as_ref() }
+    }
+}
+
+impl<T> Drop for HazardGuard<'_, T> {
+    fn drop(&mut self) {
+        self.slot.hazard.store(ptr::null_mut(), Ordering::Release);
+        self.slot.claimed.store(false, Ordering::Release);
+    }
+}
+
+/// Two hazard slots claimed together by `HazardDomain::claim_pair`, which
+/// protect the pointers of a hand-over-hand traversal in turn.
+#[cfg(feature = "alloc")]
+pub(crate) struct HazardPair<'d> {
+    slots: [&'d HazardSlot; 2],
+}
+
+#[cfg(feature = "alloc")]
+impl HazardPair<'_> {
+    /// Protects the pointer returned by `load` in the slot `which`, in place
+    /// of the pointer it protected.
+    pub(crate) fn protect_with<T>(
+        &self,
+        which: usize,
+        load: impl FnMut() -> *mut T,
+    ) -> *mut T {
+        self.slots[which].publish(load)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for HazardPair<'_> {
+    fn drop(&mut self) {
+        for slot in self.slots {
+            slot.hazard.store(ptr::null_mut(), Ordering::Release);
+        }
+        self.slots[0].claimed.store(false, Ordering::Release);
+    }
+}
+
+impl<T> fmt::Debug for HazardGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HazardGuard").field(&self.pointer).finish()
+    }
+}
@@ -0,0 +1,103 @@
+﻿use core::{
+    array,
+    fmt,
+};
+use crate::{
+    atomics_::AtomicU64, AtomicCount, CachePadded, RelaxedCountOrderings,
+};
+
+type BucketCount = AtomicCount<u64, AtomicU64, RelaxedCountOrderings>;
+
+/// The counts of values recorded by many threads in `N` buckets, such as a
+/// latency distribution.
+///
+/// The bucket of a value is chosen by a function supplied on creation, such
+/// as `Histogram::log2_bucket`, and clamped to the last bucket. Each bucket
+/// is a separate cache-padded counter, so that threads recording values in
+/// different buckets do not contend.
+///
+/// # Example
+///
+/// ```
+/// use atomex::Histogram;
+///
+/// let sizes = Histogram::<8>::new(Histogram::<8>::log2_bucket);
+/// for len in [0, 1, 3, 900, 1 << 40] {
+///     sizes.record(len);
+/// }
+/// assert_eq!(sizes.snapshot(), [1, 1, 1, 0, 0, 0, 0, 2]);
+/// assert_eq!(sizes.count(), 5);
+/// ```
+pub struct Histogram<const N: usize> {
+    buckets: [CachePadded<BucketCount>; N],
+    bucket_of: fn(u64) -> usize,
+}
+
+impl<const N: usize> Histogram<N> {
+    /// Creates a histogram recording each value in bucket `bucket_of(value)`.
+    pub const fn new(bucket_of: fn(u64) -> usize) -> Self {
+        const { assert!(N > 0, "[Histogram] no bucket") };
+        Histogram {
+            buckets: [const {
+                CachePadded::new(AtomicCount::new(AtomicU64::new(0)))
+            }; N],
+            bucket_of,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn bucket_count(&self) -> usize {
+        N
+    }
+
+    /// Counts `value` in its bucket.
+    pub fn record(&self, value: u64) {
+        self.record_n(value, 1)
+    }
+
+    /// Counts `value` `n` times in its bucket.
+    pub fn record_n(&self, value: u64, n: u64) {
+        let index = (self.bucket_of)(value).min(N - 1);
+        self.buckets[index].wrapping_add(n);
+    }
+
+    /// The count of the bucket at `index`.
+    #[inline(always)]
+    pub fn bucket(&self, index: usize) -> u64 {
+        self.buckets[index].val()
+    }
+
+    /// Reads the counts of all the buckets.
+    ///
+    /// The result is not a snapshot of a single instant: values recorded
+    /// while reading may or may not be included.
+    pub fn snapshot(&self) -> [u64; N] {
+        array::from_fn(|i| self.bucket(i))
+    }
+
+    /// The number of values recorded in all the buckets.
+    pub fn count(&self) -> u64 {
+        self.buckets
+            .iter()
+            .fold(0u64, |acc, b| acc.wrapping_add(b.val()))
+    }
+
+    /// Forgets all the values.
+    pub fn reset(&self) {
+        for b in self.buckets.iter() {
+            b.set(0);
+        }
+    }
+
+    /// A bucketing function putting `0` in bucket 0, and the values in
+    /// `2^(i-1)..2^i` in bucket `i`.
+    pub const fn log2_bucket(value: u64) -> usize {
+        (u64::BITS - value.leading_zeros()) as usize
+    }
+}
+
+impl<const N: usize> fmt::Debug for Histogram<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Histogram").field(&self.snapshot()).finish()
+    }
+}
@@ -0,0 +1,123 @@
+﻿use core::{
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomic_bitmap_::claim_lowest_zero,
+    atomics_::AtomicUsize,
+    AcqRelOrderings, AtomicFlags,
+};
+
+type Word = AtomicFlags<usize, AtomicUsize, AcqRelOrderings>;
+
+/// An allocator of the ids `0..N * usize::BITS`, tracked in a bitmap of `N`
+/// atomic words, each bit set for an allocated id.
+///
+/// An allocation takes the first clear bit of a word with a single exchange,
+/// starting from the word of the last allocation or release instead of the
+/// first word, so that concurrent allocations spread over the bitmap rather
+/// than contending on its first words. Released ids are reused, which keeps
+/// them compact.
+///
+/// # Example
+///
+/// ```
+/// use atomex::IdAllocator;
+///
+/// static SESSIONS: IdAllocator<2> = IdAllocator::new();
+///
+/// let a = SESSIONS.alloc().unwrap();
+/// let b = SESSIONS.alloc().unwrap();
+/// assert_eq!((a, b), (0, 1));
+/// SESSIONS.free(a);
+/// assert_eq!(SESSIONS.alloc(), Some(0));
+/// assert!(SESSIONS.is_allocated(b));
+/// assert_eq!(SESSIONS.allocated_count(), 2);
+/// ```
+pub struct IdAllocator<const N: usize> {
+    words: [Word; N],
+    /// The word to start looking from.
+    hint: AtomicUsize,
+}
+
+impl<const N: usize> IdAllocator<N> {
+    const BITS: usize = usize::BITS as usize;
+
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "[IdAllocator] no bitmap word") };
+        IdAllocator {
+            words: [const { AtomicFlags::new(AtomicUsize::new(0)) }; N],
+            hint: AtomicUsize::new(0),
+        }
+    }
+
+    /// The number of ids, allocated or not.
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N * Self::BITS
+    }
+
+    /// Allocates an id, or returns `None` if all of them are allocated.
+    pub fn alloc(&self) -> Option<usize> {
+        let start = self.hint.load(Ordering::Relaxed) % N;
+        for k in (start..N).chain(0..start) {
+            let r = claim_lowest_zero::<AcqRelOrderings>(
+                self.words[k].as_ref(),
+            );
+            if let Option::Some(bit) = r {
+                if k != start {
+                    self.hint.store(k, Ordering::Relaxed);
+                }
+                return Option::Some(k * Self::BITS + bit);
+            }
+        }
+        Option::None
+    }
+
+    /// Releases `id` for a later allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is out of range or not allocated.
+    pub fn free(&self, id: usize) {
+        assert!(id < self.capacity(), "[IdAllocator::free] id out of range");
+        let k = id / Self::BITS;
+        let taken = self.words[k].fetch_take(1 << (id % Self::BITS));
+        assert!(taken != 0, "[IdAllocator::free] id {id} not allocated");
+        self.hint.store(k, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if `id` is allocated right now.
+    pub fn is_allocated(&self, id: usize) -> bool {
+        assert!(
+            id < self.capacity(),
+            "[IdAllocator::is_allocated] id out of range",
+        );
+        let w = self.words[id / Self::BITS].value();
+        w & (1 << (id % Self::BITS)) != 0
+    }
+
+    /// The number of allocated ids, which may be outdated as soon as it is
+    /// returned.
+    pub fn allocated_count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|w| w.value().count_ones() as usize)
+            .sum()
+    }
+}
+
+impl<const N: usize> Default for IdAllocator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for IdAllocator<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdAllocator")
+            .field("capacity", &self.capacity())
+            .field("allocated_count", &self.allocated_count())
+            .finish()
+    }
+}
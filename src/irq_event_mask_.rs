@@ -0,0 +1,117 @@
+﻿use core::{
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::{fetch, Bitwise, TrAtomicCell, TrAtomicData};
+
+/// A set of pending events, raised by interrupt handlers and taken by the
+/// main loop, each event being a bit of `V`.
+///
+/// The handlers only ever set bits, with `raise` or `publish`, and the main
+/// loop only ever clears them, with `take` or `take_all`, so that no event
+/// is lost: a bit raised again before it is taken counts once, and a bit
+/// raised after it is taken stays pending for the next round.
+///
+/// `raise` is relaxed, for events that carry no data. An event announcing
+/// data that the handler wrote, e.g. into a buffer, is raised by `publish`,
+/// which releases the data to the `take` acquiring the event.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicU32;
+/// use atomex::IrqEventMask;
+///
+/// const UART_RX: u32 = 0b01;
+/// const TIMER: u32 = 0b10;
+///
+/// static EVENTS: IrqEventMask<u32> = IrqEventMask::new(AtomicU32::new(0));
+///
+/// // In the interrupt handlers.
+/// EVENTS.raise(TIMER);
+/// EVENTS.publish(UART_RX);
+///
+/// // In the main loop.
+/// assert_eq!(EVENTS.take(UART_RX), UART_RX);
+/// assert!(EVENTS.is_pending(TIMER));
+/// assert_eq!(EVENTS.take_all(), TIMER);
+/// assert_eq!(EVENTS.pending(), 0);
+/// ```
+pub struct IrqEventMask<V>(<V as TrAtomicData>::AtomicCell)
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V> + Bitwise;
+
+impl<V> IrqEventMask<V>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V> + Bitwise,
+{
+    pub const fn new(cell: <V as TrAtomicData>::AtomicCell) -> Self {
+        IrqEventMask(cell)
+    }
+
+    /// Marks the events of `mask` pending, from an interrupt handler.
+    #[inline(always)]
+    pub fn raise(&self, mask: V) {
+        let _ = fetch::Or::fetch_or(&self.0, mask, Ordering::Relaxed);
+    }
+
+    /// Marks the events of `mask` pending, from an interrupt handler, and
+    /// releases what the handler wrote before to the main loop taking them.
+    #[inline(always)]
+    pub fn publish(&self, mask: V) {
+        let _ = fetch::Or::fetch_or(&self.0, mask, Ordering::Release);
+    }
+
+    /// Clears the events of `mask`, from the main loop, and returns those of
+    /// them that were pending.
+    #[inline(always)]
+    pub fn take(&self, mask: V) -> V {
+        fetch::And::fetch_and(&self.0, !mask, Ordering::Acquire) & mask
+    }
+
+    /// Clears all the events, from the main loop, and returns those that
+    /// were pending.
+    #[inline(always)]
+    pub fn take_all(&self) -> V {
+        self.0.swap(V::ZERO, Ordering::Acquire)
+    }
+
+    /// The events pending, without taking them.
+    #[inline(always)]
+    pub fn pending(&self) -> V {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    pub fn is_pending(&self, mask: V) -> bool {
+        self.pending() & mask != V::ZERO
+    }
+
+    pub fn into_inner(self) -> V {
+        self.0.into_inner()
+    }
+}
+
+impl<V> Default for IrqEventMask<V>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V> + Bitwise,
+{
+    fn default() -> Self {
+        Self::new(TrAtomicCell::new(V::ZERO))
+    }
+}
+
+impl<V> fmt::Debug for IrqEventMask<V>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V> + Bitwise,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IrqEventMask")
+            .field(&format_args!("{:#b}", self.pending()))
+            .finish()
+    }
+}
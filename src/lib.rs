@@ -4,22 +4,233 @@
 
 // We always pull in `std` during tests, because it's just easier
 // to write tests when you can assume you're on a capable platform
-#[cfg(test)]
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod atomex_fat_ptr_;
+mod atomex_marked_ptr_;
+mod atomex_offset_ptr_;
 mod atomex_ptr_;
+#[cfg(feature = "std")]
+mod atomex_rel_ptr_;
+mod atomic_bitmap_;
 mod atomic_cell_;
 mod atomic_count_;
+mod atomic_fixed_;
 mod atomic_flags_;
+mod atomic_index_;
+mod atomic_option_non_zero_usize_;
+mod atomic_ref_cell_;
+mod atomic_tristate_;
+#[cfg(feature = "alloc")]
+mod atomic_waker_;
+mod atomics_;
+mod backoff_;
+mod barrier_;
+mod bit_field_;
+#[cfg(feature = "bytemuck")]
+mod bytemuck_;
+mod cache_padded_;
+mod clock_hand_;
 mod cmpxch_result_;
+mod countdown_latch_;
+#[cfg(feature = "crossbeam")]
+mod crossbeam_;
+#[cfg(feature = "critical-section")]
+mod cs_cell_;
+mod define_flags_;
+mod dw_cas_;
+mod flag_;
+mod free_list_;
+#[cfg(feature = "futex")]
+mod futex_;
+mod emulated64_;
+mod error_slot_;
+mod event_;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+mod ewma_cell_;
+mod gauge_;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+mod generation_clock_;
+mod harris_list_;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+mod histogram_;
+mod id_allocator_;
+mod irq_event_mask_;
+#[cfg(feature = "alloc")]
+mod listener_list_;
+#[cfg(feature = "lock_api")]
+mod lock_api_;
+#[cfg(feature = "alloc")]
+mod lock_free_stack_;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+mod lww_register_;
+mod mcs_lock_;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+mod monotonic_stamp_;
+mod mpmc_queue_;
+mod mpsc_queue_;
+mod once_;
+mod once_cell_;
+#[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+mod packed_ptr_tag_;
+#[cfg(all(
+    feature = "std",
+    not(all(
+        feature = "futex",
+        any(target_os = "linux", target_os = "android", windows),
+    )),
+))]
+mod parker_;
+#[cfg(feature = "primitive-state")]
+mod primitive_state_;
+#[cfg(feature = "radium")]
+mod radium_;
+#[cfg(feature = "alloc")]
+mod rcu_cell_;
+mod ref_count_;
+mod reentrant_lock_;
+#[cfg(feature = "critical-section")]
+mod register_cell_;
+mod reservation_log_;
+#[cfg(feature = "alloc")]
+mod retired_;
+mod semaphore_;
+mod seq_count_;
+mod seq_lock_;
+#[cfg(feature = "serde")]
+mod serde_;
+mod sharded_count_;
+mod slot_registry_;
+mod snapshot_cell_;
+mod spin_lock_;
+mod spin_rw_lock_;
+mod spsc_ring_;
+mod state_cell_;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+mod stats_cell_;
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
+mod ticket_lock_;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+mod token_bucket_;
+mod versioned_cell_;
+#[cfg(feature = "std")]
+mod wait_;
+mod wait_group_;
+#[cfg(feature = "async")]
+mod wait_table_;
+mod waiters_;
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod dwcas;
+#[cfg(feature = "alloc")]
+pub mod epoch;
 pub mod fetch;
+pub mod hazard;
+#[cfg(feature = "alloc")]
+pub mod qsbr;
+pub mod relax;
 
+pub use atomex_fat_ptr_::*;
+pub use atomex_marked_ptr_::*;
+pub use atomex_offset_ptr_::*;
 pub use atomex_ptr_::*;
+#[cfg(feature = "std")]
+pub use atomex_rel_ptr_::*;
+pub use atomic_bitmap_::*;
 pub use atomic_cell_::*;
 pub use atomic_count_::*;
+pub use atomic_fixed_::*;
 pub use atomic_flags_::*;
+pub use atomic_index_::*;
+pub use atomic_option_non_zero_usize_::*;
+pub use atomic_ref_cell_::*;
+pub use atomic_tristate_::*;
+#[cfg(feature = "alloc")]
+pub use atomic_waker_::*;
+pub use backoff_::*;
+pub use barrier_::*;
+pub use bit_field_::*;
+pub use cache_padded_::*;
+pub use clock_hand_::*;
 pub use cmpxch_result_::*;
+pub use countdown_latch_::*;
+#[cfg(feature = "critical-section")]
+pub use cs_cell_::*;
+pub use dw_cas_::*;
+pub use emulated64_::*;
+pub use error_slot_::*;
+pub use event_::*;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub use ewma_cell_::*;
+pub use flag_::*;
+pub use free_list_::*;
+pub use gauge_::*;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub use generation_clock_::*;
+pub use harris_list_::*;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub use histogram_::*;
+pub use id_allocator_::*;
+pub use irq_event_mask_::*;
+#[cfg(feature = "alloc")]
+pub use listener_list_::*;
+#[cfg(feature = "alloc")]
+pub use lock_free_stack_::*;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub use lww_register_::*;
+pub use mcs_lock_::*;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub use monotonic_stamp_::*;
+pub use mpmc_queue_::*;
+pub use mpsc_queue_::*;
+pub use once_::*;
+pub use once_cell_::*;
+#[cfg(all(target_pointer_width = "64", target_has_atomic = "64"))]
+pub use packed_ptr_tag_::*;
+#[cfg(feature = "primitive-state")]
+pub use primitive_state_::*;
+#[cfg(feature = "radium")]
+pub use radium_::*;
+#[cfg(feature = "alloc")]
+pub use rcu_cell_::*;
+pub use ref_count_::*;
+pub use reentrant_lock_::*;
+#[cfg(feature = "critical-section")]
+pub use register_cell_::*;
+pub use reservation_log_::*;
+pub use semaphore_::*;
+pub use seq_count_::*;
+pub use seq_lock_::*;
+pub use sharded_count_::*;
+pub use slot_registry_::*;
+pub use snapshot_cell_::*;
+pub use spin_lock_::*;
+pub use spin_rw_lock_::*;
+pub use spsc_ring_::*;
+pub use state_cell_::*;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub use stats_cell_::*;
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
+pub use ticket_lock_::*;
+#[cfg(any(target_has_atomic = "64", feature = "portable-atomic"))]
+pub use token_bucket_::*;
+pub use versioned_cell_::*;
+pub use wait_group_::*;
 
 pub mod x_deps {
+    #[cfg(feature = "bytemuck")]
+    pub use bytemuck;
+    #[cfg(feature = "crossbeam")]
+    pub use crossbeam_utils;
     pub use funty;
+    #[cfg(feature = "lock_api")]
+    pub use lock_api;
+    #[cfg(feature = "radium")]
+    pub use radium;
+    #[cfg(feature = "serde")]
+    pub use serde;
 }
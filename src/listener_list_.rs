@@ -0,0 +1,260 @@
+﻿use core::{fmt, ptr::{self, NonNull}};
+use crate::{hazard::HazardDomain, AtomexMarkedPtr, AtomicWaker};
+
+type Link = AtomexMarkedPtr<ListenerNode>;
+
+/// A node of `ListenerList`, fired either by waking the task registered in
+/// its `AtomicWaker`, or by calling its callback.
+///
+/// To get back to an item from its listener in a callback, put the listener
+/// first in a `#[repr(C)]` item and cast the reference.
+pub struct ListenerNode {
+    /// The successor, marked once the listener is being removed.
+    next: Link,
+    waker: AtomicWaker,
+    callback: Option<fn(&ListenerNode)>,
+}
+
+impl ListenerNode {
+    /// A listener waking the task registered in its `waker`.
+    pub const fn new() -> Self {
+        ListenerNode {
+            next: AtomexMarkedPtr::new(ptr::null_mut(), false),
+            waker: AtomicWaker::new(),
+            callback: Option::None,
+        }
+    }
+
+    /// A listener calling `callback` on the notifying thread.
+    pub const fn with_callback(callback: fn(&ListenerNode)) -> Self {
+        ListenerNode {
+            next: AtomexMarkedPtr::new(ptr::null_mut(), false),
+            waker: AtomicWaker::new(),
+            callback: Option::Some(callback),
+        }
+    }
+
+    #[inline(always)]
+    pub fn waker(&self) -> &AtomicWaker {
+        &self.waker
+    }
+
+    fn fire(&self) {
+        match self.callback {
+            Option::Some(f) => f(self),
+            Option::None => self.waker.wake(),
+        }
+    }
+}
+
+impl Default for ListenerNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ListenerNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListenerNode")
+            .field("has_callback", &self.callback.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// An intrusive lock-free list of `ListenerNode`s, fired by `notify_all`.
+///
+/// Listeners are inserted at the head. A removal marks the successor link of
+/// its listener, and every traversal meeting a marked listener unlinks it
+/// before going further. Traversals protect the listeners they visit with
+/// hazard pointers of the list's own, and a removal only returns once its
+/// listener is no longer protected, after which the listener can be dropped.
+///
+/// Every traversal, i.e. every call to `notify_all` or `remove`, takes two
+/// hazard slots for its whole duration. Up to `N` of them run at the same
+/// time, and any more wait for one of them to end.
+///
+/// # Example
+///
+/// ```
+/// use core::{ptr::NonNull, sync::atomic::{AtomicUsize, Ordering}};
+/// use atomex::{ListenerNode, ListenerList};
+///
+/// static FIRED: AtomicUsize = AtomicUsize::new(0);
+/// fn on_event(_: &ListenerNode) {
+///     FIRED.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// let list = ListenerList::<4>::new();
+/// let a = ListenerNode::with_callback(on_event);
+/// let b = ListenerNode::with_callback(on_event);
+/// unsafe {
+///     list.insert(NonNull::from(&a));
+///     list.insert(NonNull::from(&b));
+/// }
+/// list.notify_all();
+/// assert_eq!(FIRED.load(Ordering::Relaxed), 2);
+///
+/// unsafe { list.remove(NonNull::from(&a)) };
+/// list.notify_all();
+/// assert_eq!(FIRED.load(Ordering::Relaxed), 3);
+/// ```
+pub struct ListenerList<const N: usize = 32> {
+    head: Link,
+    /// The slots of the same index in both domains make up the pair of
+    /// slots of a traversal.
+    domains: [HazardDomain<N>; 2],
+}
+
+unsafe impl<const N: usize> Send for ListenerList<N> {}
+unsafe impl<const N: usize> Sync for ListenerList<N> {}
+
+impl<const N: usize> ListenerList<N> {
+    pub const fn new() -> Self {
+        ListenerList {
+            head: AtomexMarkedPtr::new(ptr::null_mut(), false),
+            domains: [HazardDomain::new(), HazardDomain::new()],
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.head.pointer().is_null()
+    }
+
+    /// Links `listener` at the head of the list.
+    ///
+    /// # Safety
+    ///
+    /// `listener` must not be in any list, and must stay valid and in place
+    /// until it is removed with `remove`, or the list is dropped.
+    pub unsafe fn insert(&self, listener: NonNull<ListenerNode>) {
+        let node = listener.as_ptr();
+        let mut head = self.head.pointer();
+        loop {
+            // The listener is not shared until the exchange succeeds.
+            unsafe { (*node).next.store(head, false) };
+            let r = self
+                .head
+                .compare_exchange_weak((head, false), (node, false));
+            match r {
+                Result::Ok(_) => return,
+                Result::Err((x, _)) => head = x,
+            }
+        }
+    }
+
+    /// Unlinks `listener`, returning once no traversal of the list can read
+    /// it any longer.
+    ///
+    /// # Safety
+    ///
+    /// `listener` must have been inserted in this list, and not removed
+    /// since.
+    ///
+    /// It must not be called from a callback of this list, since the
+    /// notifying traversal protects the listener until the callback returns.
+    pub unsafe fn remove(&self, listener: NonNull<ListenerNode>) {
+        let node = listener.as_ptr();
+        let _ = unsafe { (*node).next.fetch_mark() };
+        // The traversal unlinks the marked listener, unless another one
+        // already has.
+        self.walk(|p, unlinked| !(unlinked && p == node));
+        for domain in self.domains.iter() {
+            domain.wait_unprotected(node);
+        }
+    }
+
+    /// Fires every listener linked during the whole call, at least once.
+    ///
+    /// A listener inserted or removed meanwhile may or may not be fired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::{ptr::NonNull, sync::atomic::{AtomicUsize, Ordering}};
+    /// use atomex::{ListenerNode, ListenerList};
+    ///
+    /// static FIRED: AtomicUsize = AtomicUsize::new(0);
+    /// fn on_event(_: &ListenerNode) {
+    ///     FIRED.fetch_add(1, Ordering::Relaxed);
+    /// }
+    ///
+    /// // A single traversal at a time.
+    /// let list = ListenerList::<1>::new();
+    /// let a = ListenerNode::with_callback(on_event);
+    /// unsafe { list.insert(NonNull::from(&a)) };
+    /// list.notify_all();
+    /// list.notify_all();
+    /// assert_eq!(FIRED.load(Ordering::Relaxed), 2);
+    /// unsafe { list.remove(NonNull::from(&a)) };
+    /// assert!(list.is_empty());
+    /// ```
+    pub fn notify_all(&self) {
+        self.walk(|p, unlinked| {
+            if !unlinked {
+                // Protected by the traversal.
+                unsafe { (*p).fire() };
+            }
+            true
+        });
+    }
+
+    /// Visits the listeners from the head, under hazard protection, until
+    /// `visit` returns `false`.
+    ///
+    /// The marked listeners met are unlinked, then visited as such; the
+    /// others are visited as linked.
+    fn walk(&self, mut visit: impl FnMut(*mut ListenerNode, bool) -> bool) {
+        let pair = HazardDomain::claim_pair(&self.domains);
+        'restart: loop {
+            // The slot protecting `curr`, the other one protecting `prev`.
+            let mut which = 0;
+            let mut prev: *const Link = &self.head;
+            loop {
+                // Protected by the other slot, unless it is the head.
+                let link = unsafe { &*prev };
+                let curr = pair.protect_with(which, || link.pointer());
+                // `curr` is still reachable, hence not reclaimed, only if the
+                // link has not been marked meanwhile.
+                if link.load() != (curr, false) {
+                    continue 'restart;
+                }
+                if curr.is_null() {
+                    return;
+                }
+                let (next, marked) = unsafe { (*curr).next.load() };
+                if marked {
+                    let unlinked = link
+                        .compare_exchange((curr, false), (next, false))
+                        .is_ok();
+                    if !unlinked {
+                        continue 'restart;
+                    }
+                    if !visit(curr, true) {
+                        return;
+                    }
+                    continue;
+                }
+                if !visit(curr, false) {
+                    return;
+                }
+                prev = unsafe { &(*curr).next };
+                which = 1 - which;
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for ListenerList<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ListenerList<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListenerList")
+            .field("is_empty", &self.is_empty())
+            .finish()
+    }
+}
@@ -0,0 +1,153 @@
+﻿//! Adapters of the raw locks to the `lock_api` traits, so that they can back
+//! `lock_api::Mutex` and `lock_api::RwLock`.
+//!
+//! The raw locks keep no owner thread, so a lock may be released by another
+//! thread than the one that acquired it, and the guards are `GuardSend`, as
+//! the guards of the crate are `Send`.
+//!
+//! ```
+//! use atomex::{x_deps::lock_api, RawSpinLock, RawSpinRwLock};
+//!
+//! type Mutex<T> = lock_api::Mutex<RawSpinLock, T>;
+//! type RwLock<T> = lock_api::RwLock<RawSpinRwLock, T>;
+//!
+//! static COUNT: Mutex<u32> = Mutex::new(0);
+//! *COUNT.lock() += 1;
+//! assert_eq!(*COUNT.lock(), 1);
+//!
+//! let mut guard = COUNT.lock();
+//! std::thread::scope(|s| s.spawn(move || *guard += 1).join().unwrap());
+//! assert_eq!(*COUNT.lock(), 2);
+//!
+//! let table = RwLock::new([0u8; 4]);
+//! table.write()[1] = 2;
+//! assert_eq!(table.read()[1], 2);
+//! ```
+
+use lock_api::{GuardSend, RawMutex, RawRwLock};
+use crate::{RawSpinLock, RawSpinRwLock, TrCmpxchOrderings};
+
+unsafe impl<O> RawMutex for RawSpinLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    const INIT: Self = RawSpinLock::new();
+
+    type GuardMarker = GuardSend;
+
+    #[inline(always)]
+    fn lock(&self) {
+        RawSpinLock::lock(self)
+    }
+
+    #[inline(always)]
+    fn try_lock(&self) -> bool {
+        RawSpinLock::try_lock(self)
+    }
+
+    #[inline(always)]
+    unsafe fn unlock(&self) {
+        unsafe { RawSpinLock::unlock(self) }
+    }
+
+    #[inline(always)]
+    fn is_locked(&self) -> bool {
+        RawSpinLock::is_locked(self)
+    }
+}
+
+unsafe impl<O> RawRwLock for RawSpinRwLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    const INIT: Self = RawSpinRwLock::new();
+
+    type GuardMarker = GuardSend;
+
+    #[inline(always)]
+    fn lock_shared(&self) {
+        self.read()
+    }
+
+    #[inline(always)]
+    fn try_lock_shared(&self) -> bool {
+        self.try_read()
+    }
+
+    #[inline(always)]
+    unsafe fn unlock_shared(&self) {
+        unsafe { self.read_unlock() }
+    }
+
+    #[inline(always)]
+    fn lock_exclusive(&self) {
+        self.write()
+    }
+
+    #[inline(always)]
+    fn try_lock_exclusive(&self) -> bool {
+        self.try_write()
+    }
+
+    #[inline(always)]
+    unsafe fn unlock_exclusive(&self) {
+        unsafe { self.write_unlock() }
+    }
+
+    #[inline(always)]
+    fn is_locked(&self) -> bool {
+        self.is_write_locked() || self.reader_count() > 0
+    }
+
+    #[inline(always)]
+    fn is_locked_exclusive(&self) -> bool {
+        self.is_write_locked()
+    }
+}
+
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
+mod ticket {
+    use lock_api::{GuardSend, RawMutex, RawMutexFair};
+    use crate::{RawTicketLock, TrCmpxchOrderings};
+
+    unsafe impl<O> RawMutex for RawTicketLock<O>
+    where
+        O: TrCmpxchOrderings,
+    {
+        const INIT: Self = RawTicketLock::new();
+
+        type GuardMarker = GuardSend;
+
+        #[inline(always)]
+        fn lock(&self) {
+            RawTicketLock::lock(self)
+        }
+
+        #[inline(always)]
+        fn try_lock(&self) -> bool {
+            RawTicketLock::try_lock(self)
+        }
+
+        #[inline(always)]
+        unsafe fn unlock(&self) {
+            unsafe { RawTicketLock::unlock(self) }
+        }
+
+        #[inline(always)]
+        fn is_locked(&self) -> bool {
+            RawTicketLock::is_locked(self)
+        }
+    }
+
+    /// A ticket lock is always handed over in FIFO order, so the fair unlock
+    /// is the plain one.
+    unsafe impl<O> RawMutexFair for RawTicketLock<O>
+    where
+        O: TrCmpxchOrderings,
+    {
+        #[inline(always)]
+        unsafe fn unlock_fair(&self) {
+            unsafe { RawTicketLock::unlock(self) }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+﻿use core::{
+    fmt,
+    mem::ManuallyDrop,
+    ptr,
+};
+use crate::atomics_::AtomicPtr;
+use alloc::boxed::Box;
+use crate::{hazard::HazardDomain, AtomexPtr};
+
+struct Node<T> {
+    value: ManuallyDrop<T>,
+    next: *mut Node<T>,
+}
+
+/// A lock-free LIFO stack (Treiber stack) of boxed nodes.
+///
+/// A popper protects the head it is about to unlink with a hazard pointer of
+/// the stack's own `HazardDomain<N>`, so that the node can neither be freed
+/// nor, consequently, be reallocated at the same address while it reads the
+/// successor, which rules out ABA. After unlinking it, the popper moves the
+/// value out and retires the node to the domain, which frees it once no
+/// other popper protects it, so that `pop` never blocks.
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use atomex::LockFreeStack;
+///
+/// let stack = Arc::new(LockFreeStack::<usize>::new());
+/// let workers: Vec<_> = (0..4)
+///     .map(|i| {
+///         let stack = stack.clone();
+///         thread::spawn(move || {
+///             for j in 0..100 {
+///                 stack.push(i * 100 + j);
+///             }
+///         })
+///     })
+///     .collect();
+/// workers.into_iter().for_each(|w| w.join().unwrap());
+///
+/// let mut popped = Vec::new();
+/// while let Some(v) = stack.pop() {
+///     popped.push(v);
+/// }
+/// popped.sort();
+/// assert_eq!(popped, (0..400).collect::<Vec<_>>());
+/// assert!(stack.is_empty());
+/// ```
+pub struct LockFreeStack<T, const N: usize = 32> {
+    head: AtomexPtr<Node<T>>,
+    domain: HazardDomain<N>,
+}
+
+unsafe impl<T: Send, const N: usize> Send for LockFreeStack<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for LockFreeStack<T, N> {}
+
+impl<T, const N: usize> LockFreeStack<T, N> {
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "[LockFreeStack] no hazard slot") };
+        LockFreeStack {
+            head: AtomexPtr::new(AtomicPtr::new(ptr::null_mut())),
+            domain: HazardDomain::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.head.pointer().is_null()
+    }
+
+    pub fn push(&self, value: T) {
+        let node = Box::into_raw(Box::new(Node {
+            value: ManuallyDrop::new(value),
+            next: ptr::null_mut(),
+        }));
+        // The node is not shared until the compare-exchange succeeds.
+        let _ = self.head.try_spin_compare_exchange_weak(
+            |_| true,
+            |head| {
+                unsafe { (*node).next = head };
+                node
+            },
+        );
+    }
+
+    /// Pops the value pushed last, or returns `None` if the stack is empty.
+    pub fn pop(&self) -> Option<T> {
+        loop {
+            let guard = self.head.load_guarded(&self.domain);
+            let head = guard.as_ptr();
+            if head.is_null() {
+                return Option::None;
+            }
+            // The guard keeps the node from being freed meanwhile.
+            let next = unsafe { (*head).next };
+            if self.head.compare_exchange_weak(head, next).is_err() {
+                continue;
+            }
+            drop(guard);
+            // Unlinked by this call only, so the value is moved out once,
+            // while the others may still read `next` until the node is freed.
+            let value = unsafe { ptr::read(&(*head).value) };
+            // Safety: the node is unlinked and retired only here, and freeing
+            // it leaves the value alone.
+            unsafe { self.domain.retire(head, free_node::<T>) };
+            return Option::Some(ManuallyDrop::into_inner(value));
+        }
+    }
+}
+
+/// Frees a retired node, whose value has been moved out by its popper.
+unsafe fn free_node<T>(p: *mut Node<T>) {
+    drop(unsafe { Box::from_raw(p) });
+}
+
+impl<T, const N: usize> Default for LockFreeStack<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for LockFreeStack<T, N> {
+    fn drop(&mut self) {
+        let mut p = *self.head.get_mut();
+        while !p.is_null() {
+            let mut node = unsafe { Box::from_raw(p) };
+            unsafe { ManuallyDrop::drop(&mut node.value) };
+            p = node.next;
+        }
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for LockFreeStack<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockFreeStack")
+            .field("is_empty", &self.is_empty())
+            .finish()
+    }
+}
@@ -0,0 +1,107 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+};
+use crate::{atomics_::AtomicU64, AcqRelOrderings, TrCmpxchOrderings};
+
+/// A last-writer-wins register, holding a 32-bit value together with the
+/// 32-bit timestamp of its write, packed in one `AtomicU64`.
+///
+/// A store only takes effect if its timestamp is later than the stored one,
+/// so that replicas applying the same writes in any order end up with the
+/// same value. Of two writes with the same timestamp, the first one applied
+/// is kept.
+///
+/// # Example
+///
+/// ```
+/// use atomex::LwwRegister;
+///
+/// let leader: LwwRegister = LwwRegister::new(0, 0);
+/// assert!(leader.store(5, 2));
+/// assert!(!leader.store(3, 1));
+/// assert!(!leader.store(5, 4));
+/// assert_eq!(leader.load(), (5, 2));
+/// assert!(leader.store(6, 4));
+/// assert_eq!(leader.value(), 4);
+/// ```
+pub struct LwwRegister<O = AcqRelOrderings>(AtomicU64, PhantomData<O>);
+
+impl<O: TrCmpxchOrderings> LwwRegister<O> {
+    pub const fn new(ts: u32, value: u32) -> Self {
+        LwwRegister(AtomicU64::new(Self::pack(ts, value)), PhantomData)
+    }
+
+    /// The timestamp and the value of the last write.
+    #[inline(always)]
+    pub fn load(&self) -> (u32, u32) {
+        Self::unpack(self.0.load(O::LOAD_ORDERING))
+    }
+
+    #[inline(always)]
+    pub fn timestamp(&self) -> u32 {
+        self.load().0
+    }
+
+    #[inline(always)]
+    pub fn value(&self) -> u32 {
+        self.load().1
+    }
+
+    /// Stores `value` written at `ts` if `ts` is later than the timestamp of
+    /// the stored value, and returns whether it was stored.
+    pub fn store(&self, ts: u32, value: u32) -> bool {
+        let desired = Self::pack(ts, value);
+        let mut current = self.0.load(O::LOAD_ORDERING);
+        loop {
+            if Self::unpack(current).0 >= ts {
+                return false;
+            }
+            match self.0.compare_exchange_weak(
+                current,
+                desired,
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            ) {
+                Result::Ok(_) => return true,
+                Result::Err(x) => current = x,
+            }
+        }
+    }
+
+    /// Stores `value` written at `ts` whatever the stored timestamp, and
+    /// returns the previous timestamp and value.
+    pub fn force(&self, ts: u32, value: u32) -> (u32, u32) {
+        Self::unpack(self.0.swap(Self::pack(ts, value), O::SWAP_ORDERING))
+    }
+
+    pub fn into_inner(self) -> (u32, u32) {
+        Self::unpack(self.0.into_inner())
+    }
+
+    #[inline(always)]
+    const fn pack(ts: u32, value: u32) -> u64 {
+        (ts as u64) << 32 | value as u64
+    }
+
+    #[inline(always)]
+    const fn unpack(x: u64) -> (u32, u32) {
+        ((x >> 32) as u32, x as u32)
+    }
+}
+
+impl<O: TrCmpxchOrderings> Default for LwwRegister<O> {
+    fn default() -> Self {
+        Self::new(0, 0)
+    }
+}
+
+impl<O: TrCmpxchOrderings> fmt::Debug for LwwRegister<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (ts, value) = self.load();
+        f.debug_struct("LwwRegister")
+            .field("ts", &ts)
+            .field("value", &value)
+            .finish()
+    }
+}
@@ -0,0 +1,281 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomPinned,
+    ptr,
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomics_::{AtomicBool, AtomicPtr},
+    relax,
+    CachePadded,
+};
+
+/// The queue node of an MCS lock, supplied by the locker.
+///
+/// Every waiter spins on the flag of its own node, which lives on its own
+/// cache line, instead of on the shared lock word.
+pub struct McsNode(CachePadded<McsNodeInner>);
+
+struct McsNodeInner {
+    next: AtomicPtr<McsNode>,
+    locked: AtomicBool,
+    _pinned: PhantomPinned,
+}
+
+impl McsNode {
+    pub const fn new() -> Self {
+        McsNode(CachePadded::new(McsNodeInner {
+            next: AtomicPtr::new(ptr::null_mut()),
+            locked: AtomicBool::new(false),
+            _pinned: PhantomPinned,
+        }))
+    }
+}
+
+impl Default for McsNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for McsNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("McsNode")
+            .field("waiting", &self.0.locked.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// An MCS queue lock without data, i.e. only the locking protocol of
+/// `McsLock`.
+pub struct RawMcsLock {
+    tail: AtomicPtr<McsNode>,
+}
+
+impl RawMcsLock {
+    pub const fn new() -> Self {
+        RawMcsLock {
+            tail: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        !self.tail.load(Ordering::Relaxed).is_null()
+    }
+
+    /// Acquires the lock with `node` if it is free, returning whether it was
+    /// acquired.
+    ///
+    /// # Safety
+    ///
+    /// If the lock is acquired, `node` must stay valid and in place until it
+    /// is passed to `unlock`.
+    pub unsafe fn try_lock(&self, node: *mut McsNode) -> bool {
+        let n = unsafe { &(*node).0 };
+        n.next.store(ptr::null_mut(), Ordering::Relaxed);
+        self.tail
+            .compare_exchange(
+                ptr::null_mut(),
+                node,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    /// Enqueues `node` and spins on it until the lock is handed over.
+    ///
+    /// # Safety
+    ///
+    /// `node` must not be in the queue of any lock, and must stay valid and
+    /// in place until it is passed to `unlock`.
+    pub unsafe fn lock(&self, node: *mut McsNode) {
+        let n = unsafe { &(*node).0 };
+        n.next.store(ptr::null_mut(), Ordering::Relaxed);
+        n.locked.store(true, Ordering::Relaxed);
+        let prev = self.tail.swap(node, Ordering::AcqRel);
+        if prev.is_null() {
+            return;
+        }
+        // The predecessor stays valid until it has linked us, since it waits
+        // for that in `unlock`.
+        let p = unsafe { &(*prev).0 };
+        p.next.store(node, Ordering::Release);
+        while n.locked.load(Ordering::Acquire) {
+            relax::relax();
+        }
+    }
+
+    /// Releases the lock, handing it over to the next node in the queue.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held by the caller with `node`.
+    pub unsafe fn unlock(&self, node: *mut McsNode) {
+        let n = unsafe { &(*node).0 };
+        let mut next = n.next.load(Ordering::Acquire);
+        if next.is_null() {
+            let r = self.tail.compare_exchange(
+                node,
+                ptr::null_mut(),
+                Ordering::Release,
+                Ordering::Relaxed,
+            );
+            if r.is_ok() {
+                return;
+            }
+            // A successor has swapped the tail but not linked itself yet.
+            loop {
+                next = n.next.load(Ordering::Acquire);
+                if !next.is_null() {
+                    break;
+                }
+                relax::relax();
+            }
+        }
+        let next = unsafe { &(*next).0 };
+        next.locked.store(false, Ordering::Release);
+    }
+}
+
+impl Default for RawMcsLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RawMcsLock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawMcsLock")
+            .field("locked", &self.is_locked())
+            .finish()
+    }
+}
+
+/// An MCS queue lock protecting a value of `T`.
+///
+/// Lockers are granted the lock in FIFO order, and each one spins on its own
+/// `McsNode` instead of the shared lock word, which keeps the cache traffic
+/// constant however many lockers wait. No allocation is required: the nodes
+/// are supplied by the lockers, usually on their stack.
+///
+/// The lock is held for the duration of a closure, so that a node can never
+/// be released while still linked in the queue.
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use atomex::{McsLock, McsNode};
+///
+/// let lock = Arc::new(McsLock::<usize>::new(0));
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let lock = lock.clone();
+///         thread::spawn(move || {
+///             let mut node = McsNode::new();
+///             for _ in 0..100 {
+///                 lock.with_node(&mut node, |x| *x += 1);
+///             }
+///         })
+///     })
+///     .collect();
+/// workers.into_iter().for_each(|w| w.join().unwrap());
+/// assert_eq!(lock.with(|x| *x), 400);
+/// ```
+pub struct McsLock<T: ?Sized> {
+    raw: RawMcsLock,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for McsLock<T> {}
+unsafe impl<T: ?Sized + Send> Sync for McsLock<T> {}
+
+impl<T> McsLock<T> {
+    pub const fn new(data: T) -> Self {
+        McsLock {
+            raw: RawMcsLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> McsLock<T> {
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    /// Runs `f` with the lock acquired through `node`.
+    pub fn with_node<R>(
+        &self,
+        node: &mut McsNode,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> R {
+        let node: *mut McsNode = node;
+        unsafe { self.raw.lock(node) };
+        let _unlock = Unlock(&self.raw, node);
+        f(unsafe { &mut *self.data.get() })
+    }
+
+    /// Runs `f` with the lock acquired through `node`, if the lock is free.
+    pub fn try_with_node<R>(
+        &self,
+        node: &mut McsNode,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let node: *mut McsNode = node;
+        if !unsafe { self.raw.try_lock(node) } {
+            return Option::None;
+        }
+        let _unlock = Unlock(&self.raw, node);
+        Option::Some(f(unsafe { &mut *self.data.get() }))
+    }
+
+    /// Runs `f` with the lock acquired through a node on the stack.
+    #[inline(always)]
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.with_node(&mut McsNode::new(), f)
+    }
+
+    /// Returns a mutable reference to the value, which needs no locking
+    /// since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for McsLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for McsLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("McsLock");
+        let mut node = McsNode::new();
+        let r = self.try_with_node(&mut node, |x| {
+            d.field("data", &x);
+        });
+        if r.is_none() {
+            d.field("data", &format_args!("<locked>"));
+        }
+        d.finish()
+    }
+}
+
+/// Releases the lock even if the closure panics.
+struct Unlock<'a>(&'a RawMcsLock, *mut McsNode);
+
+impl Drop for Unlock<'_> {
+    fn drop(&mut self) {
+        unsafe { self.0.unlock(self.1) }
+    }
+}
@@ -0,0 +1,59 @@
+﻿use core::{
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::AtomicU64;
+
+/// A timestamp that never goes backwards, such as a coarse clock published
+/// by one thread and read by many.
+///
+/// Advancing keeps the greatest of the stored and the given timestamps, so
+/// that racing or late publishers cannot move it back. Both advancing and
+/// loading are relaxed: the stamp orders nothing but itself.
+///
+/// # Example
+///
+/// ```
+/// use atomex::MonotonicStamp;
+///
+/// static NOW_MS: MonotonicStamp = MonotonicStamp::new(0);
+///
+/// assert_eq!(NOW_MS.advance_to(1_000), 0);
+/// assert_eq!(NOW_MS.advance_to(900), 1_000);
+/// assert_eq!(NOW_MS.load(), 1_000);
+/// ```
+pub struct MonotonicStamp(AtomicU64);
+
+impl MonotonicStamp {
+    pub const fn new(ts: u64) -> Self {
+        MonotonicStamp(AtomicU64::new(ts))
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Moves the stamp to `ts` unless it is already later, and returns the
+    /// previous stamp.
+    #[inline(always)]
+    pub fn advance_to(&self, ts: u64) -> u64 {
+        self.0.fetch_max(ts, Ordering::Relaxed)
+    }
+
+    pub fn into_inner(self) -> u64 {
+        self.0.into_inner()
+    }
+}
+
+impl Default for MonotonicStamp {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl fmt::Debug for MonotonicStamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MonotonicStamp").field(&self.load()).finish()
+    }
+}
@@ -0,0 +1,173 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    sync::atomic::Ordering,
+};
+use crate::{atomics_::AtomicUsize, CachePadded};
+
+struct Slot<T> {
+    /// Equal to the position of the next push into the slot when it is
+    /// free, and to that position plus one once the value is written.
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A bounded multi-producer multi-consumer queue (Vyukov's) of capacity `N`,
+/// which must be a power of two.
+///
+/// Every slot carries a sequence number telling whether it is ready to be
+/// written or read at a given position, so that producers and consumers only
+/// contend on their own position counter and on the slots themselves.
+///
+/// # Example
+///
+/// ```
+/// use atomex::MpmcQueue;
+///
+/// let queue = MpmcQueue::<u32, 2>::new();
+/// assert_eq!(queue.try_push(1), Ok(()));
+/// assert_eq!(queue.try_push(2), Ok(()));
+/// assert_eq!(queue.try_push(3), Err(3));
+/// assert_eq!(queue.len(), 2);
+/// assert_eq!(queue.try_pop(), Some(1));
+/// assert_eq!(queue.try_pop(), Some(2));
+/// assert_eq!(queue.try_pop(), None);
+/// ```
+pub struct MpmcQueue<T, const N: usize> {
+    enqueue_pos: CachePadded<AtomicUsize>,
+    dequeue_pos: CachePadded<AtomicUsize>,
+    slots: [Slot<T>; N],
+}
+
+unsafe impl<T: Send, const N: usize> Send for MpmcQueue<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for MpmcQueue<T, N> {}
+
+impl<T, const N: usize> MpmcQueue<T, N> {
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                N.is_power_of_two(),
+                "MpmcQueue requires a power of two capacity",
+            )
+        };
+        let mut slots = [const {
+            Slot {
+                seq: AtomicUsize::new(0),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }; N];
+        let mut i = 0;
+        while i < N {
+            slots[i].seq = AtomicUsize::new(i);
+            i += 1;
+        }
+        MpmcQueue {
+            enqueue_pos: CachePadded::new(AtomicUsize::new(0)),
+            dequeue_pos: CachePadded::new(AtomicUsize::new(0)),
+            slots,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The number of values in the queue, which may be outdated as soon as
+    /// it is returned.
+    pub fn len(&self) -> usize {
+        let enq = self.enqueue_pos.load(Ordering::Relaxed);
+        let deq = self.dequeue_pos.load(Ordering::Relaxed);
+        enq.wrapping_sub(deq).min(N)
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pushes `value` at the back, or gives it back if the queue is full.
+    pub fn try_push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos % N];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos) as isize;
+            if diff == 0 {
+                match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Result::Ok(_) => {
+                        // The slot is owned until its sequence is published.
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return Result::Ok(());
+                    }
+                    Result::Err(x) => pos = x,
+                }
+            } else if diff < 0 {
+                // The slot still holds the value pushed a lap earlier.
+                return Result::Err(value);
+            } else {
+                pos = self.enqueue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Pops the value at the front, or returns `None` if the queue is empty.
+    pub fn try_pop(&self) -> Option<T> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos % N];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos.wrapping_add(1)) as isize;
+            if diff == 0 {
+                match self.dequeue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Result::Ok(_) => {
+                        let value =
+                            unsafe { (*slot.value.get()).assume_init_read() };
+                        // Frees the slot for the push a lap later.
+                        slot.seq.store(pos.wrapping_add(N), Ordering::Release);
+                        return Option::Some(value);
+                    }
+                    Result::Err(x) => pos = x,
+                }
+            } else if diff < 0 {
+                // The slot has not been written in this lap yet.
+                return Option::None;
+            } else {
+                pos = self.dequeue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> Default for MpmcQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for MpmcQueue<T, N> {
+    fn drop(&mut self) {
+        while self.try_pop().is_some() {}
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for MpmcQueue<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MpmcQueue")
+            .field("capacity", &N)
+            .field("len", &self.len())
+            .finish()
+    }
+}
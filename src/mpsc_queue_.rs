@@ -0,0 +1,209 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    ptr::{self, NonNull},
+    sync::atomic::Ordering,
+};
+use crate::{atomics_::AtomicPtr, AcqRelOrderings, AtomexPtr};
+
+/// The link embedded in the items of an `MpscQueue`.
+///
+/// To get back to an item from its node, put the node first in a
+/// `#[repr(C)]` item and cast the pointer.
+pub struct QueueNode {
+    next: AtomicPtr<QueueNode>,
+}
+
+impl QueueNode {
+    pub const fn new() -> Self {
+        QueueNode {
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+impl Default for QueueNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for QueueNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueNode").finish_non_exhaustive()
+    }
+}
+
+/// The outcome of `MpscQueue::pop`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MpscPop {
+    Data(NonNull<QueueNode>),
+    Empty,
+
+    /// A producer has enqueued a node but not linked it yet, so the queue
+    /// is not empty but nothing can be popped for now.
+    Inconsistent,
+}
+
+impl MpscPop {
+    pub fn data(self) -> Option<NonNull<QueueNode>> {
+        match self {
+            MpscPop::Data(p) => Option::Some(p),
+            _ => Option::None,
+        }
+    }
+}
+
+/// An intrusive multi-producer single-consumer queue (Vyukov's), requiring no
+/// allocation.
+///
+/// A push is a single swap of the head, wait-free for the producers; the
+/// consumer owns the tail. Between a producer's swap and its linking of the
+/// previous node, the consumer can observe the queue as `Inconsistent`.
+///
+/// # Example
+///
+/// ```
+/// use core::ptr::NonNull;
+/// use atomex::{MpscQueue, QueueNode};
+///
+/// #[repr(C)]
+/// struct Task {
+///     node: QueueNode,
+///     id: u32,
+/// }
+///
+/// let queue = MpscQueue::new();
+/// let tasks = [1, 2].map(|id| Task { node: QueueNode::new(), id });
+/// for t in tasks.iter() {
+///     unsafe { queue.push(NonNull::from(&t.node)) };
+/// }
+/// let ids: Vec<u32> = core::iter::from_fn(|| unsafe { queue.pop_spin() })
+///     .map(|p| unsafe { p.cast::<Task>().as_ref().id })
+///     .collect();
+/// assert_eq!(ids, [1, 2]);
+/// ```
+pub struct MpscQueue {
+    /// The node pushed last, or null for the stub.
+    head: AtomexPtr<QueueNode, AtomicPtr<QueueNode>, AcqRelOrderings>,
+
+    /// The next node to pop, or null for the stub; only used by the consumer.
+    tail: UnsafeCell<*mut QueueNode>,
+    stub: QueueNode,
+}
+
+unsafe impl Send for MpscQueue {}
+unsafe impl Sync for MpscQueue {}
+
+impl MpscQueue {
+    pub const fn new() -> Self {
+        MpscQueue {
+            head: AtomexPtr::new(AtomicPtr::new(ptr::null_mut())),
+            tail: UnsafeCell::new(ptr::null_mut()),
+            stub: QueueNode::new(),
+        }
+    }
+
+    /// Enqueues `node`.
+    ///
+    /// # Safety
+    ///
+    /// `node` must not be in any queue, and must stay valid and in place
+    /// until it is popped. The queue must not be moved once a node has been
+    /// pushed.
+    pub unsafe fn push(&self, node: NonNull<QueueNode>) {
+        let node = node.as_ptr();
+        unsafe { (*node).next.store(ptr::null_mut(), Ordering::Relaxed) };
+        let prev = self.resolve(self.head.swap(node));
+        // Until this store, the consumer sees the queue as inconsistent.
+        unsafe { (*prev).next.store(node, Ordering::Release) };
+    }
+
+    /// Dequeues the node pushed first.
+    ///
+    /// # Safety
+    ///
+    /// Only one thread may pop from the queue at a time.
+    pub unsafe fn pop(&self) -> MpscPop {
+        let stub = self.resolve(ptr::null_mut());
+        let tail_slot = self.tail.get();
+        let mut tail = self.resolve(unsafe { *tail_slot });
+        let mut next = unsafe { (*tail).next.load(Ordering::Acquire) };
+        if tail == stub {
+            if next.is_null() {
+                return MpscPop::Empty;
+            }
+            unsafe { *tail_slot = next };
+            tail = next;
+            next = unsafe { (*next).next.load(Ordering::Acquire) };
+        }
+        if !next.is_null() {
+            unsafe { *tail_slot = next };
+            return Self::data(tail);
+        }
+        if tail != self.resolve(self.head.pointer()) {
+            return MpscPop::Inconsistent;
+        }
+        // `tail` is the last node: push the stub behind it, so that it can
+        // be popped without leaving the queue without a node.
+        unsafe { self.push(NonNull::from(&self.stub)) };
+        next = unsafe { (*tail).next.load(Ordering::Acquire) };
+        if next.is_null() {
+            return MpscPop::Inconsistent;
+        }
+        unsafe { *tail_slot = next };
+        Self::data(tail)
+    }
+
+    /// Dequeues the node pushed first, spinning while the queue is
+    /// inconsistent.
+    ///
+    /// # Safety
+    ///
+    /// Only one thread may pop from the queue at a time.
+    pub unsafe fn pop_spin(&self) -> Option<NonNull<QueueNode>> {
+        loop {
+            match unsafe { self.pop() } {
+                MpscPop::Data(p) => break Option::Some(p),
+                MpscPop::Empty => break Option::None,
+                MpscPop::Inconsistent => crate::relax::relax(),
+            }
+        }
+    }
+
+    /// Returns `true` if no node has been pushed since the last pop, as seen
+    /// by the producers.
+    pub fn is_empty(&self) -> bool {
+        let head = self.resolve(self.head.pointer());
+        head == self.resolve(ptr::null_mut())
+            && unsafe { (*head).next.load(Ordering::Acquire) }.is_null()
+    }
+
+    /// Maps the null pointer to the stub.
+    fn resolve(&self, p: *mut QueueNode) -> *mut QueueNode {
+        if p.is_null() {
+            &self.stub as *const QueueNode as *mut QueueNode
+        } else {
+            p
+        }
+    }
+
+    fn data(p: *mut QueueNode) -> MpscPop {
+        // Only non-null nodes are ever linked.
+        MpscPop::Data(unsafe { NonNull::new_unchecked(p) })
+    }
+}
+
+impl Default for MpscQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MpscQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MpscQueue")
+            .field("is_empty", &self.is_empty())
+            .finish()
+    }
+}
@@ -0,0 +1,186 @@
+﻿use core::{convert::Infallible, fmt};
+use crate::{
+    atomics_::AtomicUsize, relax, AcqRelOrderings, StateCell, TrAtomicState,
+};
+
+/// The states of a `Once`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OnceState {
+    Incomplete,
+    Running,
+    Complete,
+
+    /// The initialization closure panicked.
+    Poisoned,
+}
+
+impl TrAtomicState for OnceState {
+    type Repr = usize;
+
+    fn into_repr(self) -> usize {
+        self as usize
+    }
+
+    fn try_from_repr(repr: usize) -> Option<Self> {
+        [
+            OnceState::Incomplete,
+            OnceState::Running,
+            OnceState::Complete,
+            OnceState::Poisoned,
+        ]
+        .get(repr)
+        .copied()
+    }
+
+    fn can_transit(from: Self, to: Self) -> bool {
+        matches!(
+            (from, to),
+            (OnceState::Incomplete, OnceState::Running)
+                | (OnceState::Running, OnceState::Incomplete)
+                | (OnceState::Running, OnceState::Complete)
+                | (OnceState::Running, OnceState::Poisoned),
+        )
+    }
+}
+
+/// A one-time initialization flag, whose waiters spin.
+///
+/// If the initialization closure panics, the `Once` is poisoned and every
+/// later `call_once` and `wait` panics as well.
+///
+/// # Example
+///
+/// ```
+/// use atomex::Once;
+///
+/// static INIT: Once = Once::new();
+///
+/// let mut calls = 0;
+/// INIT.call_once(|| calls += 1);
+/// INIT.call_once(|| calls += 1);
+/// assert_eq!(calls, 1);
+/// assert!(INIT.is_completed());
+///
+/// let failed = Once::new();
+/// let r = std::panic::catch_unwind(|| failed.call_once(|| panic!()));
+/// assert!(r.is_err());
+/// assert!(failed.is_poisoned());
+/// ```
+pub struct Once(StateCell<OnceState, AtomicUsize, AcqRelOrderings>);
+
+impl Once {
+    pub const fn new() -> Self {
+        Once(StateCell::new(AtomicUsize::new(0)))
+    }
+
+    #[inline(always)]
+    pub fn state(&self) -> OnceState {
+        self.0.load()
+    }
+
+    #[inline(always)]
+    pub fn is_completed(&self) -> bool {
+        self.state() == OnceState::Complete
+    }
+
+    #[inline(always)]
+    pub fn is_poisoned(&self) -> bool {
+        self.state() == OnceState::Poisoned
+    }
+
+    /// Runs `f` if no initialization has run yet, else waits for the running
+    /// one to complete.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Once` is poisoned, including by `f` panicking.
+    pub fn call_once(&self, f: impl FnOnce()) {
+        let r = self.try_call_once(|| {
+            f();
+            Result::<(), Infallible>::Ok(())
+        });
+        if let Result::Err(e) = r {
+            match e {}
+        }
+    }
+
+    /// Runs `f` if no initialization has run yet, else waits for the running
+    /// one to complete.
+    ///
+    /// If `f` fails, the `Once` is left incomplete so that another call can
+    /// retry, and the error is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Once` is poisoned, including by `f` panicking.
+    pub fn try_call_once<E>(
+        &self,
+        f: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        loop {
+            match self.state() {
+                OnceState::Complete => return Result::Ok(()),
+                OnceState::Poisoned => panic!("[Once::call_once] poisoned"),
+                OnceState::Running => relax::relax(),
+                OnceState::Incomplete => {
+                    let r = self.0.transition(
+                        OnceState::Incomplete,
+                        OnceState::Running,
+                    );
+                    if r.is_succ() {
+                        break;
+                    }
+                }
+            }
+        }
+        let poison = PoisonOnUnwind(self);
+        let r = f();
+        core::mem::forget(poison);
+        match r {
+            Result::Ok(_) => self.finish(OnceState::Complete),
+            Result::Err(_) => self.finish(OnceState::Incomplete),
+        }
+        r
+    }
+
+    /// Spins until the initialization is complete.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Once` is poisoned.
+    pub fn wait(&self) {
+        loop {
+            match self.state() {
+                OnceState::Complete => break,
+                OnceState::Poisoned => panic!("[Once::wait] poisoned"),
+                _ => relax::relax(),
+            }
+        }
+    }
+
+    fn finish(&self, state: OnceState) {
+        let r = self.0.transition(OnceState::Running, state);
+        debug_assert!(r.is_succ());
+    }
+}
+
+impl Default for Once {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Once {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Once").field(&self.state()).finish()
+    }
+}
+
+/// Poisons the `Once` if the initialization closure unwinds.
+struct PoisonOnUnwind<'a>(&'a Once);
+
+impl Drop for PoisonOnUnwind<'_> {
+    fn drop(&mut self) {
+        self.0.finish(OnceState::Poisoned)
+    }
+}
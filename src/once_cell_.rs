@@ -0,0 +1,241 @@
+﻿use core::{
+    cell::{Cell, UnsafeCell},
+    convert::Infallible,
+    fmt,
+    mem::MaybeUninit,
+    ops::Deref,
+};
+use crate::Once;
+
+/// A cell that can be written only once, whose readers spin while it is
+/// being initialized.
+///
+/// The initialization is guarded by a `Once`, so a panicking initializer
+/// poisons the cell and makes every later initialization attempt panic.
+///
+/// # Example
+///
+/// ```
+/// use atomex::OnceCell;
+///
+/// static NAME: OnceCell<&str> = OnceCell::new();
+///
+/// assert_eq!(NAME.get(), None);
+/// assert_eq!(NAME.get_or_init(|| "atomex"), &"atomex");
+/// assert_eq!(NAME.set("other"), Err("other"));
+///
+/// let cell = OnceCell::<u32>::new();
+/// assert_eq!(cell.get_or_try_init(|| "x".parse::<u32>()).ok(), None);
+/// assert_eq!(cell.get_or_try_init(|| "7".parse::<u32>()), Ok(&7));
+/// ```
+pub struct OnceCell<T> {
+    once: Once,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceCell<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+
+impl<T> OnceCell<T> {
+    pub const fn new() -> Self {
+        OnceCell {
+            once: Once::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value, or `None` if the cell is not initialized yet.
+    pub fn get(&self) -> Option<&T> {
+        if self.once.is_completed() {
+            Option::Some(unsafe { self.get_unchecked() })
+        } else {
+            Option::None
+        }
+    }
+
+    /// Returns a mutable reference to the value, or `None` if the cell is not
+    /// initialized yet.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.once.is_completed() {
+            Option::Some(unsafe { self.value.get_mut().assume_init_mut() })
+        } else {
+            Option::None
+        }
+    }
+
+    /// Spins until the cell is initialized by another caller, and returns the
+    /// value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is poisoned.
+    pub fn wait(&self) -> &T {
+        self.once.wait();
+        unsafe { self.get_unchecked() }
+    }
+
+    /// Initializes the cell with `value`, or gives it back if the cell is
+    /// already initialized.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Option::Some(value);
+        let _ = self.get_or_init(|| {
+            let Option::Some(v) = value.take() else { unreachable!() };
+            v
+        });
+        match value {
+            Option::None => Result::Ok(()),
+            Option::Some(v) => Result::Err(v),
+        }
+    }
+
+    /// Returns the value, initializing the cell with `f` if it is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is poisoned, including by `f` panicking.
+    pub fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+        let r = self.get_or_try_init(|| Result::<T, Infallible>::Ok(f()));
+        match r {
+            Result::Ok(v) => v,
+            Result::Err(e) => match e {},
+        }
+    }
+
+    /// Returns the value, initializing the cell with `f` if it is empty.
+    ///
+    /// If `f` fails, the cell is left empty and the error is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is poisoned, including by `f` panicking.
+    pub fn get_or_try_init<E>(
+        &self,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<&T, E> {
+        if let Option::Some(v) = self.get() {
+            return Result::Ok(v);
+        }
+        self.once.try_call_once(|| {
+            let v = f()?;
+            // The `Once` grants exclusive access while running.
+            unsafe { (*self.value.get()).write(v) };
+            Result::Ok(())
+        })?;
+        Result::Ok(unsafe { self.get_unchecked() })
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        let mut this = core::mem::ManuallyDrop::new(self);
+        if this.once.is_completed() {
+            Option::Some(unsafe { this.value.get_mut().assume_init_read() })
+        } else {
+            Option::None
+        }
+    }
+
+    unsafe fn get_unchecked(&self) -> &T {
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<T> for OnceCell<T> {
+    fn from(value: T) -> Self {
+        let cell = Self::new();
+        let _ = cell.set(value);
+        cell
+    }
+}
+
+impl<T> Drop for OnceCell<T> {
+    fn drop(&mut self) {
+        if self.once.is_completed() {
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("OnceCell");
+        match self.get() {
+            Option::Some(v) => d.field(v),
+            Option::None => d.field(&format_args!("<uninit>")),
+        };
+        d.finish()
+    }
+}
+
+/// A value initialized on its first access, with the function given at
+/// construction.
+///
+/// # Example
+///
+/// ```
+/// use atomex::Lazy;
+///
+/// static TABLE: Lazy<[u32; 4]> = Lazy::new(|| [1, 2, 4, 8]);
+///
+/// assert_eq!(TABLE[3], 8);
+/// assert_eq!(Lazy::force(&TABLE).iter().sum::<u32>(), 15);
+/// ```
+pub struct Lazy<T, F = fn() -> T> {
+    cell: OnceCell<T>,
+    init: Cell<Option<F>>,
+}
+
+// The initializer is only taken by the caller that runs the `Once`.
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+impl<T, F> Lazy<T, F> {
+    pub const fn new(init: F) -> Self {
+        Lazy {
+            cell: OnceCell::new(),
+            init: Cell::new(Option::Some(init)),
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    /// Returns the value, initializing it if this is the first access.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initialization function panicked on an earlier access.
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(|| {
+            let Option::Some(f) = this.init.take() else { unreachable!() };
+            f()
+        })
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        Lazy::force(self)
+    }
+}
+
+impl<T: Default> Default for Lazy<T> {
+    fn default() -> Self {
+        Self::new(T::default)
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("Lazy");
+        match self.cell.get() {
+            Option::Some(v) => d.field(v),
+            Option::None => d.field(&format_args!("<uninit>")),
+        };
+        d.finish()
+    }
+}
@@ -0,0 +1,243 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+    ptr,
+};
+use crate::{
+    atomics_::AtomicU64, PhantomAtomicPtr, StrictOrderings, TrCmpxchOrderings,
+};
+
+/// The number of significant bits of a virtual address.
+const ADDR_BITS: u32 = 48;
+
+const ADDR_MASK: u64 = (1 << ADDR_BITS) - 1;
+
+/// A pointer and a 16-bit tag packed into a single `u64`, for 64-bit targets
+/// whose virtual addresses fit in 48 bits, sign-extended.
+///
+/// The tag takes the 16 upper bits of the address, which are restored by
+/// sign-extending bit 47 when unpacking, so that it needs no alignment bits
+/// and leaves room for an ABA counter of 65536 generations.
+///
+/// # Example
+///
+/// ```
+/// use atomex::PackedPtrTag;
+///
+/// let mut x = 1u8;
+/// let px: *mut u8 = &mut x;
+/// let packed = PackedPtrTag::new(px, 0xffff);
+/// assert_eq!(packed.pointer(), px);
+/// assert_eq!(packed.tag(), 0xffff);
+/// assert_eq!(packed.next_tag(px).tag(), 0);
+/// assert_eq!(PackedPtrTag::from_bits(packed.into_bits()), packed);
+/// ```
+pub struct PackedPtrTag<T>(u64, PhantomData<*mut T>);
+
+impl<T> PackedPtrTag<T> {
+    /// Packs `p` with `tag`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address of `p` does not fit in 48 bits sign-extended.
+    pub fn new(p: *mut T, tag: u16) -> Self {
+        let Option::Some(packed) = Self::try_new(p, tag) else {
+            panic!("[PackedPtrTag::new] {p:p} exceeds {ADDR_BITS} bits")
+        };
+        packed
+    }
+
+    /// Packs `p` with `tag`, or returns `None` if the address of `p` does not
+    /// fit in 48 bits sign-extended.
+    pub fn try_new(p: *mut T, tag: u16) -> Option<Self> {
+        let addr = p.expose_provenance() as u64;
+        if Self::extend(addr & ADDR_MASK) != addr {
+            return Option::None;
+        }
+        let bits = (addr & ADDR_MASK) | (tag as u64) << ADDR_BITS;
+        Option::Some(PackedPtrTag(bits, PhantomData))
+    }
+
+    #[inline(always)]
+    pub const fn from_bits(bits: u64) -> Self {
+        PackedPtrTag(bits, PhantomData)
+    }
+
+    #[inline(always)]
+    pub const fn into_bits(self) -> u64 {
+        self.0
+    }
+
+    #[inline(always)]
+    pub fn pointer(self) -> *mut T {
+        let addr = Self::extend(self.0 & ADDR_MASK);
+        ptr::with_exposed_provenance_mut(addr as usize)
+    }
+
+    #[inline(always)]
+    pub const fn tag(self) -> u16 {
+        (self.0 >> ADDR_BITS) as u16
+    }
+
+    /// The same pointer with another tag.
+    #[inline(always)]
+    pub const fn with_tag(self, tag: u16) -> Self {
+        let bits = (self.0 & ADDR_MASK) | (tag as u64) << ADDR_BITS;
+        PackedPtrTag(bits, PhantomData)
+    }
+
+    /// Packs `p` with the tag following this one, wrapping around.
+    pub fn next_tag(self, p: *mut T) -> Self {
+        Self::new(p, self.tag().wrapping_add(1))
+    }
+
+    /// Sign-extends bit 47 of `addr` to the upper bits.
+    #[inline(always)]
+    const fn extend(addr: u64) -> u64 {
+        let shift = u64::BITS - ADDR_BITS;
+        ((addr << shift) as i64 >> shift) as u64
+    }
+}
+
+impl<T> Clone for PackedPtrTag<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PackedPtrTag<T> {}
+
+impl<T> PartialEq for PackedPtrTag<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for PackedPtrTag<T> {}
+
+impl<T> Default for PackedPtrTag<T> {
+    fn default() -> Self {
+        PackedPtrTag(0, PhantomData)
+    }
+}
+
+impl<T> fmt::Debug for PackedPtrTag<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackedPtrTag")
+            .field("pointer", &self.pointer())
+            .field("tag", &self.tag())
+            .finish()
+    }
+}
+
+/// An atomic `PackedPtrTag`, exchanging a pointer and its tag together in a
+/// single-word compare-exchange.
+///
+/// # Example
+///
+/// ```
+/// use atomex::{AtomexPackedPtr, PackedPtrTag};
+///
+/// let (mut a, mut b) = (1u32, 2u32);
+/// let (pa, pb): (*mut u32, *mut u32) = (&mut a, &mut b);
+/// let head = AtomexPackedPtr::<u32>::new(PackedPtrTag::new(pa, 0));
+///
+/// let seen = head.load();
+/// let moved = seen.next_tag(pb);
+/// assert!(head.compare_exchange(seen, moved).is_ok());
+/// assert!(head.compare_exchange(moved, moved.next_tag(pa)).is_ok());
+///
+/// // Back to `pa`, but a stale tag.
+/// assert!(head.compare_exchange(seen, seen.next_tag(pb)).is_err());
+/// assert_eq!(head.load().tag(), 2);
+/// ```
+pub struct AtomexPackedPtr<T, O = StrictOrderings>(
+    AtomicU64,
+    PhantomAtomicPtr<T>,
+    PhantomData<O>,
+);
+
+impl<T, O: TrCmpxchOrderings> AtomexPackedPtr<T, O> {
+    pub const fn new(packed: PackedPtrTag<T>) -> Self {
+        AtomexPackedPtr(AtomicU64::new(packed.0), PhantomData, PhantomData)
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> PackedPtrTag<T> {
+        PackedPtrTag::from_bits(self.0.load(O::LOAD_ORDERING))
+    }
+
+    #[inline(always)]
+    pub fn pointer(&self) -> *mut T {
+        self.load().pointer()
+    }
+
+    #[inline(always)]
+    pub fn tag(&self) -> u16 {
+        self.load().tag()
+    }
+
+    pub fn store(&self, packed: PackedPtrTag<T>) {
+        self.0.store(packed.0, O::STORE_ORDERING)
+    }
+
+    pub fn swap(&self, packed: PackedPtrTag<T>) -> PackedPtrTag<T> {
+        PackedPtrTag::from_bits(self.0.swap(packed.0, O::SWAP_ORDERING))
+    }
+
+    /// Replaces the pointer and its tag with `new` if they are `current`.
+    ///
+    /// Returns `Ok(current)` if they were replaced, else `Err(actual)`.
+    pub fn compare_exchange(
+        &self,
+        current: PackedPtrTag<T>,
+        new: PackedPtrTag<T>,
+    ) -> Result<PackedPtrTag<T>, PackedPtrTag<T>> {
+        self.0
+            .compare_exchange(
+                current.0,
+                new.0,
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(PackedPtrTag::from_bits)
+            .map_err(PackedPtrTag::from_bits)
+    }
+
+    /// Like `compare_exchange` but allowed to fail spuriously.
+    pub fn compare_exchange_weak(
+        &self,
+        current: PackedPtrTag<T>,
+        new: PackedPtrTag<T>,
+    ) -> Result<PackedPtrTag<T>, PackedPtrTag<T>> {
+        self.0
+            .compare_exchange_weak(
+                current.0,
+                new.0,
+                O::SUCC_ORDERING,
+                O::FAIL_ORDERING,
+            )
+            .map(PackedPtrTag::from_bits)
+            .map_err(PackedPtrTag::from_bits)
+    }
+
+    pub fn into_inner(self) -> PackedPtrTag<T> {
+        PackedPtrTag::from_bits(self.0.into_inner())
+    }
+}
+
+impl<T, O: TrCmpxchOrderings> Default for AtomexPackedPtr<T, O> {
+    fn default() -> Self {
+        Self::new(PackedPtrTag::default())
+    }
+}
+
+impl<T, O: TrCmpxchOrderings> fmt::Debug for AtomexPackedPtr<T, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let packed = self.load();
+        f.debug_struct("AtomexPackedPtr")
+            .field("pointer", &packed.pointer())
+            .field("tag", &packed.tag())
+            .finish()
+    }
+}
@@ -0,0 +1,104 @@
+﻿use core::{
+    ptr,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::{AtomicBool, AtomicU32};
+use std::{
+    sync::{Mutex, PoisonError},
+    thread::{self, Thread},
+};
+use crate::{wait_::TrWait, CachePadded};
+
+const BUCKET_BITS: u32 = 6;
+
+/// A thread parked on a word, linked in the list of its bucket while it
+/// waits, from its own stack.
+struct ParkNode {
+    addr: usize,
+    thread: Thread,
+    woken: AtomicBool,
+    next: *mut ParkNode,
+}
+
+/// The threads parked on the words whose addresses hash to the same bucket.
+struct ParkList(*mut ParkNode);
+
+// The nodes are only accessed under the lock of their bucket, except for
+// `woken` which is atomic.
+unsafe impl Send for ParkList {}
+
+static TABLE: [CachePadded<Mutex<ParkList>>; 1 << BUCKET_BITS] = [const {
+    CachePadded::new(Mutex::new(ParkList(ptr::null_mut())))
+}; 1 << BUCKET_BITS];
+
+fn bucket_of(addr: usize) -> &'static Mutex<ParkList> {
+    // Fibonacci hashing, keeping the upper bits which are the best mixed.
+    let h = addr.wrapping_mul(0x9E37_79B9_7F4A_7C15_u64 as usize);
+    &TABLE[h >> (usize::BITS - BUCKET_BITS)]
+}
+
+/// Sleeps with `thread::park`, the threads being registered by the address
+/// of their word, for the targets without a futex.
+pub(crate) struct Parker;
+
+impl TrWait for Parker {
+    fn wait(word: &AtomicU32, expected: u32) {
+        park(word, expected)
+    }
+
+    fn wake_all(word: &AtomicU32) {
+        unpark_all(word)
+    }
+}
+
+/// Parks the thread as long as `word` holds `expected`, until woken by
+/// `unpark_all`, emulating a futex wait.
+fn park(word: &AtomicU32, expected: u32) {
+    let addr = word.as_ptr() as usize;
+    let bucket = bucket_of(addr);
+    let mut node = ParkNode {
+        addr,
+        thread: thread::current(),
+        woken: AtomicBool::new(false),
+        next: ptr::null_mut(),
+    };
+    let node: *mut ParkNode = &mut node;
+    {
+        // Checked under the lock, which `unpark_all` takes after changing
+        // the word, so that no wake-up is missed.
+        let mut list = bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        if word.load(Ordering::Relaxed) != expected {
+            return;
+        }
+        unsafe { (*node).next = list.0 };
+        list.0 = node;
+    }
+    // The node is unlinked by `unpark_all` before `woken` is set, so it can
+    // be dropped once `woken` is seen.
+    while !unsafe { (*node).woken.load(Ordering::Acquire) } {
+        thread::park();
+    }
+}
+
+/// Unparks all the threads parked on `word`.
+fn unpark_all(word: &AtomicU32) {
+    let addr = word.as_ptr() as usize;
+    let mut list = bucket_of(addr)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut link: *mut *mut ParkNode = &mut list.0;
+    unsafe {
+        while !(*link).is_null() {
+            let node = *link;
+            if (*node).addr != addr {
+                link = &mut (*node).next;
+                continue;
+            }
+            *link = (*node).next;
+            // The thread handle is cloned out before the node may be dropped.
+            let thread = (*node).thread.clone();
+            (*node).woken.store(true, Ordering::Release);
+            thread.unpark();
+        }
+    }
+}
@@ -0,0 +1,66 @@
+﻿//! States converted to and from their integer representation by the
+//! standard conversions, such as the ones `num_enum` derives, so that an
+//! existing enum is stored in a `StateCell` without writing them again.
+//!
+//! ```
+//! use num_enum::{IntoPrimitive, TryFromPrimitive};
+//! use atomex::{StateCell, TrPrimitiveState};
+//!
+//! #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+//! #[derive(IntoPrimitive, TryFromPrimitive)]
+//! #[repr(u8)]
+//! enum Phase { Idle, Running, Done }
+//!
+//! impl TrPrimitiveState for Phase {
+//!     type Repr = u8;
+//!
+//!     fn can_transit(from: Self, to: Self) -> bool {
+//!         (from as u8) < (to as u8)
+//!     }
+//! }
+//!
+//! let cell = StateCell::<Phase>::with_state(Phase::Idle);
+//! assert!(cell.transition(Phase::Idle, Phase::Done).is_succ());
+//! assert!(cell.transition(Phase::Done, Phase::Running).is_unexpected());
+//! assert_eq!(cell.load(), Phase::Done);
+//! assert_eq!(cell.as_flags().value(), 2);
+//! ```
+
+use crate::{TrAtomicData, TrAtomicState};
+
+/// The trait for the states that convert into `Repr` with `Into`, and back
+/// with `TryFrom`, which makes them `TrAtomicState`.
+pub trait TrPrimitiveState: Copy + Eq {
+    /// The integer representation stored in the atomic cell.
+    type Repr: TrAtomicData + Copy + Eq;
+
+    /// The table of allowed transitions, every one of them by default.
+    fn can_transit(from: Self, to: Self) -> bool {
+        let _ = (from, to);
+        true
+    }
+}
+
+impl<E> TrAtomicState for E
+where
+    E: TrPrimitiveState
+        + Into<<E as TrPrimitiveState>::Repr>
+        + TryFrom<<E as TrPrimitiveState>::Repr>,
+{
+    type Repr = <E as TrPrimitiveState>::Repr;
+
+    #[inline(always)]
+    fn into_repr(self) -> Self::Repr {
+        self.into()
+    }
+
+    #[inline(always)]
+    fn try_from_repr(repr: Self::Repr) -> Option<Self> {
+        E::try_from(repr).ok()
+    }
+
+    #[inline(always)]
+    fn can_transit(from: Self, to: Self) -> bool {
+        <E as TrPrimitiveState>::can_transit(from, to)
+    }
+}
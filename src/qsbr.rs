@@ -0,0 +1,305 @@
+﻿//! Quiescent-state-based reclamation of the pointees unlinked from shared
+//! structures.
+//!
+//! Every thread accessing the structures registers a participant to a
+//! [`QsbrDomain`], and reports a quiescent state at the points where it holds
+//! no reference into them, such as between two iterations of its event loop.
+//! A retired pointee is destroyed once every online participant has reported
+//! a quiescent state since it was retired.
+//!
+//! Unlike epochs, reading costs nothing at all, but a participant which stops
+//! reporting quiescent states without going offline holds back all the
+//! reclamation of its domain.
+//!
+//! # Example
+//!
+//! ```
+//! use core::sync::atomic::{AtomicPtr, Ordering};
+//! use atomex::qsbr::QsbrDomain;
+//!
+//! unsafe fn free(p: *mut u32) {
+//!     drop(unsafe { Box::from_raw(p) });
+//! }
+//!
+//! let domain = QsbrDomain::<4>::new();
+//! let shared = AtomicPtr::new(Box::into_raw(Box::new(1u32)));
+//! let reader = domain.register();
+//! let writer = domain.register();
+//!
+//! let old = shared.swap(Box::into_raw(Box::new(2)), Ordering::AcqRel);
+//! unsafe { domain.retire(old, free) };
+//! writer.quiescent();
+//! assert_eq!(domain.reclaim(), 0);
+//!
+//! reader.quiescent();
+//! assert_eq!(domain.reclaim(), 1);
+//! # drop(unsafe { Box::from_raw(shared.into_inner()) });
+//! ```
+use core::{
+    cell::Cell,
+    fmt,
+    marker::PhantomData,
+    sync::atomic::{self, Ordering},
+};
+use crate::{
+    atomics_::{AtomicBool, AtomicUsize},
+    relax,
+    retired_::{Retired, RetiredList},
+    AtomicCount, CachePadded, StrictOrderings,
+};
+
+type Checkpoint = AtomicCount<usize, AtomicUsize, StrictOrderings>;
+
+/// The checkpoint of an offline participant, which never holds back the
+/// reclamation, and which the domain checkpoint skips when it wraps around.
+const OFFLINE: usize = usize::MAX;
+
+/// The number of pointers retired between two automatic reclamations.
+const RETIRES_PER_RECLAIM: usize = 64;
+
+struct Participant {
+    claimed: AtomicBool,
+    /// The last checkpoint observed in a quiescent state.
+    seen: Checkpoint,
+}
+
+/// The checkpoints, the `N` participant slots and the retired pointers
+/// shared by the threads accessing the same structures.
+pub struct QsbrDomain<const N: usize = 32> {
+    /// Bumped by every retirement, which is tagged with the new value. It
+    /// wraps around, so checkpoints are compared by their distance from it.
+    checkpoint: CachePadded<Checkpoint>,
+    participants: [CachePadded<Participant>; N],
+    retired: RetiredList,
+    retired_count: AtomicUsize,
+}
+
+impl<const N: usize> QsbrDomain<N> {
+    pub const fn new() -> Self {
+        QsbrDomain {
+            checkpoint: CachePadded::new(AtomicCount::new(AtomicUsize::new(0))),
+            participants: [const {
+                CachePadded::new(Participant {
+                    claimed: AtomicBool::new(false),
+                    seen: AtomicCount::new(AtomicUsize::new(OFFLINE)),
+                })
+            }; N],
+            retired: RetiredList::new(),
+            retired_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Claims a participant slot, spinning until one is free. The
+    /// participant starts online.
+    pub fn register(&self) -> QsbrHandle<'_, N> {
+        loop {
+            if let Option::Some(h) = self.try_register() {
+                return h;
+            }
+            relax::relax();
+        }
+    }
+
+    /// Claims a participant slot, or returns `None` if all `N` are claimed.
+    pub fn try_register(&self) -> Option<QsbrHandle<'_, N>> {
+        let index = self.participants.iter().position(|p| {
+            p.claimed
+                .compare_exchange(
+                    false,
+                    true,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+        })?;
+        let handle = QsbrHandle {
+            domain: self,
+            index,
+            _unsync: PhantomData,
+        };
+        handle.online();
+        Option::Some(handle)
+    }
+
+    /// Hands `p` over to the domain, which destroys it with `dtor` once all
+    /// the online participants have gone through a quiescent state.
+    ///
+    /// # Safety
+    ///
+    /// `p` must have been unlinked from every place a reader could load it
+    /// from, and must not be retired again. `dtor` must be safe to call on it
+    /// from any thread.
+    pub unsafe fn retire<T>(&self, p: *mut T, dtor: unsafe fn(*mut T)) {
+        let prev = self.checkpoint.fetch_update(|c| Option::Some(next(c)));
+        let tag = match prev {
+            Result::Ok(c) | Result::Err(c) => next(c),
+        };
+        self.retired.push(Retired::new(p, dtor, tag));
+        let count = self.retired_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if count.is_multiple_of(RETIRES_PER_RECLAIM) {
+            self.reclaim();
+        }
+    }
+
+    /// Destroys the retired pointers every online participant has gone
+    /// through a quiescent state since, and returns how many there were.
+    pub fn reclaim(&self) -> usize {
+        let retired = self.retired.take();
+        let current = self.checkpoint.val();
+        // Observed after the retired pointers are taken, so that a participant
+        // seen offline here has come online after they were retired, and can
+        // not have loaded any of them.
+        let seen: [usize; N] =
+            core::array::from_fn(|i| self.participants[i].seen.val());
+        // The most checkpoints an online participant lags behind. One which
+        // has gone through a quiescent state after `current` was loaded is
+        // ahead of it, and lags by none.
+        let lag = seen
+            .iter()
+            .filter(|&&s| s != OFFLINE)
+            .map(|&s| current.wrapping_sub(s))
+            .filter(|&l| l <= usize::MAX / 2)
+            .max()
+            .unwrap_or(0);
+        let mut destroyed = 0;
+        for retired in retired {
+            // Some online participant has not seen the retirement yet.
+            if current.wrapping_sub(retired.tag) < lag {
+                self.retired.push(retired);
+                continue;
+            }
+            self.retired_count.fetch_sub(1, Ordering::Relaxed);
+            // Every reader has dropped its references since the retirement.
+            unsafe { retired.destroy() };
+            destroyed += 1;
+        }
+        destroyed
+    }
+
+    /// The number of retired pointers not destroyed yet.
+    #[inline(always)]
+    pub fn retired_count(&self) -> usize {
+        self.retired_count.load(Ordering::Relaxed)
+    }
+}
+
+/// The checkpoint following `c`, skipping `OFFLINE`.
+#[inline(always)]
+const fn next(c: usize) -> usize {
+    let n = c.wrapping_add(1);
+    if n == OFFLINE { 0 } else { n }
+}
+
+impl<const N: usize> Default for QsbrDomain<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Drop for QsbrDomain<N> {
+    fn drop(&mut self) {
+        // No participant can be registered while the domain is borrowed
+        // mutably.
+        for retired in self.retired.take_mut() {
+            unsafe { retired.destroy() };
+        }
+    }
+}
+
+impl<const N: usize> fmt::Debug for QsbrDomain<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QsbrDomain")
+            .field("checkpoint", &self.checkpoint.val())
+            .field("participants", &N)
+            .field("retired_count", &self.retired_count())
+            .finish()
+    }
+}
+
+/// A participant of a `QsbrDomain`, to be used by one thread at a time,
+/// which goes offline and releases its slot when dropped.
+pub struct QsbrHandle<'d, const N: usize = 32> {
+    domain: &'d QsbrDomain<N>,
+    index: usize,
+    _unsync: PhantomData<Cell<()>>,
+}
+
+impl<'d, const N: usize> QsbrHandle<'d, N> {
+    #[inline(always)]
+    pub fn domain(&self) -> &'d QsbrDomain<N> {
+        self.domain
+    }
+
+    /// Reports that the thread holds no reference into the structures of
+    /// the domain.
+    pub fn quiescent(&self) {
+        let checkpoint = self.domain.checkpoint.val();
+        self.participant().seen.set(checkpoint);
+        // Orders the store before the loads from the structures that follow,
+        // as a reclamation must not see the participant offline or behind
+        // once it has loaded a pointer.
+        atomic::fence(Ordering::SeqCst);
+    }
+
+    /// Stops holding back the reclamation, for instance before blocking. The
+    /// thread must not access the structures until it is back online.
+    pub fn offline(&self) {
+        self.participant().seen.set(OFFLINE);
+    }
+
+    /// Resumes participating after `offline`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use atomex::qsbr::QsbrDomain;
+    ///
+    /// unsafe fn free(p: *mut u32) {
+    ///     drop(unsafe { Box::from_raw(p) });
+    /// }
+    ///
+    /// let domain = QsbrDomain::<2>::new();
+    /// let reader = domain.register();
+    ///
+    /// // Retired while the reader is offline: it cannot hold the pointer.
+    /// reader.offline();
+    /// unsafe { domain.retire(Box::into_raw(Box::new(1)), free) };
+    /// reader.online();
+    /// assert_eq!(domain.reclaim(), 1);
+    ///
+    /// // Retired while it is back online: it may hold the pointer until its
+    /// // next quiescent state.
+    /// unsafe { domain.retire(Box::into_raw(Box::new(2)), free) };
+    /// assert_eq!(domain.reclaim(), 0);
+    /// reader.quiescent();
+    /// assert_eq!(domain.reclaim(), 1);
+    /// ```
+    pub fn online(&self) {
+        self.quiescent()
+    }
+
+    #[inline(always)]
+    pub fn is_online(&self) -> bool {
+        self.participant().seen.val() != OFFLINE
+    }
+
+    fn participant(&self) -> &Participant {
+        &self.domain.participants[self.index]
+    }
+}
+
+impl<const N: usize> Drop for QsbrHandle<'_, N> {
+    fn drop(&mut self) {
+        self.offline();
+        self.participant().claimed.store(false, Ordering::Release);
+    }
+}
+
+impl<const N: usize> fmt::Debug for QsbrHandle<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QsbrHandle")
+            .field("index", &self.index)
+            .field("is_online", &self.is_online())
+            .finish()
+    }
+}
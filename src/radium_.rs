@@ -0,0 +1,327 @@
+﻿//! A bridge from the `Radium` trait of `radium` to the cell traits, so that
+//! the code generic over them runs on a thread-local `Cell` as well as on an
+//! atomic, without being written twice.
+//!
+//! A `RadiumCell` wraps any `Radium` type, i.e. a core atomic, a `Cell` of a
+//! primitive, or a `radium::types` alias that picks either of them for the
+//! target, and implements `TrAtomicCell` and the fetch traits its item
+//! supports. `fetch::Max` and `fetch::Min`, which `Radium` lacks, are
+//! emulated with `fetch_update`.
+//!
+//! The wrappers such as `AtomicCount` keep their cells to those of
+//! `TrAtomicData`, so a `RadiumCell` serves the code written against the
+//! cell traits themselves.
+//!
+//! ```
+//! use core::{cell::Cell, sync::atomic::{AtomicU32, Ordering}};
+//! use atomex::{fetch::Max, RadiumCell, TrAtomicCell};
+//!
+//! fn record<C>(peak: &C, sample: u32) -> u32
+//! where
+//!     C: TrAtomicCell<Value = u32> + Max<Value = u32>,
+//! {
+//!     peak.fetch_max(sample, Ordering::Relaxed)
+//! }
+//!
+//! let shared = RadiumCell::<AtomicU32>::default();
+//! let local = RadiumCell::from_radium(Cell::new(0u32));
+//! for sample in [3, 9, 4] {
+//!     record(&shared, sample);
+//!     record(&local, sample);
+//! }
+//! assert_eq!(shared.as_radium().load(Ordering::Relaxed), 9);
+//! assert_eq!(local.into_radium().get(), 9);
+//! ```
+
+use core::{fmt, sync::atomic::Ordering};
+
+use radium::{marker, Radium};
+
+use crate::{
+    atomic_cell_::audit, fetch, Bitwise, NumOps, TrAtomicCell, TrAtomicCellMut,
+};
+
+/// A `Radium` type, atomic or not, behind the cell traits of the crate.
+#[repr(transparent)]
+pub struct RadiumCell<R: Radium>(R);
+
+impl<R: Radium> RadiumCell<R> {
+    pub const fn from_radium(radium: R) -> Self {
+        RadiumCell(radium)
+    }
+
+    /// Views a `Radium` value in place as a `RadiumCell`.
+    pub fn from_ref(radium: &R) -> &Self {
+        // Safety: the cell is transparent over `R`.
+        unsafe { &*(radium as *const R).cast::<Self>() }
+    }
+
+    #[inline(always)]
+    pub fn as_radium(&self) -> &R {
+        &self.0
+    }
+
+    #[inline(always)]
+    pub fn into_radium(self) -> R {
+        self.0
+    }
+}
+
+impl<R> TrAtomicCell for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: Copy,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn new(val: Self::Value) -> Self {
+        RadiumCell(R::new(val))
+    }
+
+    #[inline(always)]
+    fn into_inner(self) -> Self::Value {
+        self.0.into_inner()
+    }
+
+    #[inline(always)]
+    fn load(&self, order: Ordering) -> Self::Value {
+        let r = self.0.load(order);
+        audit!(self, Load, order);
+        r
+    }
+
+    #[inline(always)]
+    fn store(&self, val: Self::Value, order: Ordering) {
+        self.0.store(val, order);
+        audit!(self, Store, order);
+    }
+
+    #[inline(always)]
+    fn swap(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let r = self.0.swap(val, order);
+        audit!(self, Swap, order);
+        r
+    }
+
+    #[inline(always)]
+    fn compare_exchange(
+        &self,
+        current: Self::Value,
+        desired: Self::Value,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self::Value, Self::Value> {
+        let r = self.0.compare_exchange(current, desired, success, failure);
+        audit!(self, CompareExchange, success, Option::Some(failure));
+        r
+    }
+
+    #[inline(always)]
+    fn compare_exchange_weak(
+        &self,
+        current: Self::Value,
+        desired: Self::Value,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self::Value, Self::Value> {
+        let r =
+            self.0.compare_exchange_weak(current, desired, success, failure);
+        audit!(self, CompareExchangeWeak, success, Option::Some(failure));
+        r
+    }
+}
+
+impl<R> TrAtomicCellMut for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: Copy,
+{
+    #[inline(always)]
+    fn get_mut(&mut self) -> &mut Self::Value {
+        self.0.get_mut()
+    }
+}
+
+impl<R> Bitwise for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: Copy + marker::BitOps,
+{}
+
+impl<R> fetch::And for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: marker::BitOps,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn fetch_and(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let r = self.0.fetch_and(val, order);
+        audit!(self, FetchAnd, order);
+        r
+    }
+}
+
+impl<R> fetch::Nand for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: marker::BitOps,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn fetch_nand(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let r = self.0.fetch_nand(val, order);
+        audit!(self, FetchNand, order);
+        r
+    }
+}
+
+impl<R> fetch::Or for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: marker::BitOps,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn fetch_or(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let r = self.0.fetch_or(val, order);
+        audit!(self, FetchOr, order);
+        r
+    }
+}
+
+impl<R> fetch::Xor for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: marker::BitOps,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn fetch_xor(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let r = self.0.fetch_xor(val, order);
+        audit!(self, FetchXor, order);
+        r
+    }
+}
+
+impl<R> NumOps for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: Copy + Ord + marker::NumericOps,
+{}
+
+impl<R> fetch::Add for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: marker::NumericOps,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn fetch_add(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let r = self.0.fetch_add(val, order);
+        audit!(self, FetchAdd, order);
+        r
+    }
+}
+
+impl<R> fetch::Sub for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: marker::NumericOps,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn fetch_sub(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let r = self.0.fetch_sub(val, order);
+        audit!(self, FetchSub, order);
+        r
+    }
+}
+
+impl<R> fetch::Update for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: marker::NumericOps,
+{
+    type Value = <R as Radium>::Item;
+
+    #[inline(always)]
+    fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        f: F,
+    ) -> Result<Self::Value, Self::Value>
+    where
+        F: FnMut(Self::Value) -> Option<Self::Value>,
+    {
+        let r = self.0.fetch_update(set_order, fetch_order, f);
+        audit!(self, FetchUpdate, set_order, Option::Some(fetch_order));
+        r
+    }
+}
+
+impl<R> fetch::Max for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: Copy + Ord + marker::NumericOps,
+{
+    type Value = <R as Radium>::Item;
+
+    fn fetch_max(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let update = |v| Option::Some(core::cmp::max(v, val));
+        let fetch_order = load_ordering_of(order);
+        let r = match self.0.fetch_update(order, fetch_order, update) {
+            Result::Ok(v) | Result::Err(v) => v,
+        };
+        audit!(self, FetchMax, order);
+        r
+    }
+}
+
+impl<R> fetch::Min for RadiumCell<R>
+where
+    R: Radium,
+    <R as Radium>::Item: Copy + Ord + marker::NumericOps,
+{
+    type Value = <R as Radium>::Item;
+
+    fn fetch_min(&self, val: Self::Value, order: Ordering) -> Self::Value {
+        let update = |v| Option::Some(core::cmp::min(v, val));
+        let fetch_order = load_ordering_of(order);
+        let r = match self.0.fetch_update(order, fetch_order, update) {
+            Result::Ok(v) | Result::Err(v) => v,
+        };
+        audit!(self, FetchMin, order);
+        r
+    }
+}
+
+impl<R: Radium + Default> Default for RadiumCell<R> {
+    fn default() -> Self {
+        RadiumCell(R::default())
+    }
+}
+
+impl<R: Radium + fmt::Debug> fmt::Debug for RadiumCell<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RadiumCell").field(&self.0).finish()
+    }
+}
+
+/// Maps an ordering for a read-modify-write operation to the closest one
+/// that is valid for a load.
+const fn load_ordering_of(order: Ordering) -> Ordering {
+    match order {
+        Ordering::Release | Ordering::Relaxed => Ordering::Relaxed,
+        Ordering::Acquire | Ordering::AcqRel => Ordering::Acquire,
+        _ => Ordering::SeqCst,
+    }
+}
@@ -0,0 +1,172 @@
+﻿use core::{
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::{AtomicPtr, AtomicUsize};
+use alloc::boxed::Box;
+use crate::{AtomicCount, CachePadded, SpinLock, StrictOrderings};
+
+type ReaderCount = AtomicCount<usize, AtomicUsize, StrictOrderings>;
+
+/// A boxed value read without locking and replaced by copy (read-copy-update).
+///
+/// The readers are counted in one of two counters, chosen by the current
+/// phase. A writer publishes the new value, flips the phase, then waits for
+/// the counter of the previous phase to drain before dropping the old value:
+/// that grace period only waits for the readers which may have loaded it.
+/// Writers are serialized with each other, and must not hold a guard of the
+/// same cell while updating it, or they would wait for themselves forever.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+/// use atomex::RcuCell;
+///
+/// let routes = RcuCell::new(vec![("/", 80)]);
+/// thread::scope(|s| {
+///     s.spawn(|| {
+///         let table = routes.read();
+///         assert!(table.len() == 1 || table.len() == 2);
+///     });
+///     routes.update(|old| {
+///         let mut new = old.clone();
+///         new.push(("/api", 8080));
+///         new
+///     });
+/// });
+/// assert_eq!(routes.read().len(), 2);
+/// ```
+pub struct RcuCell<T> {
+    value: AtomicPtr<T>,
+    phase: AtomicUsize,
+    readers: [CachePadded<ReaderCount>; 2],
+    writer: SpinLock<()>,
+    _owned: PhantomData<Box<T>>,
+}
+
+unsafe impl<T: Send + Sync> Send for RcuCell<T> {}
+unsafe impl<T: Send + Sync> Sync for RcuCell<T> {}
+
+impl<T> RcuCell<T> {
+    pub fn new(value: T) -> Self {
+        RcuCell {
+            value: AtomicPtr::new(Box::into_raw(Box::new(value))),
+            phase: AtomicUsize::new(0),
+            readers: [const {
+                CachePadded::new(AtomicCount::new(AtomicUsize::new(0)))
+            }; 2],
+            writer: SpinLock::new(()),
+            _owned: PhantomData,
+        }
+    }
+
+    /// Returns a guard to the current value, which stays valid as long as
+    /// the guard lives, even if the value is replaced meanwhile.
+    pub fn read(&self) -> RcuGuard<'_, T> {
+        let phase = loop {
+            let phase = self.phase.load(Ordering::SeqCst);
+            self.readers[phase].inc();
+            // Once counted in the current phase, a writer flipping it later
+            // waits for this reader.
+            if self.phase.load(Ordering::SeqCst) == phase {
+                break phase;
+            }
+            self.readers[phase].dec();
+        };
+        let value = self.value.load(Ordering::SeqCst);
+        RcuGuard {
+            cell: self,
+            phase,
+            value,
+        }
+    }
+
+    /// Replaces the value with the one computed by `f` from the current one,
+    /// then drops the old value after the grace period.
+    pub fn update(&self, f: impl FnOnce(&T) -> T) {
+        let _writer = self.writer.lock();
+        let old = self.value.load(Ordering::Relaxed);
+        // Only dropped by a writer, which is this one.
+        let new = Box::into_raw(Box::new(f(unsafe { &*old })));
+        self.value.store(new, Ordering::SeqCst);
+        self.synchronize();
+        // No reader can still hold the old value.
+        drop(unsafe { Box::from_raw(old) });
+    }
+
+    /// Replaces the value with `value`, then drops the old value after the
+    /// grace period.
+    pub fn store(&self, value: T) {
+        self.update(|_| value)
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut **self.value.get_mut() }
+    }
+
+    pub fn into_inner(self) -> T {
+        let this = core::mem::ManuallyDrop::new(self);
+        // Taken once, since `this` is not dropped.
+        let value = this.value.load(Ordering::Relaxed);
+        *unsafe { Box::from_raw(value) }
+    }
+
+    /// Waits for the readers which may have loaded the previous value.
+    fn synchronize(&self) {
+        let phase = self.phase.load(Ordering::Relaxed);
+        self.phase.store(1 - phase, Ordering::SeqCst);
+        while self.readers[phase].val() != 0 {
+            crate::relax::relax();
+        }
+    }
+}
+
+impl<T: Default> Default for RcuCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> Drop for RcuCell<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(*self.value.get_mut()) });
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RcuCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RcuCell").field(&*self.read()).finish()
+    }
+}
+
+/// A reader of the value of an `RcuCell`, holding back the grace period of
+/// any update until dropped.
+pub struct RcuGuard<'a, T> {
+    cell: &'a RcuCell<T>,
+    phase: usize,
+    value: *const T,
+}
+
+impl<T> Deref for RcuGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Not dropped before this guard ends its grace period.
+        unsafe { &*self.value }
+    }
+}
+
+impl<T> Drop for RcuGuard<'_, T> {
+    fn drop(&mut self) {
+        self.cell.readers[self.phase].dec();
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RcuGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,305 @@
+﻿use core::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+    marker::PhantomData,
+    num::NonZeroUsize,
+    ops::Deref,
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomics_::AtomicUsize,
+    AtomicFlags, LocksOrderings, NoBackoff,
+    TrAtomicCell, TrBackoff, TrCmpxchOrderings,
+};
+
+/// Provides an identifier of the current execution context, such as a
+/// thread, a task or a CPU with interrupts disabled.
+///
+/// # Safety
+///
+/// No two contexts running at the same time may have the same identifier,
+/// and a context must keep its identifier while it runs. Otherwise, two
+/// contexts could hold a `ReentrantSpinLock` at once, and reach its value
+/// from two threads.
+pub unsafe trait TrThreadId {
+    fn current() -> NonZeroUsize;
+}
+
+/// The identifier of the current `std` thread.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdThreadId;
+
+// Safety: every thread takes a new id from the counter.
+#[cfg(feature = "std")]
+unsafe impl TrThreadId for StdThreadId {
+    fn current() -> NonZeroUsize {
+        use core::sync::atomic::Ordering;
+
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+        std::thread_local! {
+            static ID: NonZeroUsize = {
+                let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+                NonZeroUsize::new(id).expect("[StdThreadId] ids exhausted")
+            };
+        }
+        ID.with(|id| *id)
+    }
+}
+
+/// A spin lock that can be acquired again by the context already holding it,
+/// protecting a value of `T`.
+///
+/// The lock is always acquired with `Acquire` and released with `Release`,
+/// whatever `O`, which only orders the loads observing the lock.
+///
+/// The holder is recognized with the identifier provided by `I`. Since the
+/// value can be reached through several guards at once, the guards only give
+/// shared access; use a `Cell` or `RefCell` inside for mutation.
+///
+/// # Example
+///
+/// ```
+/// use core::{cell::Cell, num::NonZeroUsize};
+/// use atomex::{ReentrantSpinLock, TrThreadId};
+///
+/// struct LocalAddr;
+///
+/// // Safety: a thread local lies at a distinct address in every running
+/// // thread.
+/// unsafe impl TrThreadId for LocalAddr {
+///     fn current() -> NonZeroUsize {
+///         std::thread_local!(static SLOT: u8 = const { 0 });
+///         SLOT.with(|s| NonZeroUsize::new(s as *const u8 as usize).unwrap())
+///     }
+/// }
+///
+/// let lock = ReentrantSpinLock::<Cell<u32>, LocalAddr>::new(Cell::new(0));
+/// let outer = lock.lock();
+/// let inner = lock.lock();
+/// inner.set(1);
+/// assert_eq!(lock.depth(), 2);
+/// std::thread::scope(|s| {
+///     s.spawn(|| assert!(lock.try_lock().is_none()));
+/// });
+/// drop(inner);
+/// assert_eq!(outer.get(), 1);
+/// drop(outer);
+/// assert!(!lock.is_locked());
+/// ```
+pub struct ReentrantSpinLock<T: ?Sized, I, O = LocksOrderings>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    owner: AtomicFlags<usize, AtomicUsize, O>,
+    // Only accessed by the owner.
+    depth: Cell<usize>,
+    _id: PhantomData<fn() -> I>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, I, O> Send for ReentrantSpinLock<T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{}
+
+unsafe impl<T: ?Sized + Send, I, O> Sync for ReentrantSpinLock<T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{}
+
+impl<T, I, O> ReentrantSpinLock<T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    pub const fn new(data: T) -> Self {
+        ReentrantSpinLock {
+            owner: AtomicFlags::new(AtomicUsize::new(0)),
+            depth: Cell::new(0),
+            _id: PhantomData,
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, I, O> ReentrantSpinLock<T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.owner.value() != 0
+    }
+
+    /// Returns `true` if the current context holds the lock.
+    pub fn is_owned_by_current(&self) -> bool {
+        self.owner.value() == I::current().get()
+    }
+
+    /// The number of guards alive, if the current context holds the lock,
+    /// else zero.
+    pub fn depth(&self) -> usize {
+        if self.is_owned_by_current() {
+            self.depth.get()
+        } else {
+            0
+        }
+    }
+
+    pub fn try_lock(&self) -> Option<ReentrantSpinLockGuard<'_, T, I, O>> {
+        let id = I::current().get();
+        if self.owner.value() == id {
+            return Option::Some(self.enter());
+        }
+        let r = TrAtomicCell::compare_exchange(
+            self.owner.as_ref(),
+            0,
+            id,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        );
+        if r.is_ok() {
+            Option::Some(self.enter())
+        } else {
+            Option::None
+        }
+    }
+
+    #[inline(always)]
+    pub fn lock(&self) -> ReentrantSpinLockGuard<'_, T, I, O> {
+        self.lock_with_backoff(NoBackoff)
+    }
+
+    pub fn lock_with_backoff(
+        &self,
+        mut backoff: impl TrBackoff,
+    ) -> ReentrantSpinLockGuard<'_, T, I, O> {
+        let id = I::current().get();
+        // Only this context can store its own id, so a stale load can not
+        // make it believe it holds the lock.
+        if self.owner.value() == id {
+            return self.enter();
+        }
+        loop {
+            let r = TrAtomicCell::compare_exchange_weak(
+                self.owner.as_ref(),
+                0,
+                id,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            );
+            if r.is_ok() {
+                break self.enter();
+            }
+            let _ = self.owner.spin_while(|v| {
+                if v != 0 {
+                    backoff.backoff();
+                }
+                v != 0
+            });
+        }
+    }
+
+    fn enter(&self) -> ReentrantSpinLockGuard<'_, T, I, O> {
+        let Option::Some(depth) = self.depth.get().checked_add(1) else {
+            panic!("[ReentrantSpinLock::enter] too many nested locks")
+        };
+        self.depth.set(depth);
+        ReentrantSpinLockGuard {
+            lock: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    fn exit(&self) {
+        let depth = self.depth.get() - 1;
+        self.depth.set(depth);
+        if depth == 0 {
+            TrAtomicCell::store(self.owner.as_ref(), 0, Ordering::Release);
+        }
+    }
+
+    /// Returns a mutable reference to the value, which needs no locking
+    /// since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default, I, O> Default for ReentrantSpinLock<T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, I, O> fmt::Debug for ReentrantSpinLock<T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ReentrantSpinLock");
+        match self.try_lock() {
+            Option::Some(g) => d.field("data", &&*g),
+            Option::None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// The RAII guard of a `ReentrantSpinLock`, releasing one level of the lock
+/// when dropped.
+pub struct ReentrantSpinLockGuard<'a, T: ?Sized, I, O = LocksOrderings>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    lock: &'a ReentrantSpinLock<T, I, O>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<T: ?Sized, I, O> Deref for ReentrantSpinLockGuard<'_, T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, I, O> Drop for ReentrantSpinLockGuard<'_, T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    fn drop(&mut self) {
+        self.lock.exit()
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, I, O> fmt::Debug
+for ReentrantSpinLockGuard<'_, T, I, O>
+where
+    I: TrThreadId,
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
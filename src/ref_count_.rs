@@ -0,0 +1,134 @@
+﻿use core::{
+    borrow::BorrowMut,
+    fmt::{self, Debug},
+    sync::atomic::{self, Ordering},
+};
+use crate::atomics_::AtomicUsize;
+
+/// A reference count with the orderings of `Arc`, that saturates instead of
+/// overflowing.
+///
+/// Once the count reaches `RefCount::SATURATED` it stays there: the shared
+/// data is leaked rather than freed while references may still be alive.
+/// This can only happen when references are leaked in a loop, which is a bug
+/// on its own, but one that should not turn into a use-after-free.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicUsize;
+/// use atomex::RefCount;
+///
+/// let refs = RefCount::new(AtomicUsize::new(1));
+/// refs.acquire();
+/// assert!(!refs.release());
+/// assert!(refs.release());
+/// assert!(!refs.try_upgrade());
+/// ```
+pub struct RefCount<B = AtomicUsize>(B)
+where
+    B: BorrowMut<AtomicUsize>;
+
+impl<B> RefCount<B>
+where
+    B: BorrowMut<AtomicUsize>,
+{
+    /// The count at which the reference count sticks.
+    pub const SATURATED: usize = isize::MAX as usize;
+
+    pub const fn new(cell: B) -> Self {
+        RefCount(cell)
+    }
+
+    /// Loads the count, which may be stale as soon as it is returned.
+    pub fn count(&self) -> usize {
+        self.cell().load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the caller holds the only reference, with every
+    /// access made through the released references happening before.
+    pub fn is_unique(&self) -> bool {
+        self.cell().load(Ordering::Acquire) == 1
+    }
+
+    /// Adds a reference, for a caller that already holds one.
+    pub fn acquire(&self) {
+        // As in `Arc::clone`, the new reference is derived from an existing
+        // one, so no synchronization is needed.
+        let prev = self.cell().fetch_add(1, Ordering::Relaxed);
+        if prev >= Self::SATURATED {
+            self.cell().store(Self::SATURATED, Ordering::Relaxed);
+        }
+    }
+
+    /// Adds a reference unless the count has dropped to zero, i.e. unless
+    /// the shared data is being destroyed. Returns whether a reference was
+    /// added.
+    pub fn try_upgrade(&self) -> bool {
+        let cell = self.cell();
+        let mut current = cell.load(Ordering::Relaxed);
+        loop {
+            if current == 0 {
+                break false;
+            }
+            if current >= Self::SATURATED {
+                break true;
+            }
+            match cell.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Result::Ok(_) => break true,
+                Result::Err(x) => current = x,
+            }
+        }
+    }
+
+    /// Drops a reference, and returns `true` exactly when it was the last
+    /// one, in which case the caller is responsible for destroying the shared
+    /// data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count was already zero.
+    pub fn release(&self) -> bool {
+        let cell = self.cell();
+        if cell.load(Ordering::Relaxed) >= Self::SATURATED {
+            return false;
+        }
+        let prev = cell.fetch_sub(1, Ordering::Release);
+        if prev != 1 {
+            assert!(prev != 0, "[RefCount::release] released too many times");
+            return false;
+        }
+        atomic::fence(Ordering::Acquire);
+        true
+    }
+
+    #[inline(always)]
+    fn cell(&self) -> &AtomicUsize {
+        self.0.borrow()
+    }
+}
+
+impl RefCount<AtomicUsize> {
+    /// Create an owned instance holding a single reference.
+    pub const fn one() -> Self {
+        RefCount(AtomicUsize::new(1))
+    }
+
+    pub fn into_inner(self) -> AtomicUsize {
+        self.0
+    }
+}
+
+impl<B> Debug for RefCount<B>
+where
+    B: BorrowMut<AtomicUsize>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RefCount").field(&self.count()).finish()
+    }
+}
@@ -0,0 +1,348 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    ptr,
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomic_cell_::audit, fetch, Bitwise, NumOps, TrAtomicCell, TrAtomicCellMut,
+};
+
+/// A memory-mapped peripheral register, accessed with volatile reads and
+/// writes only, with the same fetch operations as the atomic cells.
+///
+/// Loads and stores are single volatile accesses. The read-modify-write
+/// operations are a volatile read and a volatile write inside a critical
+/// section, so that no interrupt handler touches the register in between.
+/// The orderings are ignored: volatile accesses are neither reordered with
+/// each other nor elided, which is what device registers need.
+///
+/// Registers are usually obtained from their address with `from_ptr`. The
+/// cell can also be owned, e.g. to simulate a device in RAM.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use atomex::{fetch::{And, Or}, RegisterCell, TrAtomicCell};
+///
+/// const ENABLE: u32 = 1 << 0;
+/// const IRQ_ON: u32 = 1 << 4;
+///
+/// let mut device = [0u32; 4];
+/// // Safety: a stand-in for the address of a control register.
+/// let ctrl = unsafe { RegisterCell::from_ptr(&mut device[1]) };
+/// ctrl.store(ENABLE, Ordering::Relaxed);
+/// ctrl.fetch_or(IRQ_ON, Ordering::Relaxed);
+/// assert_eq!(ctrl.fetch_and(!ENABLE, Ordering::Relaxed), ENABLE | IRQ_ON);
+/// assert_eq!(ctrl.load(Ordering::Relaxed), IRQ_ON);
+/// ```
+#[repr(transparent)]
+pub struct RegisterCell<T: Copy>(UnsafeCell<T>);
+
+// Safety: the register is only accessed by single volatile accesses, or in
+// critical sections.
+unsafe impl<T: Copy + Send> Sync for RegisterCell<T> {}
+
+impl<T: Copy> RegisterCell<T> {
+    pub const fn new(value: T) -> Self {
+        RegisterCell(UnsafeCell::new(value))
+    }
+
+    /// The register at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for volatile reads and writes of `T`, aligned,
+    /// and for `'a` only accessed through cells or volatile accesses.
+    #[inline(always)]
+    pub const unsafe fn from_ptr<'a>(ptr: *mut T) -> &'a Self {
+        // Safety: the cell is transparent over `T`.
+        unsafe { &*(ptr as *const Self) }
+    }
+
+    #[inline(always)]
+    pub const fn as_ptr(&self) -> *mut T {
+        self.0.get()
+    }
+
+    #[inline(always)]
+    pub fn read(&self) -> T {
+        // Safety: the register is valid for volatile accesses.
+        unsafe { ptr::read_volatile(self.0.get()) }
+    }
+
+    #[inline(always)]
+    pub fn write(&self, value: T) {
+        // Safety: as above.
+        unsafe { ptr::write_volatile(self.0.get(), value) }
+    }
+
+    /// Replaces the value with `f` of it unless `f` returns `None`, inside a
+    /// critical section.
+    ///
+    /// Returns `Ok(previous)` if it was replaced, else `Err(current)`.
+    pub fn modify(
+        &self,
+        f: impl FnOnce(T) -> Option<T>,
+    ) -> Result<T, T> {
+        critical_section::with(|_| {
+            let prev = self.read();
+            match f(prev) {
+                Option::Some(x) => {
+                    self.write(x);
+                    Result::Ok(prev)
+                }
+                Option::None => Result::Err(prev),
+            }
+        })
+    }
+
+    #[inline(always)]
+    fn fetch_apply(&self, f: impl FnOnce(T) -> T) -> T {
+        match self.modify(|x| Option::Some(f(x))) {
+            Result::Ok(x) | Result::Err(x) => x,
+        }
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl RegisterCell<u32> {
+    /// Sets or clears the bit `bit` with a single write to its bit-band
+    /// alias, which needs no critical section.
+    ///
+    /// # Safety
+    ///
+    /// The core must implement bit-banding, such as Cortex-M3 and Cortex-M4
+    /// do, and the register must lie in the bit-band region of the
+    /// peripherals or of the SRAM.
+    pub unsafe fn write_bit_banded(&self, bit: u32, set: bool) {
+        const { assert!(core::mem::size_of::<usize>() == 4) };
+        assert!(bit < 32, "[RegisterCell::write_bit_banded] bit out of range");
+        let addr = self.as_ptr() as usize;
+        let region = addr & 0xF000_0000;
+        let alias = region + 0x0200_0000
+            + (addr - region) * 32
+            + bit as usize * 4;
+        // Safety: the alias is valid, as required.
+        unsafe { ptr::write_volatile(alias as *mut u32, set as u32) }
+    }
+}
+
+impl<T: Copy + Default> Default for RegisterCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for RegisterCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RegisterCell").field(&self.read()).finish()
+    }
+}
+
+impl<T: Copy + PartialEq> TrAtomicCell for RegisterCell<T> {
+    type Value = T;
+
+    #[inline(always)]
+    fn new(val: T) -> Self {
+        RegisterCell::new(val)
+    }
+
+    #[inline(always)]
+    fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+
+    #[inline(always)]
+    fn load(&self, order: Ordering) -> T {
+        let r = self.read();
+        audit!(self, Load, order);
+        r
+    }
+
+    #[inline(always)]
+    fn store(&self, val: T, order: Ordering) {
+        self.write(val);
+        audit!(self, Store, order);
+    }
+
+    #[inline(always)]
+    fn swap(&self, val: T, order: Ordering) -> T {
+        let r = self.fetch_apply(|_| val);
+        audit!(self, Swap, order);
+        r
+    }
+
+    fn compare_exchange(
+        &self,
+        current: T,
+        desired: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        let r = self.modify(|x| (x == current).then_some(desired));
+        audit!(self, CompareExchange, success, Option::Some(failure));
+        r
+    }
+
+    #[inline(always)]
+    fn compare_exchange_weak(
+        &self,
+        current: T,
+        desired: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        let r = self.modify(|x| (x == current).then_some(desired));
+        audit!(self, CompareExchangeWeak, success, Option::Some(failure));
+        r
+    }
+}
+
+impl<T: Copy + PartialEq> TrAtomicCellMut for RegisterCell<T> {
+    #[inline(always)]
+    fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut()
+    }
+}
+
+macro_rules! impl_register_cell {
+    ($primitive:ty) => {
+        impl Bitwise for RegisterCell<$primitive> {}
+
+        impl fetch::And for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_and(
+                &self,
+                val: $primitive,
+                order: Ordering,
+            ) -> $primitive {
+                let r = self.fetch_apply(|x| x & val);
+                audit!(self, FetchAnd, order);
+                r
+            }
+        }
+
+        impl fetch::Nand for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_nand(
+                &self,
+                val: $primitive,
+                order: Ordering,
+            ) -> $primitive {
+                let r = self.fetch_apply(|x| !(x & val));
+                audit!(self, FetchNand, order);
+                r
+            }
+        }
+
+        impl fetch::Or for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_or(&self, val: $primitive, order: Ordering) -> $primitive {
+                let r = self.fetch_apply(|x| x | val);
+                audit!(self, FetchOr, order);
+                r
+            }
+        }
+
+        impl fetch::Xor for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_xor(
+                &self,
+                val: $primitive,
+                order: Ordering,
+            ) -> $primitive {
+                let r = self.fetch_apply(|x| x ^ val);
+                audit!(self, FetchXor, order);
+                r
+            }
+        }
+
+        impl NumOps for RegisterCell<$primitive> {}
+
+        impl fetch::Add for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_add(
+                &self,
+                val: $primitive,
+                order: Ordering,
+            ) -> $primitive {
+                let r = self.fetch_apply(|x| x.wrapping_add(val));
+                audit!(self, FetchAdd, order);
+                r
+            }
+        }
+
+        impl fetch::Sub for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_sub(
+                &self,
+                val: $primitive,
+                order: Ordering,
+            ) -> $primitive {
+                let r = self.fetch_apply(|x| x.wrapping_sub(val));
+                audit!(self, FetchSub, order);
+                r
+            }
+        }
+
+        impl fetch::Update for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<Self::Value, Self::Value>
+            where
+                F: FnMut(Self::Value) -> Option<Self::Value>,
+            {
+                let r = self.modify(f);
+                audit!(self, FetchUpdate, set_order, Option::Some(fetch_order));
+                r
+            }
+        }
+
+        impl fetch::Max for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_max(
+                &self,
+                val: $primitive,
+                order: Ordering,
+            ) -> $primitive {
+                let r = self.fetch_apply(|x| x.max(val));
+                audit!(self, FetchMax, order);
+                r
+            }
+        }
+
+        impl fetch::Min for RegisterCell<$primitive> {
+            type Value = $primitive;
+
+            fn fetch_min(
+                &self,
+                val: $primitive,
+                order: Ordering,
+            ) -> $primitive {
+                let r = self.fetch_apply(|x| x.min(val));
+                audit!(self, FetchMin, order);
+                r
+            }
+        }
+    };
+}
+
+impl_register_cell!(u8);
+impl_register_cell!(u16);
+impl_register_cell!(u32);
+impl_register_cell!(u64);
+impl_register_cell!(usize);
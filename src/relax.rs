@@ -0,0 +1,155 @@
+﻿//! The hints issued by spin loops between two attempts, as policy types.
+//!
+//! The backoff policies such as `SpinBackoff` and `ExpBackoff` take the
+//! relax policy as a type parameter, so that an application can pick the
+//! hint that suits its cores, e.g. `isb` on AArch64 servers where `yield` is
+//! a no-op, or no hint at all on a single-core microcontroller.
+//!
+//! The spin loops inside the crate issue the hint of `DefaultRelax`, chosen
+//! per target.
+//!
+//! # Example
+//!
+//! ```
+//! use atomex::{relax::{Nop, TrRelax}, ExpBackoff, SpinLock};
+//!
+//! /// The board has a single core, which spinning never yields to.
+//! struct BoardRelax;
+//!
+//! impl TrRelax for BoardRelax {
+//!     fn relax() {
+//!         Nop::relax()
+//!     }
+//! }
+//!
+//! let lock = SpinLock::<u32>::new(0);
+//! let backoff = ExpBackoff::new().with_relax::<BoardRelax>();
+//! *lock.lock_with_backoff(backoff) += 1;
+//! assert_eq!(*lock.lock(), 1);
+//! ```
+
+use core::hint;
+
+/// A hint issued by a spin loop between two attempts.
+pub trait TrRelax {
+    fn relax();
+}
+
+/// The hint of `core::hint::spin_loop`, which is `pause` on x86, `isb` on
+/// AArch64, `yield` on ARMv6 and later, and `pause` on RISC-V cores built
+/// with the `zihintpause` target feature.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Spin;
+
+impl TrRelax for Spin {
+    #[inline(always)]
+    fn relax() {
+        hint::spin_loop()
+    }
+}
+
+/// The `pause` instruction of x86 and RISC-V.
+///
+/// On RISC-V, it is encoded as the `fence` hint of the `Zihintpause`
+/// extension, so that the cores without it run it as a no-op. Elsewhere, it
+/// falls back to `Spin`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pause;
+
+impl TrRelax for Pause {
+    #[inline(always)]
+    fn relax() {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        // Safety: a hint only.
+        unsafe {
+            core::arch::asm!("pause", options(nomem, nostack, preserves_flags))
+        };
+        #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+        // Safety: as above.
+        unsafe {
+            core::arch::asm!(
+                ".insn i 0x0F, 0, x0, x0, 0x010",
+                options(nomem, nostack, preserves_flags),
+            )
+        };
+        #[cfg(not(any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "riscv32",
+            target_arch = "riscv64",
+        )))]
+        Spin::relax();
+    }
+}
+
+/// The `yield` instruction of AArch64, which most cores run as a no-op but
+/// which lets the other hardware threads of the core run on those with SMT.
+///
+/// Elsewhere, it falls back to `Spin`, which is `yield` on ARMv6 and later.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Yield;
+
+impl TrRelax for Yield {
+    #[inline(always)]
+    fn relax() {
+        #[cfg(target_arch = "aarch64")]
+        // Safety: a hint only.
+        unsafe {
+            core::arch::asm!("yield", options(nomem, nostack, preserves_flags))
+        };
+        #[cfg(not(target_arch = "aarch64"))]
+        Spin::relax();
+    }
+}
+
+/// The `isb` barrier of AArch64, which stalls the core for a few dozen
+/// cycles, for a longer and more predictable delay than `yield`.
+///
+/// Elsewhere, it falls back to `Spin`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Isb;
+
+impl TrRelax for Isb {
+    #[inline(always)]
+    fn relax() {
+        #[cfg(target_arch = "aarch64")]
+        // Safety: a barrier only.
+        unsafe {
+            core::arch::asm!("isb sy", options(nostack, preserves_flags))
+        };
+        #[cfg(not(target_arch = "aarch64"))]
+        Spin::relax();
+    }
+}
+
+/// No hint at all, for the single-core targets where nothing else can run
+/// while spinning but an interrupt handler.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Nop;
+
+impl TrRelax for Nop {
+    #[inline(always)]
+    fn relax() {}
+}
+
+/// The relax policy of the target, used by the spin loops of the crate and
+/// by default by the backoff policies.
+///
+/// This is `Pause` on RISC-V, which `core::hint::spin_loop` only emits with
+/// the `zihintpause` target feature, and `Spin` elsewhere.
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+pub type DefaultRelax = Pause;
+
+/// The relax policy of the target, used by the spin loops of the crate and
+/// by default by the backoff policies.
+///
+/// This is `Pause` on RISC-V, which `core::hint::spin_loop` only emits with
+/// the `zihintpause` target feature, and `Spin` elsewhere.
+#[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+pub type DefaultRelax = Spin;
+
+/// Issues the hint of `DefaultRelax`.
+#[inline(always)]
+pub fn relax() {
+    DefaultRelax::relax()
+}
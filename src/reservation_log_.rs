@@ -0,0 +1,154 @@
+﻿use core::{
+    fmt,
+    ops::Range,
+    sync::atomic::Ordering,
+};
+use crate::{atomics_::AtomicUsize, CachePadded};
+
+/// What `ReservationLog::reserve` does with a range going past the capacity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReservePolicy {
+    /// Fails the reservation, so that every position is reserved once.
+    Bounded,
+
+    /// Lets the positions go on, mapping them to the buffer modulo the
+    /// capacity, so that the latest entries overwrite the oldest ones.
+    Wrapping,
+}
+
+/// The positions reserved and committed by the producers of a shared buffer
+/// of `capacity` entries.
+///
+/// Each producer reserves a range of contiguous positions, writes its
+/// entries in the buffer, then commits the range. The committed watermark
+/// only moves past a range once all the ranges before it are committed, so
+/// that every entry below the watermark is completely written.
+///
+/// # Example
+///
+/// ```
+/// use atomex::{ReservationLog, ReservePolicy};
+///
+/// let log = ReservationLog::new(8, ReservePolicy::Bounded);
+/// let a = log.reserve(3).unwrap();
+/// let b = log.reserve(4).unwrap();
+/// assert_eq!((a.clone(), b.clone()), (0..3, 3..7));
+/// assert!(log.reserve(2).is_none());
+///
+/// log.commit(a);
+/// assert_eq!(log.committed(), 3);
+/// log.commit(b);
+/// assert_eq!(log.committed(), 7);
+///
+/// let wrapping = ReservationLog::new(8, ReservePolicy::Wrapping);
+/// assert_eq!(wrapping.reserve(6), Some(0..6));
+/// let r = wrapping.reserve(4).unwrap();
+/// assert_eq!(r, 6..10);
+/// assert_eq!(wrapping.index_of(r.end - 1), 1);
+/// ```
+pub struct ReservationLog {
+    reserved: CachePadded<AtomicUsize>,
+    committed: CachePadded<AtomicUsize>,
+    capacity: usize,
+    policy: ReservePolicy,
+}
+
+impl ReservationLog {
+    pub const fn new(capacity: usize, policy: ReservePolicy) -> Self {
+        assert!(capacity > 0, "[ReservationLog::new] zero capacity");
+        ReservationLog {
+            reserved: CachePadded::new(AtomicUsize::new(0)),
+            committed: CachePadded::new(AtomicUsize::new(0)),
+            capacity,
+            policy,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline(always)]
+    pub const fn policy(&self) -> ReservePolicy {
+        self.policy
+    }
+
+    /// The end of the reserved positions.
+    #[inline(always)]
+    pub fn reserved(&self) -> usize {
+        self.reserved.load(Ordering::Relaxed)
+    }
+
+    /// The watermark below which all the positions are committed, and their
+    /// entries visible to this thread.
+    #[inline(always)]
+    pub fn committed(&self) -> usize {
+        self.committed.load(Ordering::Acquire)
+    }
+
+    /// The index in the buffer of the entry at position `pos`.
+    #[inline(always)]
+    pub const fn index_of(&self, pos: usize) -> usize {
+        pos % self.capacity
+    }
+
+    /// Reserves `n` contiguous positions.
+    ///
+    /// Returns `None` if the range would go past the capacity under
+    /// `ReservePolicy::Bounded`, or would overwrite itself under
+    /// `ReservePolicy::Wrapping`.
+    pub fn reserve(&self, n: usize) -> Option<Range<usize>> {
+        match self.policy {
+            ReservePolicy::Bounded => {
+                let start = self
+                    .reserved
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+                        x.checked_add(n).filter(|end| *end <= self.capacity)
+                    })
+                    .ok()?;
+                Option::Some(start..start + n)
+            }
+            ReservePolicy::Wrapping => {
+                if n > self.capacity {
+                    return Option::None;
+                }
+                let start = self.reserved.fetch_add(n, Ordering::Relaxed);
+                let Option::Some(end) = start.checked_add(n) else {
+                    panic!("[ReservationLog::reserve] positions overflow")
+                };
+                Option::Some(start..end)
+            }
+        }
+    }
+
+    /// Commits `range`, once all the ranges reserved before it are.
+    ///
+    /// Spins while an earlier range is not committed yet, so the entries
+    /// should be written between the reservation and the commit without
+    /// blocking.
+    pub fn commit(&self, range: Range<usize>) {
+        while self.committed.load(Ordering::Acquire) != range.start {
+            crate::relax::relax();
+        }
+        // Publishes the entries of the range.
+        self.committed.store(range.end, Ordering::Release);
+    }
+
+    /// Forgets all the positions, to start over from `0`.
+    pub fn reset(&mut self) {
+        *self.reserved.get_mut() = 0;
+        *self.committed.get_mut() = 0;
+    }
+}
+
+impl fmt::Debug for ReservationLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReservationLog")
+            .field("capacity", &self.capacity)
+            .field("policy", &self.policy)
+            .field("reserved", &self.reserved())
+            .field("committed", &self.committed())
+            .finish()
+    }
+}
@@ -0,0 +1,100 @@
+﻿use core::{
+    mem,
+    ptr,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::AtomicPtr;
+use alloc::boxed::Box;
+
+/// A pointer handed over to a reclamation scheme, with the function
+/// destroying it and the scheme's own tag.
+pub(crate) struct Retired {
+    pointer: *mut (),
+    dtor: unsafe fn(*mut ()),
+    pub tag: usize,
+    next: *mut Retired,
+}
+
+impl Retired {
+    pub fn new<T>(p: *mut T, dtor: unsafe fn(*mut T), tag: usize) -> Box<Self> {
+        // Function pointers differing only in the pointee of a thin pointer
+        // argument are ABI compatible.
+        let dtor = unsafe {
+            mem::transmute::<unsafe fn(*mut T), unsafe fn(*mut ())>(dtor)
+        };
+        Box::new(Retired {
+            pointer: p as *mut (),
+            dtor,
+            tag,
+            next: ptr::null_mut(),
+        })
+    }
+
+    #[inline(always)]
+    pub fn pointer(&self) -> *mut () {
+        self.pointer
+    }
+
+    /// Destroys the pointer with the function it was retired with.
+    ///
+    /// # Safety
+    ///
+    /// No reader may access the pointer any longer.
+    pub unsafe fn destroy(self) {
+        unsafe { (self.dtor)(self.pointer) }
+    }
+}
+
+/// A lock-free stack of retired pointers.
+pub(crate) struct RetiredList(AtomicPtr<Retired>);
+
+impl RetiredList {
+    pub const fn new() -> Self {
+        RetiredList(AtomicPtr::new(ptr::null_mut()))
+    }
+
+    pub fn push(&self, retired: Box<Retired>) {
+        let node = Box::into_raw(retired);
+        let mut head = self.0.load(Ordering::Relaxed);
+        loop {
+            // The node is not shared until the exchange succeeds.
+            unsafe { (*node).next = head };
+            match self.0.compare_exchange_weak(
+                head,
+                node,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Result::Ok(_) => return,
+                Result::Err(x) => head = x,
+            }
+        }
+    }
+
+    /// Takes all the retired pointers out of the list.
+    pub fn take(&self) -> Drain {
+        Drain(self.0.swap(ptr::null_mut(), Ordering::Acquire))
+    }
+
+    pub fn take_mut(&mut self) -> Drain {
+        Drain(mem::replace(self.0.get_mut(), ptr::null_mut()))
+    }
+}
+
+/// The retired pointers taken out of a `RetiredList`, which must all be
+/// either destroyed or pushed back.
+pub(crate) struct Drain(*mut Retired);
+
+impl Iterator for Drain {
+    type Item = Box<Retired>;
+
+    fn next(&mut self) -> Option<Box<Retired>> {
+        if self.0.is_null() {
+            return Option::None;
+        }
+        // Owned by the drain since taken out of the list.
+        let mut retired = unsafe { Box::from_raw(self.0) };
+        self.0 = mem::replace(&mut retired.next, ptr::null_mut());
+        Option::Some(retired)
+    }
+}
@@ -0,0 +1,119 @@
+﻿use core::{fmt, mem};
+use crate::{
+    atomics_::AtomicUsize, AtomicCount, NoBackoff, RefCountOrderings, TrBackoff,
+};
+
+/// A counting semaphore whose waiters spin, limiting how many holders can
+/// use a resource at once.
+///
+/// # Example
+///
+/// ```
+/// use atomex::Semaphore;
+///
+/// let slots = Semaphore::new(3);
+/// let two = slots.try_acquire(2).unwrap();
+/// assert_eq!(two.count(), 2);
+/// assert!(slots.try_acquire(2).is_none());
+///
+/// let one = slots.acquire_spin(1);
+/// assert_eq!(slots.available_permits(), 0);
+/// drop(two);
+/// assert_eq!(slots.available_permits(), 2);
+///
+/// one.forget();
+/// assert_eq!(slots.available_permits(), 2);
+/// slots.release(1);
+/// assert_eq!(slots.available_permits(), 3);
+/// ```
+pub struct Semaphore(AtomicCount<usize, AtomicUsize, RefCountOrderings>);
+
+impl Semaphore {
+    pub const fn new(permits: usize) -> Self {
+        Semaphore(AtomicCount::new(AtomicUsize::new(permits)))
+    }
+
+    #[inline(always)]
+    pub fn available_permits(&self) -> usize {
+        self.0.val()
+    }
+
+    /// Takes `n` permits if that many are available.
+    pub fn try_acquire(&self, n: usize) -> Option<Permit<'_>> {
+        self.0
+            .fetch_update(|v| v.checked_sub(n))
+            .ok()
+            .map(|_| Permit { sem: self, n })
+    }
+
+    /// Takes `n` permits, spinning until that many are available.
+    #[inline(always)]
+    pub fn acquire_spin(&self, n: usize) -> Permit<'_> {
+        self.acquire_spin_with_backoff(n, NoBackoff)
+    }
+
+    /// Takes `n` permits, waiting according to `backoff` whenever fewer are
+    /// available.
+    pub fn acquire_spin_with_backoff(
+        &self,
+        n: usize,
+        mut backoff: impl TrBackoff,
+    ) -> Permit<'_> {
+        loop {
+            if let Option::Some(p) = self.try_acquire(n) {
+                break p;
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Adds `n` permits, e.g. to return the ones of a forgotten `Permit`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under `debug_assertions` if the count of permits overflows.
+    #[inline(always)]
+    pub fn release(&self, n: usize) {
+        let _ = self.0.add(n);
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("permits", &self.available_permits())
+            .finish()
+    }
+}
+
+/// The permits taken from a `Semaphore`, given back when dropped.
+#[must_use = "the permits are released at once if not held"]
+pub struct Permit<'a> {
+    sem: &'a Semaphore,
+    n: usize,
+}
+
+impl Permit<'_> {
+    /// The number of permits held.
+    #[inline(always)]
+    pub fn count(&self) -> usize {
+        self.n
+    }
+
+    /// Drops the guard without giving back the permits.
+    pub fn forget(self) {
+        mem::forget(self)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.sem.release(self.n)
+    }
+}
+
+impl fmt::Debug for Permit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Permit").field("count", &self.n).finish()
+    }
+}
@@ -0,0 +1,176 @@
+﻿use core::{
+    fmt,
+    sync::atomic::{fence, Ordering},
+};
+use crate::{atomics_::AtomicUsize, relax};
+
+/// The sequence counter of a sequence lock, protecting data laid out by the
+/// user.
+///
+/// A reader takes the sequence with `read_begin`, reads the data, and must
+/// discard what it read and retry if `read_retry` returns `true`. The data
+/// must therefore be read in a way that tolerates concurrent writes, such as
+/// with relaxed atomics or volatile reads of plain `Copy` values.
+///
+/// A writer brackets its writes with `begin_write` and `end_write`, which
+/// also excludes other writers.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use atomex::SeqCount;
+///
+/// let seq = SeqCount::new();
+/// let (lo, hi) = (AtomicU32::new(0), AtomicU32::new(0));
+///
+/// let w = seq.begin_write();
+/// lo.store(1, Ordering::Relaxed);
+/// hi.store(2, Ordering::Relaxed);
+/// seq.end_write(w);
+///
+/// let pair = loop {
+///     let s = seq.read_begin();
+///     let pair = (lo.load(Ordering::Relaxed), hi.load(Ordering::Relaxed));
+///     if !seq.read_retry(s) {
+///         break pair;
+///     }
+/// };
+/// assert_eq!(pair, (1, 2));
+/// ```
+pub struct SeqCount(AtomicUsize);
+
+/// A write in progress on a `SeqCount`, to be ended with `end_write` or
+/// `cancel_write`.
+#[must_use = "a write must be ended or cancelled"]
+#[derive(Debug)]
+pub struct SeqWrite(usize);
+
+impl SeqCount {
+    pub const fn new() -> Self {
+        SeqCount(AtomicUsize::new(0))
+    }
+
+    /// The current sequence, which is odd while a write is in progress and
+    /// grows by two with every write.
+    #[inline(always)]
+    pub fn sequence(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Spins until no write is in progress, and returns the sequence to
+    /// check with `read_retry` after reading.
+    pub fn read_begin(&self) -> usize {
+        loop {
+            if let Option::Some(s) = self.try_read_begin() {
+                break s;
+            }
+            relax::relax();
+        }
+    }
+
+    /// Returns the sequence to check with `read_retry` after reading, or
+    /// `None` if a write is in progress.
+    #[inline(always)]
+    pub fn try_read_begin(&self) -> Option<usize> {
+        let s = self.0.load(Ordering::Acquire);
+        (s & 1 == 0).then_some(s)
+    }
+
+    /// Returns `true` if a write happened since `read_begin` returned
+    /// `start`, in which case the data read must be discarded.
+    #[inline(always)]
+    pub fn read_retry(&self, start: usize) -> bool {
+        fence(Ordering::Acquire);
+        self.0.load(Ordering::Relaxed) != start
+    }
+
+    /// Spins until the other writers are done, and starts a write.
+    pub fn begin_write(&self) -> SeqWrite {
+        let mut s = self.0.load(Ordering::Relaxed);
+        loop {
+            if s & 1 == 1 {
+                relax::relax();
+                s = self.0.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.0.compare_exchange_weak(
+                s,
+                s.wrapping_add(1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Result::Ok(_) => break,
+                Result::Err(x) => s = x,
+            }
+        }
+        fence(Ordering::Release);
+        SeqWrite(s)
+    }
+
+    /// Starts a write if no other write is in progress.
+    pub fn try_begin_write(&self) -> Option<SeqWrite> {
+        let s = self.0.load(Ordering::Relaxed);
+        if s & 1 == 1 {
+            return Option::None;
+        }
+        let r = self.0.compare_exchange(
+            s,
+            s.wrapping_add(1),
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        );
+        r.ok()?;
+        fence(Ordering::Release);
+        Option::Some(SeqWrite(s))
+    }
+
+    /// Ends a write, making the readers that overlapped it retry.
+    pub fn end_write(&self, write: SeqWrite) {
+        self.0.store(write.0.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Ends a write that did not modify the data, restoring the sequence so
+    /// that overlapping readers need not retry.
+    pub fn cancel_write(&self, write: SeqWrite) {
+        self.0.store(write.0, Ordering::Release);
+    }
+}
+
+/// A write on a `SeqCount` ended when dropped, even if the writer panics,
+/// unless it is cancelled.
+pub(crate) struct WriteGuard<'a>(&'a SeqCount, Option<SeqWrite>);
+
+impl<'a> WriteGuard<'a> {
+    /// Spins until the other writers are done, and starts a write.
+    pub(crate) fn begin(seq: &'a SeqCount) -> Self {
+        WriteGuard(seq, Option::Some(seq.begin_write()))
+    }
+
+    /// Ends the write as one that did not modify the data.
+    pub(crate) fn cancel(mut self) {
+        if let Option::Some(write) = self.1.take() {
+            self.0.cancel_write(write)
+        }
+    }
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        if let Option::Some(write) = self.1.take() {
+            self.0.end_write(write)
+        }
+    }
+}
+
+impl Default for SeqCount {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SeqCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SeqCount").field(&self.sequence()).finish()
+    }
+}
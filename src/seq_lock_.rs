@@ -0,0 +1,110 @@
+﻿use core::{cell::UnsafeCell, fmt, mem::MaybeUninit, ptr};
+use crate::{relax, seq_count_::WriteGuard, SeqCount};
+
+/// A sequence lock protecting a `Copy` value, for small read-mostly data such
+/// as timestamps and configuration words.
+///
+/// Readers never block writers: they copy the value optimistically and retry
+/// if a writer was active meanwhile, which they tell from an odd sequence or
+/// a sequence changed during the copy. Writers serialize on making the
+/// sequence odd. See `SeqCount` to protect data laid out otherwise.
+///
+/// # Example
+///
+/// ```
+/// use atomex::SeqLock;
+///
+/// let clock = SeqLock::new((0u64, 0u32));
+/// clock.write((1, 500));
+/// assert_eq!(clock.read(), (1, 500));
+/// assert_eq!(clock.try_read(), Some((1, 500)));
+/// assert_eq!(clock.sequence(), 2);
+/// ```
+pub struct SeqLock<T: Copy> {
+    seq: SeqCount,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+impl<T: Copy> SeqLock<T> {
+    pub const fn new(data: T) -> Self {
+        SeqLock {
+            seq: SeqCount::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    /// Returns a mutable reference to the value, which needs no locking
+    /// since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// The current sequence, which is odd while a write is in progress and
+    /// grows by two with every write.
+    #[inline(always)]
+    pub fn sequence(&self) -> usize {
+        self.seq.sequence()
+    }
+
+    /// Copies the value, retrying until the copy is not torn by a writer.
+    pub fn read(&self) -> T {
+        loop {
+            if let Option::Some(v) = self.try_read() {
+                break v;
+            }
+            relax::relax();
+        }
+    }
+
+    /// Copies the value once, returning `None` if a writer was active.
+    pub fn try_read(&self) -> Option<T> {
+        let s = self.seq.try_read_begin()?;
+        // The copy may race with a writer, so it is kept uninitialized, and
+        // discarded below without being looked at.
+        let v = unsafe {
+            ptr::read_volatile(self.data.get().cast::<MaybeUninit<T>>())
+        };
+        if self.seq.read_retry(s) {
+            Option::None
+        } else {
+            // Safety: no writer was active, so the copy is not torn.
+            Option::Some(unsafe { v.assume_init() })
+        }
+    }
+
+    /// Overwrites the value.
+    pub fn write(&self, data: T) {
+        let _ = self.update(|_| data);
+    }
+
+    /// Replaces the value with the one computed by `f` from the current
+    /// value, excluding other writers, and returns the previous value.
+    pub fn update(&self, f: impl FnOnce(T) -> T) -> T {
+        // Ends the write even if `f` panics.
+        let write = WriteGuard::begin(&self.seq);
+        let prev = unsafe { ptr::read_volatile(self.data.get()) };
+        let next = f(prev);
+        unsafe { ptr::write_volatile(self.data.get(), next) };
+        drop(write);
+        prev
+    }
+}
+
+impl<T: Copy + Default> Default for SeqLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for SeqLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SeqLock").field(&self.read()).finish()
+    }
+}
@@ -0,0 +1,212 @@
+﻿//! Implementations of the `serde` traits for the counters, the flags and the
+//! metrics, such as to persist them or to report snapshots of them.
+//!
+//! A wrapper is serialized as the value it loads, and deserialized into a
+//! fresh cell holding that value. Only the owned wrappers are deserialized,
+//! since the borrowed ones have no cell to deserialize into.
+//!
+//! ```
+//! use atomex::{AtomicCountOwned, Gauge, StatsCell};
+//!
+//! let hits = AtomicCountOwned::<u32>::default();
+//! hits.add(3);
+//! let json = serde_json::to_string(&hits).unwrap();
+//! assert_eq!(json, "3");
+//! let hits: AtomicCountOwned<u32> = serde_json::from_str(&json).unwrap();
+//! assert_eq!(hits.val(), 3);
+//!
+//! let in_use = Gauge::<usize>::new();
+//! in_use.set(4);
+//! in_use.sub(1);
+//! let json = serde_json::to_string(&in_use).unwrap();
+//! assert_eq!(json, r#"{"value":3,"max_seen":4}"#);
+//! let in_use: Gauge<usize> = serde_json::from_str(&json).unwrap();
+//! assert_eq!((in_use.get(), in_use.max_seen()), (3, 4));
+//!
+//! let latency = StatsCell::new();
+//! latency.record(120);
+//! latency.record(80);
+//! let json = serde_json::to_string(&latency).unwrap();
+//! let copy: StatsCell = serde_json::from_str(&json).unwrap();
+//! assert_eq!(copy.snapshot(), latency.snapshot());
+//! ```
+
+use core::borrow::BorrowMut;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    fetch, AtomicCount, AtomicFlags, Gauge, ShardedCount, StatsCell,
+    StatsSnapshot, TrAtomicCell, TrAtomicData, TrCmpxchOrderings,
+    TrCountOrderings,
+};
+
+impl<V, B, O> Serialize for AtomicCount<V, B, O>
+where
+    V: TrAtomicData + funty::Integral + Serialize,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    B: BorrowMut<<V as TrAtomicData>::AtomicCell>,
+    O: TrCountOrderings,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.val().serialize(serializer)
+    }
+}
+
+impl<'de, V, O> Deserialize<'de>
+for AtomicCount<V, <V as TrAtomicData>::AtomicCell, O>
+where
+    V: TrAtomicData + funty::Integral + Deserialize<'de>,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let val = V::deserialize(deserializer)?;
+        Result::Ok(AtomicCount::new(TrAtomicCell::new(val)))
+    }
+}
+
+impl<T, B, O> Serialize for AtomicFlags<T, B, O>
+where
+    T: TrAtomicData + Copy + Serialize,
+    <T as TrAtomicData>::AtomicCell: TrAtomicCell<Value = T>,
+    B: BorrowMut<<T as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value().serialize(serializer)
+    }
+}
+
+impl<'de, T, O> Deserialize<'de>
+for AtomicFlags<T, <T as TrAtomicData>::AtomicCell, O>
+where
+    T: TrAtomicData + Copy + Deserialize<'de>,
+    <T as TrAtomicData>::AtomicCell: TrAtomicCell<Value = T>,
+    O: TrCmpxchOrderings,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let val = T::deserialize(deserializer)?;
+        Result::Ok(AtomicFlags::new(TrAtomicCell::new(val)))
+    }
+}
+
+/// The sum of the shards is serialized, and deserialized into the first
+/// shard.
+impl<V, const N: usize, O> Serialize for ShardedCount<V, N, O>
+where
+    V: TrAtomicData + funty::Integral + Serialize,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.sum().serialize(serializer)
+    }
+}
+
+impl<'de, V, const N: usize, O> Deserialize<'de> for ShardedCount<V, N, O>
+where
+    V: TrAtomicData + funty::Integral + Deserialize<'de>,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let sum = V::deserialize(deserializer)?;
+        let count = ShardedCount::new();
+        count.add(0, sum);
+        Result::Ok(count)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Gauge")]
+struct GaugeRepr<V> {
+    value: V,
+    max_seen: V,
+}
+
+impl<V> Serialize for Gauge<V>
+where
+    V: TrAtomicData + funty::Unsigned + Serialize,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>
+        + fetch::Max<Value = V>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        GaugeRepr {
+            value: self.get(),
+            max_seen: self.max_seen(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, V> Deserialize<'de> for Gauge<V>
+where
+    V: TrAtomicData + funty::Unsigned + Deserialize<'de>,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>
+        + fetch::Max<Value = V>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = GaugeRepr::<V>::deserialize(deserializer)?;
+        let gauge = Gauge::new();
+        // The highest amount is tracked by setting it first.
+        gauge.set(repr.max_seen);
+        gauge.set(repr.value);
+        Result::Ok(gauge)
+    }
+}
+
+/// The statistics are serialized as their `StatsSnapshot`.
+impl Serialize for StatsCell {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.snapshot().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StatsCell {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        StatsSnapshot::deserialize(deserializer).map(StatsCell::from)
+    }
+}
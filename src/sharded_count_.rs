@@ -0,0 +1,127 @@
+﻿use core::{
+    array,
+    fmt::{self, Debug},
+};
+use crate::{
+    fetch, AtomicCountOwned, CachePadded, CountOrderings,
+    TrAtomicCell, TrAtomicData, TrCountOrderings,
+};
+
+/// A counter split into `N` cache-padded shards, for counters updated by many
+/// threads at once.
+///
+/// Every update goes to the shard selected by a caller-provided hint, such as
+/// a thread or CPU index, and reading the count sums all the shards. Shards
+/// wrap around independently, so the sum is exact as long as the total count
+/// itself does not overflow.
+///
+/// # Example
+///
+/// ```
+/// use atomex::ShardedCount;
+///
+/// let hits = ShardedCount::<usize, 4>::new();
+/// hits.inc(0);
+/// hits.add(1, 3);
+/// hits.dec(7);
+/// assert_eq!(hits.sum(), 3);
+/// ```
+pub struct ShardedCount<V, const N: usize, O = CountOrderings>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    shards: [CachePadded<AtomicCountOwned<V, O>>; N],
+}
+
+impl<V, const N: usize, O> ShardedCount<V, N, O>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    pub fn new() -> Self {
+        const { assert!(N > 0, "[ShardedCount] no shard") };
+        ShardedCount {
+            shards: array::from_fn(|_| {
+                CachePadded::new(AtomicCountOwned::new(
+                    TrAtomicCell::new(V::ZERO),
+                ))
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub const fn shard_count(&self) -> usize {
+        N
+    }
+
+    #[inline(always)]
+    pub fn inc(&self, hint: usize) -> V {
+        self.add(hint, V::ONE)
+    }
+
+    /// Adds `val` to the shard selected by `hint`, and returns the previous
+    /// value of that shard.
+    pub fn add(&self, hint: usize, val: V) -> V {
+        self.shard(hint).wrapping_add(val)
+    }
+
+    #[inline(always)]
+    pub fn dec(&self, hint: usize) -> V {
+        self.sub(hint, V::ONE)
+    }
+
+    /// Subtracts `val` from the shard selected by `hint`, and returns the
+    /// previous value of that shard.
+    pub fn sub(&self, hint: usize, val: V) -> V {
+        self.shard(hint).wrapping_sub(val)
+    }
+
+    /// Sums the shards.
+    ///
+    /// The result is not a snapshot: updates racing with the summing may or
+    /// may not be included.
+    pub fn sum(&self) -> V {
+        self.shards
+            .iter()
+            .fold(V::ZERO, |acc, s| acc.wrapping_add(s.val()))
+    }
+
+    fn shard(&self, hint: usize) -> &AtomicCountOwned<V, O> {
+        // Fibonacci hashing spreads consecutive hints over the shards.
+        let h = hint.wrapping_mul(0x9E37_79B9_7F4A_7C15_u64 as usize);
+        &self.shards[(h >> (usize::BITS / 2)) % N]
+    }
+}
+
+impl<V, const N: usize, O> Default for ShardedCount<V, N, O>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V, const N: usize, O> Debug for ShardedCount<V, N, O>
+where
+    V: TrAtomicData + funty::Integral,
+    <V as TrAtomicData>::AtomicCell: TrAtomicCell<Value = V>
+        + fetch::Add<Value = V>
+        + fetch::Sub<Value = V>,
+    O: TrCountOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ShardedCount").field(&self.sum()).finish()
+    }
+}
@@ -0,0 +1,169 @@
+﻿use core::{fmt, mem};
+use crate::{
+    atomic_bitmap_::claim_lowest_zero,
+    atomics_::AtomicUsize,
+    AcqRelOrderings, AtomicFlags,
+};
+
+type Word = AtomicFlags<usize, AtomicUsize, AcqRelOrderings>;
+
+/// A registry of `N` slots which can be claimed and released concurrently,
+/// tracked in a bitmap of `WORDS` words, each bit set for a claimed slot.
+///
+/// `WORDS` must be at least `N` divided by `usize::BITS`, rounded up. A claim
+/// looks for a clear bit word by word and sets it with a single exchange, so
+/// it only loops while other claims or releases hit the same word.
+///
+/// # Example
+///
+/// ```
+/// use atomex::SlotRegistry;
+///
+/// static CONNECTIONS: SlotRegistry<3> = SlotRegistry::new();
+///
+/// let a = CONNECTIONS.claim().unwrap();
+/// let b = CONNECTIONS.claim().unwrap();
+/// let c = CONNECTIONS.claim().unwrap();
+/// assert_eq!([a.index(), b.index(), c.index()], [0, 1, 2]);
+/// assert!(CONNECTIONS.claim().is_none());
+///
+/// drop(b);
+/// assert_eq!(CONNECTIONS.claimed_count(), 2);
+/// assert_eq!(CONNECTIONS.claim().map(|s| s.index()), Some(1));
+/// ```
+pub struct SlotRegistry<const N: usize, const WORDS: usize = 1> {
+    words: [Word; WORDS],
+}
+
+impl<const N: usize, const WORDS: usize> SlotRegistry<N, WORDS> {
+    const BITS: usize = usize::BITS as usize;
+
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                N <= WORDS * usize::BITS as usize,
+                "SlotRegistry needs more bitmap words",
+            )
+        };
+        let mut words =
+            [const { AtomicFlags::new(AtomicUsize::new(0)) }; WORDS];
+        // The bits past the last slot are claimed once and for all.
+        let mut k = N / Self::BITS;
+        while k < WORDS {
+            let first = k * Self::BITS;
+            let padding = if first >= N {
+                !0
+            } else {
+                !0 << (N - first)
+            };
+            words[k] = AtomicFlags::new(AtomicUsize::new(padding));
+            k += 1;
+        }
+        SlotRegistry { words }
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Claims a free slot, or returns `None` if all of them are claimed.
+    pub fn claim(&self) -> Option<SlotGuard<'_, N, WORDS>> {
+        for (k, word) in self.words.iter().enumerate() {
+            let r = claim_lowest_zero::<AcqRelOrderings>(word.as_ref());
+            if let Option::Some(bit) = r {
+                let index = k * Self::BITS + bit;
+                return Option::Some(SlotGuard {
+                    registry: self,
+                    index,
+                });
+            }
+        }
+        Option::None
+    }
+
+    /// Returns `true` if the slot at `index` is claimed right now.
+    pub fn is_claimed(&self, index: usize) -> bool {
+        assert!(index < N, "[SlotRegistry::is_claimed] index out of range");
+        let w = self.words[index / Self::BITS].value();
+        w & (1 << (index % Self::BITS)) != 0
+    }
+
+    /// The number of claimed slots, which may be outdated as soon as it is
+    /// returned.
+    pub fn claimed_count(&self) -> usize {
+        let total: usize = self
+            .words
+            .iter()
+            .map(|w| w.value().count_ones() as usize)
+            .sum();
+        total - (WORDS * Self::BITS - N)
+    }
+
+    /// Releases the slot at `index`, kept claimed by `SlotGuard::into_index`.
+    ///
+    /// # Safety
+    ///
+    /// The slot must have been kept claimed by `into_index`, and not been
+    /// released since.
+    pub unsafe fn release(&self, index: usize) {
+        assert!(index < N, "[SlotRegistry::release] index out of range");
+        let bit = 1 << (index % Self::BITS);
+        let taken = self.words[index / Self::BITS].fetch_take(bit);
+        debug_assert!(taken != 0, "[SlotRegistry::release] slot not claimed");
+    }
+}
+
+impl<const N: usize, const WORDS: usize> Default for SlotRegistry<N, WORDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const WORDS: usize> fmt::Debug
+    for SlotRegistry<N, WORDS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlotRegistry")
+            .field("capacity", &N)
+            .field("claimed_count", &self.claimed_count())
+            .finish()
+    }
+}
+
+/// A slot claimed from a `SlotRegistry`, released when dropped.
+#[must_use = "the slot is released at once if the guard is dropped"]
+pub struct SlotGuard<'a, const N: usize, const WORDS: usize = 1> {
+    registry: &'a SlotRegistry<N, WORDS>,
+    index: usize,
+}
+
+impl<const N: usize, const WORDS: usize> SlotGuard<'_, N, WORDS> {
+    #[inline(always)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Keeps the slot claimed past the guard, to be released with
+    /// `SlotRegistry::release`.
+    pub fn into_index(self) -> usize {
+        let index = self.index;
+        mem::forget(self);
+        index
+    }
+}
+
+impl<const N: usize, const WORDS: usize> Drop for SlotGuard<'_, N, WORDS> {
+    fn drop(&mut self) {
+        // Claimed by this guard.
+        unsafe { self.registry.release(self.index) }
+    }
+}
+
+impl<const N: usize, const WORDS: usize> fmt::Debug
+    for SlotGuard<'_, N, WORDS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlotGuard").field("index", &self.index).finish()
+    }
+}
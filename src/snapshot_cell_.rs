@@ -0,0 +1,179 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt, mem, ptr,
+    sync::atomic::{fence, Ordering},
+};
+use crate::{atomics_::AtomicUsize, relax};
+
+/// A small `Copy` struct of several words, such as shared settings, read as
+/// a consistent snapshot without locking.
+///
+/// The value is kept in two copies. A writer fills the copy the readers are
+/// not directed to, then publishes it by bumping the sequence, so that a
+/// reader only retries if two writes start while it is copying. Writers are
+/// serialized with each other.
+///
+/// Unlike `SeqLock`, readers never wait for a write in progress.
+///
+/// # Example
+///
+/// ```
+/// use atomex::SnapshotCell;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Settings {
+///     timeout_ms: u64,
+///     retries: u32,
+///     verbose: bool,
+/// }
+///
+/// let settings = SnapshotCell::new(Settings {
+///     timeout_ms: 100,
+///     retries: 3,
+///     verbose: false,
+/// });
+/// settings.update(|s| Settings { retries: 5, ..s });
+/// assert_eq!(settings.read().retries, 5);
+/// assert_eq!(settings.version(), 1);
+/// ```
+pub struct SnapshotCell<T: Copy> {
+    /// Twice the number of writes done, plus one while a write is in
+    /// progress.
+    seq: AtomicUsize,
+    copies: [UnsafeCell<T>; 2],
+}
+
+unsafe impl<T: Copy + Send> Send for SnapshotCell<T> {}
+unsafe impl<T: Copy + Send> Sync for SnapshotCell<T> {}
+
+impl<T: Copy> SnapshotCell<T> {
+    pub const fn new(value: T) -> Self {
+        SnapshotCell {
+            seq: AtomicUsize::new(0),
+            copies: [UnsafeCell::new(value), UnsafeCell::new(value)],
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        let [a, b] = self.copies;
+        let k = self.seq.into_inner() >> 1;
+        if k & 1 == 0 {
+            a.into_inner()
+        } else {
+            b.into_inner()
+        }
+    }
+
+    /// Returns a mutable reference to the value, which needs no
+    /// synchronization since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        let k = *self.seq.get_mut() >> 1;
+        self.copies[k & 1].get_mut()
+    }
+
+    /// The number of writes done so far.
+    #[inline(always)]
+    pub fn version(&self) -> usize {
+        self.seq.load(Ordering::Acquire) >> 1
+    }
+
+    /// Copies the value.
+    pub fn read(&self) -> T {
+        loop {
+            if let Option::Some(v) = self.try_read() {
+                break v;
+            }
+            relax::relax();
+        }
+    }
+
+    /// Copies the value once, returning `None` if the copy was overwritten
+    /// meanwhile.
+    pub fn try_read(&self) -> Option<T> {
+        let s = self.seq.load(Ordering::Acquire);
+        let k = s >> 1;
+        // The copy may race with the second write after `k`, so it is kept
+        // uninitialized, and discarded below without being looked at.
+        let v = unsafe {
+            let copy = self.copies[k & 1].get();
+            ptr::read_volatile(copy.cast::<mem::MaybeUninit<T>>())
+        };
+        fence(Ordering::Acquire);
+        let overwriting = (k + 1).wrapping_shl(1) | 1;
+        let now = self.seq.load(Ordering::Relaxed);
+        if now.wrapping_sub(s) >= overwriting.wrapping_sub(s) {
+            Option::None
+        } else {
+            // Safety: the copy was not overwritten, so it is not torn.
+            Option::Some(unsafe { v.assume_init() })
+        }
+    }
+
+    /// Overwrites the value.
+    pub fn write(&self, value: T) {
+        let _ = self.update(|_| value);
+    }
+
+    /// Replaces the value with the one computed by `f` from the current
+    /// value, excluding other writers, and returns the previous value.
+    pub fn update(&self, f: impl FnOnce(T) -> T) -> T {
+        let s = self.begin_write();
+        let cancel = CancelGuard(&self.seq, s);
+        let k = s >> 1;
+        // Writers are excluded by the sequence, and readers are directed to
+        // the other copy until the write is published.
+        let prev = unsafe { *self.copies[k & 1].get() };
+        let next = f(prev);
+        unsafe { ptr::write_volatile(self.copies[(k + 1) & 1].get(), next) };
+        mem::forget(cancel);
+        self.seq.store(s.wrapping_add(2), Ordering::Release);
+        prev
+    }
+
+    /// Spins until the other writers are done, and makes the sequence odd.
+    fn begin_write(&self) -> usize {
+        let mut s = self.seq.load(Ordering::Relaxed);
+        loop {
+            if s & 1 == 1 {
+                relax::relax();
+                s = self.seq.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.seq.compare_exchange_weak(
+                s,
+                s.wrapping_add(1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Result::Ok(_) => break,
+                Result::Err(x) => s = x,
+            }
+        }
+        // Orders the odd sequence before the copy is overwritten, for the
+        // readers still copying it since two writes.
+        fence(Ordering::Release);
+        s
+    }
+}
+
+impl<T: Copy + Default> Default for SnapshotCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for SnapshotCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SnapshotCell").field(&self.read()).finish()
+    }
+}
+
+/// Restores the sequence if the update closure panics, before anything was
+/// written.
+struct CancelGuard<'a>(&'a AtomicUsize, usize);
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(self.1, Ordering::Release);
+    }
+}
@@ -0,0 +1,316 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomics_::AtomicBool,
+    AtomicFlags, LocksOrderings,
+    TrAtomicCell, TrBackoff, TrCmpxchOrderings,
+};
+
+/// A test-and-set spin lock without data, i.e. only the locking protocol of
+/// `SpinLock`.
+///
+/// The lock is always acquired with `Acquire` and released with `Release`,
+/// whatever `O`, which only orders the loads observing the lock, such as
+/// `is_locked`.
+pub struct RawSpinLock<O = LocksOrderings>(AtomicFlags<bool, AtomicBool, O>)
+where
+    O: TrCmpxchOrderings;
+
+impl<O> RawSpinLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    pub const fn new() -> Self {
+        RawSpinLock(AtomicFlags::new(AtomicBool::new(false)))
+    }
+
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.0.value()
+    }
+
+    /// Acquires the lock if it is free, returning whether it was acquired.
+    pub fn try_lock(&self) -> bool {
+        TrAtomicCell::compare_exchange(
+            self.0.as_ref(),
+            false,
+            true,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_ok()
+    }
+
+    /// The number of attempts before sleeping in `lock`.
+    #[cfg(feature = "futex")]
+    const SPIN_LIMIT: usize = 100;
+
+    /// Acquires the lock, spinning until it is free.
+    ///
+    /// Under the `futex` feature, the thread sleeps once it has spun for a
+    /// while, until the lock is released.
+    #[inline(always)]
+    pub fn lock(&self) {
+        #[cfg(feature = "futex")]
+        {
+            for _ in 0..Self::SPIN_LIMIT {
+                if self.try_lock() {
+                    return;
+                }
+                crate::relax::relax();
+            }
+            let addr = self.0.as_ref() as *const _ as usize;
+            while !self.try_lock() {
+                crate::futex_::block_until(addr, || !self.is_locked());
+            }
+        }
+        #[cfg(not(feature = "futex"))]
+        self.lock_with_backoff(crate::NoBackoff)
+    }
+
+    /// Acquires the lock, waiting according to `backoff` whenever the lock
+    /// is found held.
+    pub fn lock_with_backoff(&self, mut backoff: impl TrBackoff) {
+        loop {
+            // Spin on loads rather than on compare-exchanges, to keep the
+            // cache line shared while the lock is held.
+            let _ = self.0.spin_while(|locked| {
+                if locked {
+                    backoff.backoff();
+                }
+                locked
+            });
+            let r = TrAtomicCell::compare_exchange_weak(
+                self.0.as_ref(),
+                false,
+                true,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            );
+            if r.is_ok() {
+                break;
+            }
+        }
+    }
+
+    /// Releases the lock.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held by the caller.
+    pub unsafe fn unlock(&self) {
+        TrAtomicCell::store(self.0.as_ref(), false, Ordering::Release);
+        self.0.wake_waiters();
+    }
+}
+
+impl<O> Default for RawSpinLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> fmt::Debug for RawSpinLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawSpinLock")
+            .field("locked", &self.is_locked())
+            .finish()
+    }
+}
+
+/// A spin lock protecting a value of `T`, for short critical sections where
+/// blocking is not available or not worth it.
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use atomex::SpinLock;
+///
+/// let lock = Arc::new(SpinLock::<usize>::new(0));
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let lock = lock.clone();
+///         thread::spawn(move || {
+///             for _ in 0..100 {
+///                 *lock.lock() += 1;
+///             }
+///         })
+///     })
+///     .collect();
+/// workers.into_iter().for_each(|w| w.join().unwrap());
+/// assert_eq!(*lock.lock(), 400);
+/// assert!(lock.try_lock().is_some());
+/// ```
+pub struct SpinLock<T: ?Sized, O = LocksOrderings>
+where
+    O: TrCmpxchOrderings,
+{
+    raw: RawSpinLock<O>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, O> Send for SpinLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+unsafe impl<T: ?Sized + Send, O> Sync for SpinLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+impl<T, O> SpinLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    pub const fn new(data: T) -> Self {
+        SpinLock {
+            raw: RawSpinLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, O> SpinLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T, O>> {
+        if self.raw.try_lock() {
+            Option::Some(SpinLockGuard::new(self))
+        } else {
+            Option::None
+        }
+    }
+
+    pub fn lock(&self) -> SpinLockGuard<'_, T, O> {
+        self.raw.lock();
+        SpinLockGuard::new(self)
+    }
+
+    pub fn lock_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+    ) -> SpinLockGuard<'_, T, O> {
+        self.raw.lock_with_backoff(backoff);
+        SpinLockGuard::new(self)
+    }
+
+    /// Returns a mutable reference to the value, which needs no locking
+    /// since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default, O> Default for SpinLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, O> fmt::Debug for SpinLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("SpinLock");
+        match self.try_lock() {
+            Option::Some(g) => d.field("data", &&*g),
+            Option::None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// The RAII guard of a `SpinLock`, releasing the lock when dropped.
+pub struct SpinLockGuard<'a, T: ?Sized, O = LocksOrderings>
+where
+    O: TrCmpxchOrderings,
+{
+    lock: &'a SpinLock<T, O>,
+}
+
+unsafe impl<T: ?Sized + Sync, O> Sync for SpinLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+impl<'a, T: ?Sized, O> SpinLockGuard<'a, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn new(lock: &'a SpinLock<T, O>) -> Self {
+        SpinLockGuard { lock }
+    }
+}
+
+impl<T: ?Sized, O> Deref for SpinLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, O> DerefMut for SpinLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, O> Drop for SpinLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn drop(&mut self) {
+        unsafe { self.lock.raw.unlock() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, O> fmt::Debug for SpinLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display, O> fmt::Display for SpinLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
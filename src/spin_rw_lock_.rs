@@ -0,0 +1,422 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::atomic::Ordering,
+};
+use crate::{
+    atomics_::AtomicUsize,
+    fetch, AtomicFlags, CmpxchResult, LocksOrderings, NoBackoff,
+    TrAtomicCell, TrBackoff, TrCmpxchOrderings,
+};
+
+/// A reader-writer spin lock without data, i.e. only the locking protocol of
+/// `SpinRwLock`.
+///
+/// The lock is a single word holding the writer bit and the number of
+/// readers. Readers are preferred: a writer waits until there is no reader.
+///
+/// The lock is always acquired with `Acquire` and released with `Release`,
+/// whatever `O`, which only orders the loads observing the lock, such as
+/// `reader_count`.
+pub struct RawSpinRwLock<O = LocksOrderings>(AtomicFlags<usize, AtomicUsize, O>)
+where
+    O: TrCmpxchOrderings;
+
+impl<O> RawSpinRwLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    const WRITER: usize = 1;
+    const READER: usize = 2;
+
+    pub const fn new() -> Self {
+        RawSpinRwLock(AtomicFlags::new(AtomicUsize::new(0)))
+    }
+
+    #[inline(always)]
+    pub fn is_write_locked(&self) -> bool {
+        self.0.value() & Self::WRITER != 0
+    }
+
+    /// The number of readers holding the lock.
+    #[inline(always)]
+    pub fn reader_count(&self) -> usize {
+        self.0.value() / Self::READER
+    }
+
+    /// Acquires a shared lock unless a writer holds the lock, returning
+    /// whether it was acquired.
+    pub fn try_read(&self) -> bool {
+        self.0
+            .try_spin_compare_exchange_weak_with(
+                Ordering::Acquire,
+                Ordering::Relaxed,
+                |v| v & Self::WRITER == 0,
+                Self::add_reader,
+            )
+            .is_succ()
+    }
+
+    #[inline(always)]
+    pub fn read(&self) {
+        self.read_with_backoff(NoBackoff)
+    }
+
+    /// Acquires a shared lock, waiting according to `backoff` whenever a
+    /// writer holds the lock.
+    pub fn read_with_backoff(&self, mut backoff: impl TrBackoff) {
+        loop {
+            let r = self.0.try_spin_compare_exchange_weak_with(
+                Ordering::Acquire,
+                Ordering::Relaxed,
+                |v| v & Self::WRITER == 0,
+                Self::add_reader,
+            );
+            if let CmpxchResult::Succ(_) = r {
+                break;
+            }
+            let _ = self.0.spin_while(|v| {
+                let write_locked = v & Self::WRITER != 0;
+                if write_locked {
+                    backoff.backoff();
+                }
+                write_locked
+            });
+        }
+    }
+
+    /// Releases a shared lock.
+    ///
+    /// # Safety
+    ///
+    /// A shared lock must be held by the caller.
+    pub unsafe fn read_unlock(&self) {
+        let _ = fetch::Sub::fetch_sub(
+            self.0.as_ref(),
+            Self::READER,
+            Ordering::Release,
+        );
+        self.0.wake_waiters();
+    }
+
+    /// Acquires the exclusive lock if nobody holds the lock, returning
+    /// whether it was acquired.
+    pub fn try_write(&self) -> bool {
+        TrAtomicCell::compare_exchange(
+            self.0.as_ref(),
+            0,
+            Self::WRITER,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_ok()
+    }
+
+    #[inline(always)]
+    pub fn write(&self) {
+        self.write_with_backoff(NoBackoff)
+    }
+
+    /// Acquires the exclusive lock, waiting according to `backoff` whenever
+    /// somebody holds the lock.
+    pub fn write_with_backoff(&self, mut backoff: impl TrBackoff) {
+        loop {
+            let _ = self.0.spin_while(|v| {
+                if v != 0 {
+                    backoff.backoff();
+                }
+                v != 0
+            });
+            let r = TrAtomicCell::compare_exchange_weak(
+                self.0.as_ref(),
+                0,
+                Self::WRITER,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            );
+            if r.is_ok() {
+                break;
+            }
+        }
+    }
+
+    /// Releases the exclusive lock.
+    ///
+    /// # Safety
+    ///
+    /// The exclusive lock must be held by the caller.
+    pub unsafe fn write_unlock(&self) {
+        // Readers never change the word while the writer bit is set.
+        TrAtomicCell::store(self.0.as_ref(), 0, Ordering::Release);
+        self.0.wake_waiters();
+    }
+
+    fn add_reader(v: usize) -> usize {
+        let Option::Some(x) = v.checked_add(Self::READER) else {
+            panic!("[RawSpinRwLock] too many readers")
+        };
+        x
+    }
+}
+
+impl<O> Default for RawSpinRwLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> fmt::Debug for RawSpinRwLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0.value();
+        f.debug_struct("RawSpinRwLock")
+            .field("writer", &(v & Self::WRITER != 0))
+            .field("readers", &(v / Self::READER))
+            .finish()
+    }
+}
+
+/// A reader-writer spin lock protecting a value of `T`, allowing either many
+/// readers or a single writer at a time.
+///
+/// # Example
+///
+/// ```
+/// use atomex::SpinRwLock;
+///
+/// let lock = SpinRwLock::<Vec<u8>>::new(vec![1]);
+/// {
+///     let r1 = lock.read();
+///     let r2 = lock.try_read().unwrap();
+///     assert_eq!(r1.len() + r2.len(), 2);
+///     assert!(lock.try_write().is_none());
+/// }
+/// lock.write().push(2);
+/// assert_eq!(*lock.read(), [1, 2]);
+/// ```
+pub struct SpinRwLock<T: ?Sized, O = LocksOrderings>
+where
+    O: TrCmpxchOrderings,
+{
+    raw: RawSpinRwLock<O>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, O> Send for SpinRwLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+unsafe impl<T: ?Sized + Send + Sync, O> Sync for SpinRwLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+impl<T, O> SpinRwLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    pub const fn new(data: T) -> Self {
+        SpinRwLock {
+            raw: RawSpinRwLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, O> SpinRwLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    pub fn try_read(&self) -> Option<SpinRwLockReadGuard<'_, T, O>> {
+        if self.raw.try_read() {
+            Option::Some(SpinRwLockReadGuard::new(self))
+        } else {
+            Option::None
+        }
+    }
+
+    pub fn read(&self) -> SpinRwLockReadGuard<'_, T, O> {
+        self.raw.read();
+        SpinRwLockReadGuard::new(self)
+    }
+
+    pub fn read_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+    ) -> SpinRwLockReadGuard<'_, T, O> {
+        self.raw.read_with_backoff(backoff);
+        SpinRwLockReadGuard::new(self)
+    }
+
+    pub fn try_write(&self) -> Option<SpinRwLockWriteGuard<'_, T, O>> {
+        if self.raw.try_write() {
+            Option::Some(SpinRwLockWriteGuard::new(self))
+        } else {
+            Option::None
+        }
+    }
+
+    pub fn write(&self) -> SpinRwLockWriteGuard<'_, T, O> {
+        self.raw.write();
+        SpinRwLockWriteGuard::new(self)
+    }
+
+    pub fn write_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+    ) -> SpinRwLockWriteGuard<'_, T, O> {
+        self.raw.write_with_backoff(backoff);
+        SpinRwLockWriteGuard::new(self)
+    }
+
+    /// Returns a mutable reference to the value, which needs no locking
+    /// since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default, O> Default for SpinRwLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, O> fmt::Debug for SpinRwLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("SpinRwLock");
+        match self.try_read() {
+            Option::Some(g) => d.field("data", &&*g),
+            Option::None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// The RAII guard of a shared lock of `SpinRwLock`.
+pub struct SpinRwLockReadGuard<'a, T: ?Sized, O = LocksOrderings>
+where
+    O: TrCmpxchOrderings,
+{
+    lock: &'a SpinRwLock<T, O>,
+}
+
+unsafe impl<T: ?Sized + Sync, O> Sync for SpinRwLockReadGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+impl<'a, T: ?Sized, O> SpinRwLockReadGuard<'a, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn new(lock: &'a SpinRwLock<T, O>) -> Self {
+        SpinRwLockReadGuard { lock }
+    }
+}
+
+impl<T: ?Sized, O> Deref for SpinRwLockReadGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, O> Drop for SpinRwLockReadGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn drop(&mut self) {
+        unsafe { self.lock.raw.read_unlock() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, O> fmt::Debug for SpinRwLockReadGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// The RAII guard of the exclusive lock of `SpinRwLock`.
+pub struct SpinRwLockWriteGuard<'a, T: ?Sized, O = LocksOrderings>
+where
+    O: TrCmpxchOrderings,
+{
+    lock: &'a SpinRwLock<T, O>,
+}
+
+unsafe impl<T: ?Sized + Sync, O> Sync for SpinRwLockWriteGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+impl<'a, T: ?Sized, O> SpinRwLockWriteGuard<'a, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn new(lock: &'a SpinRwLock<T, O>) -> Self {
+        SpinRwLockWriteGuard { lock }
+    }
+}
+
+impl<T: ?Sized, O> Deref for SpinRwLockWriteGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, O> DerefMut for SpinRwLockWriteGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, O> Drop for SpinRwLockWriteGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn drop(&mut self) {
+        unsafe { self.lock.raw.write_unlock() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, O> fmt::Debug for SpinRwLockWriteGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
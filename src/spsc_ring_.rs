@@ -0,0 +1,217 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    sync::atomic::Ordering,
+};
+use crate::{atomics_::AtomicUsize, CachePadded};
+
+/// The position owned by one side of an `SpscRing`, next to that side's
+/// cached copy of the other side's position.
+struct Side {
+    pos: AtomicUsize,
+    /// The other side's position as last loaded, only used by this side.
+    cached: UnsafeCell<usize>,
+}
+
+impl Side {
+    const fn new() -> Self {
+        Side {
+            pos: AtomicUsize::new(0),
+            cached: UnsafeCell::new(0),
+        }
+    }
+}
+
+/// A wait-free single-producer single-consumer ring of capacity `N`, which
+/// must be a power of two.
+///
+/// Only the two positions are atomic. Each side keeps a cached copy of the
+/// other side's position and only loads it again when the cached one says
+/// the ring is full (or empty), which keeps the positions' cache lines from
+/// bouncing between the cores at every operation.
+///
+/// # Example
+///
+/// ```
+/// use atomex::SpscRing;
+///
+/// let mut ring = SpscRing::<u32, 4>::new();
+/// let (mut tx, mut rx) = ring.split();
+/// std::thread::scope(|s| {
+///     s.spawn(move || {
+///         for i in 0..100 {
+///             while tx.try_push(i).is_err() {}
+///         }
+///     });
+///     for i in 0..100 {
+///         let v = loop {
+///             if let Some(v) = rx.try_pop() {
+///                 break v;
+///             }
+///         };
+///         assert_eq!(v, i);
+///     }
+/// });
+/// assert!(ring.is_empty());
+/// ```
+pub struct SpscRing<T, const N: usize> {
+    /// The consumer's side.
+    head: CachePadded<Side>,
+    /// The producer's side.
+    tail: CachePadded<Side>,
+    slots: [UnsafeCell<MaybeUninit<T>>; N],
+}
+
+unsafe impl<T: Send, const N: usize> Send for SpscRing<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for SpscRing<T, N> {}
+
+impl<T, const N: usize> SpscRing<T, N> {
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                N.is_power_of_two(),
+                "SpscRing requires a power of two capacity",
+            )
+        };
+        SpscRing {
+            head: CachePadded::new(Side::new()),
+            tail: CachePadded::new(Side::new()),
+            slots: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+        }
+    }
+
+    /// Splits the ring into its producer and consumer handles.
+    pub fn split(
+        &mut self,
+    ) -> (SpscProducer<'_, T, N>, SpscConsumer<'_, T, N>) {
+        let ring = &*self;
+        (SpscProducer(ring), SpscConsumer(ring))
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The number of values in the ring, which may be outdated as soon as it
+    /// is returned.
+    pub fn len(&self) -> usize {
+        let head = self.head.pos.load(Ordering::Acquire);
+        let tail = self.tail.pos.load(Ordering::Acquire);
+        tail.wrapping_sub(head).min(N)
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pushes `value` at the back, or gives it back if the ring is full.
+    ///
+    /// # Safety
+    ///
+    /// Only one thread may push into the ring at a time.
+    pub unsafe fn try_push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.pos.load(Ordering::Relaxed);
+        let cached = self.tail.cached.get();
+        if tail.wrapping_sub(unsafe { *cached }) == N {
+            unsafe { *cached = self.head.pos.load(Ordering::Acquire) };
+            if tail.wrapping_sub(unsafe { *cached }) == N {
+                return Result::Err(value);
+            }
+        }
+        // The consumer does not read the slot before the new tail is seen.
+        unsafe { (*self.slots[tail % N].get()).write(value) };
+        self.tail.pos.store(tail.wrapping_add(1), Ordering::Release);
+        Result::Ok(())
+    }
+
+    /// Pops the value at the front, or returns `None` if the ring is empty.
+    ///
+    /// # Safety
+    ///
+    /// Only one thread may pop from the ring at a time.
+    pub unsafe fn try_pop(&self) -> Option<T> {
+        let head = self.head.pos.load(Ordering::Relaxed);
+        let cached = self.head.cached.get();
+        if unsafe { *cached } == head {
+            unsafe { *cached = self.tail.pos.load(Ordering::Acquire) };
+            if unsafe { *cached } == head {
+                return Option::None;
+            }
+        }
+        // The producer does not write the slot before the new head is seen.
+        let value = unsafe { (*self.slots[head % N].get()).assume_init_read() };
+        self.head.pos.store(head.wrapping_add(1), Ordering::Release);
+        Option::Some(value)
+    }
+}
+
+impl<T, const N: usize> Default for SpscRing<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for SpscRing<T, N> {
+    fn drop(&mut self) {
+        // Exclusive access, so both sides are this thread.
+        while unsafe { self.try_pop() }.is_some() {}
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for SpscRing<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpscRing")
+            .field("capacity", &N)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// The pushing half of a split `SpscRing`.
+pub struct SpscProducer<'a, T, const N: usize>(&'a SpscRing<T, N>);
+
+unsafe impl<T: Send, const N: usize> Send for SpscProducer<'_, T, N> {}
+
+impl<T, const N: usize> SpscProducer<'_, T, N> {
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        // The only producer, as the ring is borrowed by the split.
+        unsafe { self.0.try_push(value) }
+    }
+
+    #[inline(always)]
+    pub fn is_full(&self) -> bool {
+        self.0.len() == N
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for SpscProducer<'_, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SpscProducer").field(&self.0).finish()
+    }
+}
+
+/// The popping half of a split `SpscRing`.
+pub struct SpscConsumer<'a, T, const N: usize>(&'a SpscRing<T, N>);
+
+unsafe impl<T: Send, const N: usize> Send for SpscConsumer<'_, T, N> {}
+
+impl<T, const N: usize> SpscConsumer<'_, T, N> {
+    pub fn try_pop(&mut self) -> Option<T> {
+        // The only consumer, as the ring is borrowed by the split.
+        unsafe { self.0.try_pop() }
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for SpscConsumer<'_, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SpscConsumer").field(&self.0).finish()
+    }
+}
@@ -0,0 +1,177 @@
+﻿use core::{
+    borrow::BorrowMut,
+    cell::Cell,
+    fmt::{self, Debug},
+};
+use crate::{
+    AtomicFlags, CmpxchResult, StrictOrderings,
+    TrAtomicCell, TrAtomicData, TrCmpxchOrderings,
+};
+
+/// The trait for enumerated states that can be stored in a `StateCell`.
+pub trait TrAtomicState: Copy + Eq {
+    /// The integer representation stored in the atomic cell.
+    type Repr: TrAtomicData + Copy + Eq;
+
+    fn into_repr(self) -> Self::Repr;
+
+    fn try_from_repr(repr: Self::Repr) -> Option<Self>;
+
+    /// The table of allowed transitions, returns `true` if the state is
+    /// allowed to change from `from` into `to`.
+    fn can_transit(from: Self, to: Self) -> bool;
+}
+
+/// An atomic state machine over an enumerated state type.
+///
+/// Every transition is checked against `TrAtomicState::can_transit`, and a
+/// disallowed one is rejected as `CmpxchResult::Unexpected` without touching
+/// the cell.
+///
+/// # Example
+///
+/// ```
+/// use atomex::{StateCell, TrAtomicState};
+///
+/// #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// enum Phase { Idle, Running, Done }
+///
+/// impl TrAtomicState for Phase {
+///     type Repr = u8;
+///
+///     fn into_repr(self) -> u8 {
+///         self as u8
+///     }
+///
+///     fn try_from_repr(repr: u8) -> Option<Self> {
+///         [Phase::Idle, Phase::Running, Phase::Done].get(repr as usize).copied()
+///     }
+///
+///     fn can_transit(from: Self, to: Self) -> bool {
+///         matches!(
+///             (from, to),
+///             (Phase::Idle, Phase::Running) | (Phase::Running, Phase::Done),
+///         )
+///     }
+/// }
+///
+/// let cell = StateCell::<Phase>::with_state(Phase::Idle);
+/// assert!(cell.transition(Phase::Idle, Phase::Done).is_unexpected());
+/// assert!(cell.transition(Phase::Idle, Phase::Running).is_succ());
+/// assert!(cell.transition(Phase::Idle, Phase::Running).is_unexpected());
+/// assert_eq!(cell.load(), Phase::Running);
+/// ```
+pub struct StateCell<
+    E,
+    B = <<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell,
+    O = StrictOrderings,
+>(AtomicFlags<<E as TrAtomicState>::Repr, B, O>)
+where
+    E: TrAtomicState,
+    <<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell:
+        TrAtomicCell<Value = <E as TrAtomicState>::Repr>,
+    B: BorrowMut<<<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings;
+
+impl<E, B, O> StateCell<E, B, O>
+where
+    E: TrAtomicState,
+    <<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell:
+        TrAtomicCell<Value = <E as TrAtomicState>::Repr>,
+    B: BorrowMut<<<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    pub const fn new(cell: B) -> Self {
+        StateCell(AtomicFlags::new(cell))
+    }
+
+    /// Loads the current state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell holds a representation that is not a valid state,
+    /// which can only happen if it was written bypassing the `StateCell`.
+    pub fn load(&self) -> E {
+        Self::decode(self.0.value())
+    }
+
+    /// Changes the state from `from` into `to`, if it is currently `from` and
+    /// the transition is allowed.
+    ///
+    /// Returns `Succ(from)` on success, `Unexpected(current)` if the current
+    /// state is not `from` or the transition is not allowed.
+    pub fn transition(&self, from: E, to: E) -> CmpxchResult<E> {
+        if !E::can_transit(from, to) {
+            return CmpxchResult::Unexpected(self.load());
+        }
+        let expected = from.into_repr();
+        let desired = to.into_repr();
+        self.0
+            .try_spin_compare_exchange_weak(|r| r == expected, |_| desired)
+            .map(Self::decode)
+    }
+
+    /// Changes the state into the one returned by `f` from the current state,
+    /// retrying as long as it is contended.
+    ///
+    /// Returns `Unexpected(current)` if `f` returns `None` or a state that is
+    /// not allowed to follow the current one.
+    pub fn transition_with<F>(&self, mut f: F) -> CmpxchResult<E>
+    where
+        F: FnMut(E) -> Option<E>,
+    {
+        let next = Cell::new(Option::None);
+        self.0
+            .try_spin_compare_exchange_weak(
+                |r| {
+                    let current = Self::decode(r);
+                    let to = f(current)
+                        .filter(|to| E::can_transit(current, *to));
+                    next.set(to);
+                    to.is_some()
+                },
+                |r| next.get().map_or(r, E::into_repr),
+            )
+            .map(Self::decode)
+    }
+
+    /// The underlying `AtomicFlags` over the state representation.
+    #[inline(always)]
+    pub fn as_flags(&self) -> &AtomicFlags<<E as TrAtomicState>::Repr, B, O> {
+        &self.0
+    }
+
+    fn decode(repr: <E as TrAtomicState>::Repr) -> E {
+        let Option::Some(state) = E::try_from_repr(repr) else {
+            panic!("[StateCell::decode] invalid state representation")
+        };
+        state
+    }
+}
+
+impl<E, O>
+StateCell<E, <<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell, O>
+where
+    E: TrAtomicState,
+    <<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell:
+        TrAtomicCell<Value = <E as TrAtomicState>::Repr>,
+    O: TrCmpxchOrderings,
+{
+    /// Create an owned instance in the initial state.
+    pub fn with_state(init: E) -> Self {
+        Self::new(TrAtomicCell::new(init.into_repr()))
+    }
+}
+
+impl<E, B, O> Debug for StateCell<E, B, O>
+where
+    E: TrAtomicState + Debug,
+    <<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell:
+        TrAtomicCell<Value = <E as TrAtomicState>::Repr>,
+    B: BorrowMut<<<E as TrAtomicState>::Repr as TrAtomicData>::AtomicCell>,
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StateCell").field(&self.load()).finish()
+    }
+}
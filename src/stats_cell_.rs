@@ -0,0 +1,139 @@
+﻿use core::{
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::{atomics_::AtomicU64, CachePadded};
+
+/// The number of times `StatsCell::snapshot` rereads the statistics while
+/// samples keep being recorded.
+const SNAPSHOT_RETRIES: usize = 4;
+
+/// The count, sum, minimum and maximum of samples recorded by many threads,
+/// such as latencies or request sizes.
+///
+/// Each statistic is a separate cache-padded atomic, so that recording never
+/// waits. A snapshot is consistent when no sample is recorded meanwhile, and
+/// otherwise may include part of the samples recorded concurrently, but
+/// never misses a sample counted.
+///
+/// # Example
+///
+/// ```
+/// use atomex::StatsCell;
+///
+/// let latency = StatsCell::new();
+/// for us in [120, 80, 400] {
+///     latency.record(us);
+/// }
+/// let s = latency.snapshot();
+/// assert_eq!((s.count, s.sum), (3, 600));
+/// assert_eq!((s.min, s.max), (Some(80), Some(400)));
+/// assert_eq!(s.mean(), Some(200));
+/// ```
+pub struct StatsCell {
+    count: CachePadded<AtomicU64>,
+    sum: CachePadded<AtomicU64>,
+    min: CachePadded<AtomicU64>,
+    max: CachePadded<AtomicU64>,
+}
+
+impl StatsCell {
+    pub const fn new() -> Self {
+        StatsCell {
+            count: CachePadded::new(AtomicU64::new(0)),
+            sum: CachePadded::new(AtomicU64::new(0)),
+            min: CachePadded::new(AtomicU64::new(u64::MAX)),
+            max: CachePadded::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Records `sample`. The sum wraps around on overflow.
+    pub fn record(&self, sample: u64) {
+        self.sum.fetch_add(sample, Ordering::Relaxed);
+        self.min.fetch_min(sample, Ordering::Relaxed);
+        self.max.fetch_max(sample, Ordering::Relaxed);
+        // Publishes the other statistics to the snapshots counting it.
+        self.count.fetch_add(1, Ordering::Release);
+    }
+
+    /// The number of samples recorded.
+    #[inline(always)]
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Reads the statistics, rereading them a few times if samples are
+    /// recorded meanwhile.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let mut count = self.count.load(Ordering::Acquire);
+        let mut retries = 0;
+        loop {
+            let sum = self.sum.load(Ordering::Relaxed);
+            let min = self.min.load(Ordering::Relaxed);
+            let max = self.max.load(Ordering::Relaxed);
+            let again = self.count.load(Ordering::Acquire);
+            retries += 1;
+            if again == count || retries == SNAPSHOT_RETRIES {
+                let any = count > 0;
+                return StatsSnapshot {
+                    count,
+                    sum,
+                    min: any.then_some(min),
+                    max: any.then_some(max),
+                };
+            }
+            count = again;
+        }
+    }
+
+    /// Forgets all the samples.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for StatsCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Creates a cell holding the statistics of `snapshot`, such as to carry on
+/// recording samples after a restart.
+impl From<StatsSnapshot> for StatsCell {
+    fn from(snapshot: StatsSnapshot) -> Self {
+        StatsCell {
+            count: CachePadded::new(AtomicU64::new(snapshot.count)),
+            sum: CachePadded::new(AtomicU64::new(snapshot.sum)),
+            min: CachePadded::new(AtomicU64::new(
+                snapshot.min.unwrap_or(u64::MAX),
+            )),
+            max: CachePadded::new(AtomicU64::new(snapshot.max.unwrap_or(0))),
+        }
+    }
+}
+
+impl fmt::Debug for StatsCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StatsCell").field(&self.snapshot()).finish()
+    }
+}
+
+/// The statistics read by `StatsCell::snapshot`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatsSnapshot {
+    pub count: u64,
+    pub sum: u64,
+    /// The smallest sample, `None` if none was recorded.
+    pub min: Option<u64>,
+    /// The largest sample, `None` if none was recorded.
+    pub max: Option<u64>,
+}
+
+impl StatsSnapshot {
+    /// The mean of the samples, rounded down, or `None` if there is none.
+    pub fn mean(&self) -> Option<u64> {
+        self.sum.checked_div(self.count)
+    }
+}
@@ -0,0 +1,312 @@
+﻿use core::{
+    cell::UnsafeCell,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::atomic::{fence, Ordering},
+};
+use crate::{
+    atomics_::AtomicU32,
+    fetch, AtomicFlags, LocksOrderings, NoBackoff,
+    TrAtomicCell, TrBackoff, TrCmpxchOrderings,
+};
+
+/// A ticket spin lock without data, i.e. only the locking protocol of
+/// `TicketLock`.
+///
+/// The word packs the next ticket to hand out in its high half and the
+/// ticket being served in its low half. Each locker takes a ticket and waits
+/// for it to be served, so the lock is acquired in FIFO order.
+///
+/// The lock is always acquired with `Acquire` and released with `Release`,
+/// whatever `O`, which only orders the loads observing the lock, such as
+/// `queue_len`.
+pub struct RawTicketLock<O = LocksOrderings>(AtomicFlags<u32, AtomicU32, O>)
+where
+    O: TrCmpxchOrderings;
+
+impl<O> RawTicketLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    const NEXT_ONE: u32 = 1 << 16;
+    const OWNER_MASK: u32 = 0xFFFF;
+
+    pub const fn new() -> Self {
+        RawTicketLock(AtomicFlags::new(AtomicU32::new(0)))
+    }
+
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        let (next, owner) = Self::split(self.0.value());
+        next != owner
+    }
+
+    /// The number of lockers holding or waiting for the lock.
+    #[inline(always)]
+    pub fn queue_len(&self) -> u16 {
+        let (next, owner) = Self::split(self.0.value());
+        next.wrapping_sub(owner)
+    }
+
+    /// Acquires the lock if it is free, returning whether it was acquired.
+    pub fn try_lock(&self) -> bool {
+        let current = self.0.value();
+        let (next, owner) = Self::split(current);
+        next == owner
+            && TrAtomicCell::compare_exchange(
+                self.0.as_ref(),
+                current,
+                current.wrapping_add(Self::NEXT_ONE),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    #[inline(always)]
+    pub fn lock(&self) {
+        self.lock_with_backoff(NoBackoff)
+    }
+
+    /// Takes a ticket and waits for it to be served, according to `backoff`.
+    pub fn lock_with_backoff(&self, mut backoff: impl TrBackoff) {
+        let prev = fetch::Add::fetch_add(
+            self.0.as_ref(),
+            Self::NEXT_ONE,
+            O::SWAP_ORDERING,
+        );
+        let (ticket, _) = Self::split(prev);
+        let _ = self.0.spin_until(|v| {
+            let (_, owner) = Self::split(v);
+            let served = owner == ticket;
+            if !served {
+                backoff.backoff();
+            }
+            served
+        });
+        // Synchronizes with the `Release` unlock serving the ticket.
+        fence(Ordering::Acquire);
+    }
+
+    /// Releases the lock, serving the next ticket.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held by the caller.
+    pub unsafe fn unlock(&self) {
+        // The owner half must wrap around without carrying into the next
+        // half, which rules out a plain `fetch_add`.
+        let _ = self.0.try_spin_compare_exchange_weak_with(
+            Ordering::Release,
+            Ordering::Relaxed,
+            |_| true,
+            |v| {
+                let owner = (v as u16).wrapping_add(1) as u32;
+                (v & !Self::OWNER_MASK) | owner
+            },
+        );
+    }
+
+    #[inline(always)]
+    fn split(v: u32) -> (u16, u16) {
+        ((v >> 16) as u16, v as u16)
+    }
+}
+
+impl<O> Default for RawTicketLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> fmt::Debug for RawTicketLock<O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (next, owner) = Self::split(self.0.value());
+        f.debug_struct("RawTicketLock")
+            .field("next", &next)
+            .field("owner", &owner)
+            .finish()
+    }
+}
+
+/// A ticket spin lock protecting a value of `T`, granting the lock in the
+/// order it was requested.
+///
+/// At most 65535 lockers may hold or wait for the lock at the same time.
+///
+/// # Example
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use atomex::TicketLock;
+///
+/// let lock = Arc::new(TicketLock::<Vec<usize>>::new(Vec::new()));
+/// let workers: Vec<_> = (0..4)
+///     .map(|i| {
+///         let lock = lock.clone();
+///         thread::spawn(move || lock.lock().push(i))
+///     })
+///     .collect();
+/// workers.into_iter().for_each(|w| w.join().unwrap());
+/// assert_eq!(lock.lock().len(), 4);
+/// assert!(!lock.is_locked());
+/// ```
+pub struct TicketLock<T: ?Sized, O = LocksOrderings>
+where
+    O: TrCmpxchOrderings,
+{
+    raw: RawTicketLock<O>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, O> Send for TicketLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+unsafe impl<T: ?Sized + Send, O> Sync for TicketLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+impl<T, O> TicketLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    pub const fn new(data: T) -> Self {
+        TicketLock {
+            raw: RawTicketLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, O> TicketLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    pub fn try_lock(&self) -> Option<TicketLockGuard<'_, T, O>> {
+        if self.raw.try_lock() {
+            Option::Some(TicketLockGuard::new(self))
+        } else {
+            Option::None
+        }
+    }
+
+    pub fn lock(&self) -> TicketLockGuard<'_, T, O> {
+        self.raw.lock();
+        TicketLockGuard::new(self)
+    }
+
+    pub fn lock_with_backoff(
+        &self,
+        backoff: impl TrBackoff,
+    ) -> TicketLockGuard<'_, T, O> {
+        self.raw.lock_with_backoff(backoff);
+        TicketLockGuard::new(self)
+    }
+
+    /// Returns a mutable reference to the value, which needs no locking
+    /// since the access is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default, O> Default for TicketLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, O> fmt::Debug for TicketLock<T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("TicketLock");
+        match self.try_lock() {
+            Option::Some(g) => d.field("data", &&*g),
+            Option::None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// The RAII guard of a `TicketLock`, serving the next ticket when dropped.
+pub struct TicketLockGuard<'a, T: ?Sized, O = LocksOrderings>
+where
+    O: TrCmpxchOrderings,
+{
+    lock: &'a TicketLock<T, O>,
+}
+
+unsafe impl<T: ?Sized + Sync, O> Sync for TicketLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{}
+
+impl<'a, T: ?Sized, O> TicketLockGuard<'a, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn new(lock: &'a TicketLock<T, O>) -> Self {
+        TicketLockGuard { lock }
+    }
+}
+
+impl<T: ?Sized, O> Deref for TicketLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, O> DerefMut for TicketLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, O> Drop for TicketLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn drop(&mut self) {
+        unsafe { self.lock.raw.unlock() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, O> fmt::Debug for TicketLockGuard<'_, T, O>
+where
+    O: TrCmpxchOrderings,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
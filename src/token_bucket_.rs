@@ -0,0 +1,133 @@
+﻿use core::{
+    fmt,
+    sync::atomic::Ordering,
+};
+use crate::atomics_::AtomicU64;
+
+/// A rate limiter holding up to `capacity` tokens, refilled by `amount`
+/// tokens every `interval` ticks of a clock provided by the caller.
+///
+/// The tokens and the time of the last refill are packed in a single
+/// `AtomicU64` updated with a CAS loop, so that it needs neither a lock nor a
+/// clock of its own. Only the low 32 bits of the clock are kept: the calls
+/// must be less than `2^31` ticks apart to refill correctly, and a clock
+/// going backwards refills nothing.
+///
+/// # Example
+///
+/// ```
+/// use atomex::TokenBucket;
+///
+/// // Bursts of 10, then 1 token every 100 ticks.
+/// let bucket = TokenBucket::new(10, 1, 100);
+/// assert!(bucket.try_acquire(10, 0));
+/// assert!(!bucket.try_acquire(1, 50));
+/// assert!(bucket.try_acquire(1, 150));
+/// assert!(!bucket.try_acquire(1, 199));
+/// assert_eq!(bucket.available(1_000), 9);
+/// ```
+pub struct TokenBucket {
+    /// The tokens in the low half, the time of the last refill in the high
+    /// half.
+    state: AtomicU64,
+    capacity: u32,
+    amount: u32,
+    interval: u32,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `amount` or `interval` is `0`, or if `interval` is not below
+    /// `2^31`.
+    pub const fn new(capacity: u32, amount: u32, interval: u32) -> Self {
+        assert!(amount > 0, "[TokenBucket::new] zero refill amount");
+        assert!(interval > 0, "[TokenBucket::new] zero refill interval");
+        assert!(
+            interval <= i32::MAX as u32,
+            "[TokenBucket::new] refill interval too long",
+        );
+        TokenBucket {
+            state: AtomicU64::new(Self::pack(capacity, 0)),
+            capacity,
+            amount,
+            interval,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Takes `n` tokens at time `now`, after refilling the bucket, and
+    /// returns whether there were enough.
+    pub fn try_acquire(&self, n: u32, now: u64) -> bool {
+        self.state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+                let (tokens, last) = self.refill(state, now);
+                let tokens = tokens.checked_sub(n)?;
+                Option::Some(Self::pack(tokens, last))
+            })
+            .is_ok()
+    }
+
+    /// The tokens the bucket would hold at time `now`.
+    pub fn available(&self, now: u64) -> u32 {
+        self.refill(self.state.load(Ordering::Acquire), now).0
+    }
+
+    /// Fills the bucket up again.
+    pub fn reset(&self, now: u64) {
+        let state = Self::pack(self.capacity, now as u32);
+        self.state.store(state, Ordering::Release);
+    }
+
+    /// The tokens and the time of the last refill at time `now`.
+    fn refill(&self, state: u64, now: u64) -> (u32, u32) {
+        let (tokens, last) = Self::unpack(state);
+        let now = now as u32;
+        if tokens >= self.capacity {
+            // A full bucket does not accumulate time.
+            return (tokens, now);
+        }
+        let elapsed = now.wrapping_sub(last);
+        if (elapsed as i32) <= 0 {
+            return (tokens, last);
+        }
+        let intervals = elapsed / self.interval;
+        let refilled = tokens as u64 + intervals as u64 * self.amount as u64;
+        if refilled >= self.capacity as u64 {
+            (self.capacity, now)
+        } else {
+            // Keeps the part of an interval elapsed since the last refill.
+            let last = last.wrapping_add(intervals * self.interval);
+            (refilled as u32, last)
+        }
+    }
+
+    #[inline(always)]
+    const fn pack(tokens: u32, last: u32) -> u64 {
+        tokens as u64 | (last as u64) << 32
+    }
+
+    #[inline(always)]
+    const fn unpack(state: u64) -> (u32, u32) {
+        (state as u32, (state >> 32) as u32)
+    }
+}
+
+impl fmt::Debug for TokenBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (tokens, last) = Self::unpack(self.state.load(Ordering::Relaxed));
+        f.debug_struct("TokenBucket")
+            .field("tokens", &tokens)
+            .field("last_refill", &last)
+            .field("capacity", &self.capacity)
+            .field("amount", &self.amount)
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
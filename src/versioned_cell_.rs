@@ -0,0 +1,119 @@
+﻿use core::{cell::UnsafeCell, fmt, mem::MaybeUninit, ptr};
+use crate::{relax, seq_count_::WriteGuard, SeqCount};
+
+/// A `Copy` value paired with a version that only grows, for state
+/// replicated with last-writer-wins semantics.
+///
+/// The value and its version are protected together by a sequence lock:
+/// readers copy both optimistically and retry if a writer was active, and a
+/// write carrying a version not newer than the current one is discarded
+/// without disturbing the readers.
+///
+/// # Example
+///
+/// ```
+/// use atomex::VersionedCell;
+///
+/// let leader = VersionedCell::new("a");
+/// assert_eq!(leader.load(), ("a", 0));
+/// assert!(leader.store_if_newer("b", 3));
+/// assert!(!leader.store_if_newer("c", 2));
+/// assert!(!leader.store_if_newer("c", 3));
+/// assert_eq!(leader.store("d"), 4);
+/// assert_eq!(leader.load(), ("d", 4));
+/// ```
+pub struct VersionedCell<T: Copy> {
+    seq: SeqCount,
+    data: UnsafeCell<(T, u32)>,
+}
+
+unsafe impl<T: Copy + Send> Send for VersionedCell<T> {}
+unsafe impl<T: Copy + Send> Sync for VersionedCell<T> {}
+
+impl<T: Copy> VersionedCell<T> {
+    /// Creates a cell holding `value` at version `0`.
+    pub const fn new(value: T) -> Self {
+        Self::with_version(value, 0)
+    }
+
+    pub const fn with_version(value: T, version: u32) -> Self {
+        VersionedCell {
+            seq: SeqCount::new(),
+            data: UnsafeCell::new((value, version)),
+        }
+    }
+
+    pub fn into_inner(self) -> (T, u32) {
+        self.data.into_inner()
+    }
+
+    /// Returns the value and its version.
+    pub fn load(&self) -> (T, u32) {
+        loop {
+            let s = self.seq.read_begin();
+            // The copy may race with a writer, so it is kept uninitialized,
+            // and discarded below without being looked at.
+            let data = unsafe {
+                ptr::read_volatile(self.data.get().cast::<MaybeUninit<_>>())
+            };
+            if !self.seq.read_retry(s) {
+                // Safety: no writer was active, so the copy is not torn.
+                break unsafe { data.assume_init() };
+            }
+            relax::relax();
+        }
+    }
+
+    #[inline(always)]
+    pub fn version(&self) -> u32 {
+        self.load().1
+    }
+
+    /// Stores `value` at `version` if it is newer than the current version.
+    ///
+    /// Returns `false`, leaving the cell unchanged, if it is not.
+    pub fn store_if_newer(&self, value: T, version: u32) -> bool {
+        let write = WriteGuard::begin(&self.seq);
+        // Writers are excluded by the sequence.
+        let current = unsafe { (*self.data.get()).1 };
+        if version <= current {
+            write.cancel();
+            return false;
+        }
+        unsafe { ptr::write_volatile(self.data.get(), (value, version)) };
+        true
+    }
+
+    /// Stores `value` at the version following the current one, and returns
+    /// that version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the version would overflow.
+    pub fn store(&self, value: T) -> u32 {
+        let _write = WriteGuard::begin(&self.seq);
+        // Writers are excluded by the sequence.
+        let current = unsafe { (*self.data.get()).1 };
+        let Option::Some(version) = current.checked_add(1) else {
+            panic!("[VersionedCell::store] version overflow")
+        };
+        unsafe { ptr::write_volatile(self.data.get(), (value, version)) };
+        version
+    }
+}
+
+impl<T: Copy + Default> Default for VersionedCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for VersionedCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (value, version) = self.load();
+        f.debug_struct("VersionedCell")
+            .field("value", &value)
+            .field("version", &version)
+            .finish()
+    }
+}
@@ -0,0 +1,37 @@
+﻿//! The one way the crate blocks a thread: sleeping on a 32-bit word until
+//! it is woken after the word changed.
+//!
+//! Every blocking wait, be it on a `Waiters` of a primitive or on the address
+//! of an atomic, sleeps through the `TrWait` backend of the platform, which is
+//! `futex` on Linux and `WaitOnAddress` on Windows under the `futex` feature,
+//! and a registry of parked threads otherwise.
+
+use crate::atomics_::AtomicU32;
+
+/// A way of putting threads to sleep on a word, in the manner of a futex.
+pub(crate) trait TrWait {
+    /// Blocks the thread as long as `word` holds `expected`, until woken by
+    /// `wake_all`. May also return spuriously.
+    fn wait(word: &AtomicU32, expected: u32);
+
+    /// Wakes up all the threads blocked in `wait` on `word`.
+    fn wake_all(word: &AtomicU32);
+}
+
+/// The backend of the target.
+#[cfg(all(
+    feature = "futex",
+    any(target_os = "linux", target_os = "android"),
+))]
+pub(crate) type Platform = crate::futex_::Futex;
+
+/// The backend of the target.
+#[cfg(all(feature = "futex", windows))]
+pub(crate) type Platform = crate::futex_::WaitOnAddress;
+
+/// The backend of the target.
+#[cfg(not(all(
+    feature = "futex",
+    any(target_os = "linux", target_os = "android", windows),
+)))]
+pub(crate) type Platform = crate::parker_::Parker;
@@ -0,0 +1,132 @@
+﻿use core::fmt;
+use crate::{
+    atomics_::AtomicUsize, waiters_::Waiters, AtomicCount, RefCountOrderings,
+    TrBackoff,
+};
+
+/// Waits for a group of tasks to finish, the count of running tasks being
+/// raised with `add` and lowered with `done`.
+///
+/// A `WaitGroupHandle` counts as one task for as long as it lives, and its
+/// clones as one more each, which saves pairing `add` and `done` by hand.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+/// use atomex::WaitGroup;
+///
+/// let wg = WaitGroup::new();
+/// thread::scope(|s| {
+///     let handle = wg.handle();
+///     for _ in 0..4 {
+///         let handle = handle.clone();
+///         s.spawn(move || drop(handle));
+///     }
+///     drop(handle);
+///     wg.wait();
+///     assert_eq!(wg.count(), 0);
+/// });
+/// ```
+pub struct WaitGroup {
+    count: AtomicCount<usize, AtomicUsize, RefCountOrderings>,
+    waiters: Waiters,
+}
+
+impl WaitGroup {
+    pub const fn new() -> Self {
+        WaitGroup {
+            count: AtomicCount::new(AtomicUsize::new(0)),
+            waiters: Waiters::new(),
+        }
+    }
+
+    /// The number of tasks not done yet.
+    #[inline(always)]
+    pub fn count(&self) -> usize {
+        self.count.val()
+    }
+
+    /// Adds `n` tasks to wait for.
+    ///
+    /// # Panics
+    ///
+    /// Panics under `debug_assertions` if the count overflows.
+    #[inline(always)]
+    pub fn add(&self, n: usize) {
+        let _ = self.count.add(n);
+    }
+
+    /// Marks one task as done, waking up the waiters if it was the last one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no task is left.
+    pub fn done(&self) {
+        match self.count.try_dec_nonzero() {
+            Result::Ok(1) => self.waiters.notify_all(),
+            Result::Ok(_) => (),
+            Result::Err(_) => panic!("[WaitGroup::done] no task left"),
+        }
+    }
+
+    /// Adds a task, which is done when the returned handle and all of its
+    /// clones are dropped.
+    pub fn handle(&self) -> WaitGroupHandle<'_> {
+        self.add(1);
+        WaitGroupHandle(self)
+    }
+
+    /// Waits until all the tasks are done.
+    ///
+    /// With the `std` feature, the thread sleeps until the task done last
+    /// wakes it up, be it by `done` or by dropping a handle. Without it, the
+    /// thread spins on the count.
+    pub fn wait(&self) {
+        self.waiters.wait_until(|| self.count() == 0)
+    }
+
+    /// Spins until all the tasks are done, waiting according to `backoff`
+    /// between two checks.
+    pub fn wait_with_backoff(&self, mut backoff: impl TrBackoff) {
+        while self.count() != 0 {
+            backoff.backoff();
+        }
+    }
+}
+
+impl Default for WaitGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitGroup")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+/// A task of a `WaitGroup`, done when dropped.
+#[must_use = "the task is done at once if the handle is not held"]
+pub struct WaitGroupHandle<'a>(&'a WaitGroup);
+
+impl Clone for WaitGroupHandle<'_> {
+    fn clone(&self) -> Self {
+        self.0.handle()
+    }
+}
+
+impl Drop for WaitGroupHandle<'_> {
+    fn drop(&mut self) {
+        self.0.done()
+    }
+}
+
+impl fmt::Debug for WaitGroupHandle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WaitGroupHandle").field(self.0).finish()
+    }
+}
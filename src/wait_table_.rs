@@ -0,0 +1,271 @@
+﻿use core::{
+    cell::UnsafeCell,
+    future::Future,
+    marker::PhantomPinned,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    ptr,
+    sync::atomic::{fence, Ordering},
+    task::{Context, Poll, Waker},
+};
+use crate::{
+    atomics_::{AtomicBool, AtomicUsize},
+    relax,
+    CachePadded,
+};
+
+const BUCKET_BITS: u32 = 6;
+
+/// The number of waiters linked in the whole table, which spares the
+/// notifiers the bucket probe, and their modifications made `SeqCst` the
+/// fence, as long as no task waits at all.
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// The waiters of the atomics whose addresses hash to the same bucket.
+struct Bucket {
+    /// The number of waiters linked, checked by notifiers before locking.
+    waiters: AtomicUsize,
+
+    // Not a `SpinLock`, whose updates would notify the table recursively.
+    locked: AtomicBool,
+    list: UnsafeCell<WaitList>,
+}
+
+// The list is only accessed under the lock.
+unsafe impl Sync for Bucket {}
+
+struct WaitList {
+    head: *mut WaitNode,
+    tail: *mut WaitNode,
+}
+
+struct WaitNode {
+    addr: usize,
+    waker: Option<Waker>,
+    prev: *mut WaitNode,
+    next: *mut WaitNode,
+    linked: bool,
+}
+
+static TABLE: [CachePadded<Bucket>; 1 << BUCKET_BITS] = [const {
+    CachePadded::new(Bucket {
+        waiters: AtomicUsize::new(0),
+        locked: AtomicBool::new(false),
+        list: UnsafeCell::new(WaitList {
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+        }),
+    })
+}; 1 << BUCKET_BITS];
+
+fn bucket_of(addr: usize) -> &'static Bucket {
+    // Fibonacci hashing, keeping the upper bits which are the best mixed.
+    let h = addr.wrapping_mul(0x9E37_79B9_7F4A_7C15_u64 as usize);
+    &TABLE[h >> (usize::BITS - BUCKET_BITS)]
+}
+
+impl Bucket {
+    fn lock(&self) -> BucketGuard<'_> {
+        while self
+            .locked
+            .compare_exchange_weak(
+                false,
+                true,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            relax::relax();
+        }
+        BucketGuard(self)
+    }
+}
+
+struct BucketGuard<'a>(&'a Bucket);
+
+impl Deref for BucketGuard<'_> {
+    type Target = WaitList;
+
+    fn deref(&self) -> &WaitList {
+        unsafe { &*self.0.list.get() }
+    }
+}
+
+impl DerefMut for BucketGuard<'_> {
+    fn deref_mut(&mut self) -> &mut WaitList {
+        unsafe { &mut *self.0.list.get() }
+    }
+}
+
+impl Drop for BucketGuard<'_> {
+    fn drop(&mut self) {
+        self.0.locked.store(false, Ordering::Release)
+    }
+}
+
+impl WaitList {
+    unsafe fn push_back(&mut self, node: *mut WaitNode) {
+        let n = unsafe { &mut *node };
+        n.prev = self.tail;
+        n.next = ptr::null_mut();
+        n.linked = true;
+        match unsafe { self.tail.as_mut() } {
+            Option::Some(tail) => tail.next = node,
+            Option::None => self.head = node,
+        }
+        self.tail = node;
+    }
+
+    unsafe fn remove(&mut self, node: *mut WaitNode) {
+        let n = unsafe { &mut *node };
+        match unsafe { n.prev.as_mut() } {
+            Option::Some(prev) => prev.next = n.next,
+            Option::None => self.head = n.next,
+        }
+        match unsafe { n.next.as_mut() } {
+            Option::Some(next) => next.prev = n.prev,
+            Option::None => self.tail = n.prev,
+        }
+        n.linked = false;
+    }
+
+    /// Unlinks the first node waiting on `addr`, and takes its waker.
+    fn pop(&mut self, addr: usize) -> Option<Option<Waker>> {
+        let mut p = self.head;
+        while !p.is_null() {
+            if unsafe { (*p).addr } == addr {
+                unsafe { self.remove(p) };
+                return Option::Some(unsafe { (*p).waker.take() });
+            }
+            p = unsafe { (*p).next };
+        }
+        Option::None
+    }
+}
+
+/// Wakes up all the tasks waiting on the atomic at `addr`.
+///
+/// Must be called after the atomic was modified with `order`, so that the
+/// waiters which registered too late to be woken see the modification.
+pub(crate) fn notify(addr: usize, order: Ordering) {
+    // Pairs with the fence of `Wait::poll`: either the waiter sees the
+    // modification, or we see the waiter. A `SeqCst` modification is already
+    // ordered before the `SeqCst` load below.
+    if order != Ordering::SeqCst {
+        fence(Ordering::SeqCst);
+    }
+    if WAITING.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+    // The count of the bucket was raised before `WAITING`, so it is seen by
+    // now if the waiter is.
+    let bucket = bucket_of(addr);
+    if bucket.waiters.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    loop {
+        // The wakers are woken out of the lock, since waking may poll.
+        let waker = {
+            let mut list = bucket.lock();
+            let Option::Some(w) = list.pop(addr) else { break };
+            bucket.waiters.fetch_sub(1, Ordering::Relaxed);
+            WAITING.fetch_sub(1, Ordering::Relaxed);
+            w
+        };
+        if let Option::Some(w) = waker {
+            w.wake();
+        }
+    }
+}
+
+/// A future resolved with the first `Some` returned by `check`, which is
+/// tried again every time the atomic at `addr` is notified.
+pub(crate) struct Wait<F> {
+    addr: usize,
+    check: F,
+    node: UnsafeCell<WaitNode>,
+    registered: bool,
+    _pinned: PhantomPinned,
+}
+
+// The node is only shared with the other tasks through the bucket lock.
+unsafe impl<F: Send> Send for Wait<F> {}
+unsafe impl<F: Sync> Sync for Wait<F> {}
+
+impl<F> Wait<F> {
+    pub const fn new(addr: usize, check: F) -> Self {
+        Wait {
+            addr,
+            check,
+            node: UnsafeCell::new(WaitNode {
+                addr,
+                waker: Option::None,
+                prev: ptr::null_mut(),
+                next: ptr::null_mut(),
+                linked: false,
+            }),
+            registered: false,
+            _pinned: PhantomPinned,
+        }
+    }
+
+    fn unregister(&mut self) {
+        if !self.registered {
+            return;
+        }
+        self.registered = false;
+        let bucket = bucket_of(self.addr);
+        let mut list = bucket.lock();
+        let node = self.node.get();
+        if unsafe { (*node).linked } {
+            unsafe { list.remove(node) };
+            bucket.waiters.fetch_sub(1, Ordering::Relaxed);
+            WAITING.fetch_sub(1, Ordering::Relaxed);
+        }
+        unsafe { (*node).waker = Option::None };
+    }
+}
+
+impl<F, R> Future for Wait<F>
+where
+    F: FnMut() -> Option<R>,
+{
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        // The node is not moved out, and stays in place until dropped.
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Option::Some(r) = (this.check)() {
+            this.unregister();
+            return Poll::Ready(r);
+        }
+        let bucket = bucket_of(this.addr);
+        {
+            let mut list = bucket.lock();
+            let node = unsafe { &mut *this.node.get() };
+            match &node.waker {
+                Option::Some(w) if w.will_wake(cx.waker()) => (),
+                _ => node.waker = Option::Some(cx.waker().clone()),
+            }
+            if !node.linked {
+                bucket.waiters.fetch_add(1, Ordering::Relaxed);
+                WAITING.fetch_add(1, Ordering::SeqCst);
+                unsafe { list.push_back(node) };
+                this.registered = true;
+            }
+        }
+        fence(Ordering::SeqCst);
+        if let Option::Some(r) = (this.check)() {
+            this.unregister();
+            return Poll::Ready(r);
+        }
+        Poll::Pending
+    }
+}
+
+impl<F> Drop for Wait<F> {
+    fn drop(&mut self) {
+        self.unregister()
+    }
+}
@@ -0,0 +1,68 @@
+﻿#[cfg(feature = "std")]
+use core::sync::atomic::{fence, Ordering};
+#[cfg(feature = "std")]
+use crate::{
+    atomics_::{AtomicU32, AtomicUsize},
+    wait_::{Platform, TrWait},
+};
+
+/// The waiting side shared by the blocking primitives: blocks the waiting
+/// threads on the `TrWait` backend of the platform under the `std` feature,
+/// and spins otherwise.
+///
+/// Whoever makes a waited condition true must call `notify_all` afterwards.
+pub(crate) struct Waiters {
+    /// Bumped at every notification, for the waiters to sleep on.
+    #[cfg(feature = "std")]
+    epoch: AtomicU32,
+    /// The number of waiters about to sleep or sleeping, without which the
+    /// notifications skip the wake-up.
+    #[cfg(feature = "std")]
+    sleepers: AtomicUsize,
+}
+
+impl Waiters {
+    pub const fn new() -> Self {
+        Waiters {
+            #[cfg(feature = "std")]
+            epoch: AtomicU32::new(0),
+            #[cfg(feature = "std")]
+            sleepers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits until `ready` returns `true`.
+    pub fn wait_until(&self, mut ready: impl FnMut() -> bool) {
+        #[cfg(feature = "std")]
+        while !ready() {
+            // The epoch is loaded before checking `ready` again, so that a
+            // notification in between makes the wait return at once.
+            let epoch = self.epoch.load(Ordering::Acquire);
+            self.sleepers.fetch_add(1, Ordering::Relaxed);
+            // Pairs with the fence of `notify_all`: either we see the
+            // condition, or the notifier sees us and bumps the epoch.
+            fence(Ordering::SeqCst);
+            if !ready() {
+                Platform::wait(&self.epoch, epoch);
+            }
+            self.sleepers.fetch_sub(1, Ordering::Relaxed);
+        }
+        #[cfg(not(feature = "std"))]
+        while !ready() {
+            crate::relax::relax();
+        }
+    }
+
+    /// Wakes up all the waiters to check their conditions again.
+    pub fn notify_all(&self) {
+        #[cfg(feature = "std")]
+        {
+            fence(Ordering::SeqCst);
+            if self.sleepers.load(Ordering::Relaxed) == 0 {
+                return;
+            }
+            self.epoch.fetch_add(1, Ordering::Release);
+            Platform::wake_all(&self.epoch);
+        }
+    }
+}